@@ -24,7 +24,7 @@ export const TaskItem = ({ task }: { task: Task }) => {
 
   const deleteTask = () => {
     if (!rep) return;
-    rep.mutate.deleteTask(task.id);
+    rep.mutate.deleteTask({ id: task.id });
   };
 
   const setTaskCompleted = (completed: boolean) => {
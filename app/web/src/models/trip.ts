@@ -10,6 +10,7 @@ export const tripSchema = z.object({
   startDate: z.string().optional(),
   endDate: z.string().optional(),
   coverImage: z.string().optional(),
+  archivedAt: z.string().optional(),
   createdAt: z.string(),
   updatedAt: z.string(),
 });
@@ -27,17 +27,26 @@ import {
 } from './models/itineraryItem';
 import { createTask, deleteTask, getTasksByTrip, TaskCreate, updateTask } from './models/tasks';
 import { createTrip, deleteTrip, TripCreate, updateTrip } from './models/trip';
+import { DeleteArgs } from './types/mutators';
 import { getNextPosition } from './utils/positioning';
 
+// Has to match `MUTATOR_VERSION` in server/src/replicache/mutators.rs, the server upgrades
+// mutations queued by older versions.
+export const MUTATOR_VERSION = 3;
+
 export type Mutators = typeof mutators;
 
+const deleteById =
+  (del: (tx: WriteTransaction, id: string) => Promise<void>) =>
+  (tx: WriteTransaction, { id }: DeleteArgs) =>
+    del(tx, id);
+
 export const mutators = {
   updateTrip,
-  createCollaborator,
-  deleteCollaborator,
+  deleteCollaborator: deleteById(deleteCollaborator),
   updateCollaborator,
   createItineraryItem,
-  deleteItineraryItem,
+  deleteItineraryItem: deleteById(deleteItineraryItem),
   updateItineraryItem: async (
     tx: WriteTransaction,
     args: { itineraryItem: ItineraryItemUpdate; tripId: string }
@@ -51,7 +60,7 @@ export const mutators = {
     });
   },
   createExpense,
-  deleteExpense,
+  deleteExpense: deleteById(deleteExpense),
   updateExpense: async (tx: WriteTransaction, args: { expense: ExpenseUpdate; tripId: string }) => {
     const { expense, tripId } = args;
     await updateExpense(tx, expense);
@@ -62,7 +71,7 @@ export const mutators = {
     });
   },
   createExpensePayer,
-  deleteExpensePayer,
+  deleteExpensePayer: deleteById(deleteExpensePayer),
   createTask: async (tx: WriteTransaction, task: TaskCreate) => {
     const tripId = task.tripId;
     const tasks = await getTasksByTrip(tx, tripId);
@@ -77,11 +86,15 @@ export const mutators = {
     });
   },
   updateTask,
-  deleteTask,
-  createTrip: async (tx: WriteTransaction, args: { trip: TripCreate; user: User }) => {
+  deleteTask: deleteById(deleteTask),
+  createTrip: async (
+    tx: WriteTransaction,
+    args: { trip: TripCreate & { id: string }; user: User }
+  ) => {
     const { trip, user } = args;
     let collaboratorId = nanoid();
-    let tripId = nanoid();
+    // the server creates the trip with this ID, so mutations queued before the next pull match
+    let tripId = trip.id;
 
     await createCollaborator(tx, {
       id: collaboratorId,
@@ -102,7 +115,7 @@ export const mutators = {
       updatedAt: new Date().toISOString(),
     });
   },
-  deleteTrip: async (tx: WriteTransaction, tripId: string) => {
+  deleteTrip: async (tx: WriteTransaction, { id: tripId }: DeleteArgs) => {
     const collaborators = await getCollaboratorsByTrip(tx, tripId);
 
     await Promise.all(collaborators.map((collaborator) => deleteCollaborator(tx, collaborator.id)));
@@ -1,4 +1,5 @@
 import { createContext, useContext } from 'react';
+import { nanoid } from 'nanoid';
 import { useSubscribe } from 'replicache-react';
 import { listAllTripsSortedByUpdatedAt, Trip, TripCreate } from '@/models/trip';
 import { useEventSourcePoke } from '@/utils/poke';
@@ -33,7 +34,7 @@ export const AllTripsProvider: React.FC<{ children: React.ReactNode }> = ({ chil
       throw new Error('User ID and Replicache and user are required');
     }
     await rep.mutate.createTrip({
-      trip,
+      trip: { ...trip, id: nanoid() },
       user: {
         id: user.id,
         username: user.username,
@@ -47,7 +48,7 @@ export const AllTripsProvider: React.FC<{ children: React.ReactNode }> = ({ chil
     if (!rep) return;
 
     for (const trip of trips) {
-      await rep.mutate.deleteTrip(trip.id);
+      await rep.mutate.deleteTrip({ id: trip.id });
     }
   };
 
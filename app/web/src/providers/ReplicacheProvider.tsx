@@ -1,6 +1,6 @@
 import { createContext, ReactNode, useContext, useMemo } from 'react';
 import { Replicache } from 'replicache';
-import { Mutators, mutators } from '../mutators';
+import { Mutators, MUTATOR_VERSION, mutators } from '../mutators';
 import { useAuth } from './AuthProvider';
 
 const createReplicacheClient = (userId?: string | null) => {
@@ -16,6 +16,7 @@ const createReplicacheClient = (userId?: string | null) => {
     pushURL,
     pullURL,
     mutators,
+    schemaVersion: String(MUTATOR_VERSION),
   });
 };
 
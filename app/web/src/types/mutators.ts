@@ -0,0 +1,147 @@
+/*
+ Generated by typeshare 1.13.4
+*/
+
+/**
+ * What a collaborator may do on a trip, stored in `user_trip.permission`. Roles are ordered,
+ * each one can do everything the ones before it can.
+ */
+export enum Role {
+	/** Can see the trip */
+	Viewer = "viewer",
+	/** Can change the trip and invite people */
+	Editor = "editor",
+	/** Can delete the trip and hand it over, there's exactly one per trip */
+	Owner = "owner",
+}
+
+export interface CollaboratorUpdate {
+	id: string;
+	role?: Role;
+}
+
+export interface TripCreate {
+	id: string;
+	name?: string;
+	description?: string;
+	startDate?: string;
+	endDate?: string;
+	coverImage?: string;
+}
+
+export interface CreateTripArgs {
+	trip: TripCreate;
+}
+
+/** Arguments of every `delete*` mutator. */
+export interface DeleteArgs {
+	id: string;
+}
+
+export interface ExpenseCreate {
+	id: string;
+	tripId: string;
+	name?: string;
+	description?: string;
+	amount: number;
+	currency: string;
+	category?: string;
+}
+
+export interface ExpensePayerCreate {
+	id: string;
+	expenseId: string;
+	collaboratorId: string;
+}
+
+export interface ExpenseUpdate {
+	id: string;
+	name?: string;
+	description?: string;
+	amount?: number;
+	currency?: string;
+	category?: string;
+}
+
+export interface ItineraryItemCreate {
+	id: string;
+	tripId: string;
+	name: string;
+	description?: string;
+	startDateTime: string;
+	endDateTime?: string;
+	allDay?: boolean;
+	location?: string;
+	notes?: string;
+	expenseId?: string;
+}
+
+export interface ItineraryItemUpdate {
+	id: string;
+	name?: string;
+	description?: string;
+	startDateTime?: string;
+	endDateTime?: string;
+	allDay?: boolean;
+	location?: string;
+	notes?: string;
+	expenseId?: string;
+}
+
+export interface TaskCreate {
+	id?: string;
+	tripId: string;
+	title: string;
+	description?: string;
+	completed?: boolean;
+	urgency?: string;
+}
+
+export interface TaskUpdate {
+	id: string;
+	title?: string;
+	description?: string;
+	completed?: boolean;
+	position?: string;
+	urgency?: string;
+}
+
+export interface TripUpdate {
+	id: string;
+	name?: string;
+	description?: string;
+	startDate?: string;
+	endDate?: string;
+	coverImage?: string;
+}
+
+export interface UpdateExpenseArgs {
+	expense: ExpenseUpdate;
+}
+
+export interface UpdateItineraryItemArgs {
+	itineraryItem: ItineraryItemUpdate;
+}
+
+/**
+ * Every mutator the server can replay. Mutator names and argument shapes mirror
+ * `app/web/src/mutators.tsx`, the TypeScript types are generated from these with typeshare.
+ */
+export type Mutator = 
+	| { name: "createTrip", args: CreateTripArgs }
+	| { name: "updateTrip", args: TripUpdate }
+	| { name: "deleteTrip", args: DeleteArgs }
+	| { name: "updateCollaborator", args: CollaboratorUpdate }
+	| { name: "deleteCollaborator", args: DeleteArgs }
+	| { name: "createItineraryItem", args: ItineraryItemCreate }
+	| { name: "updateItineraryItem", args: UpdateItineraryItemArgs }
+	| { name: "deleteItineraryItem", args: DeleteArgs }
+	| { name: "createExpense", args: ExpenseCreate }
+	| { name: "updateExpense", args: UpdateExpenseArgs }
+	| { name: "deleteExpense", args: DeleteArgs }
+	| { name: "createExpensePayer", args: ExpensePayerCreate }
+	| { name: "deleteExpensePayer", args: DeleteArgs }
+	| { name: "createTask", args: TaskCreate }
+	| { name: "updateTask", args: TaskUpdate }
+	| { name: "deleteTask", args: DeleteArgs };
+
@@ -1,15 +1,19 @@
 import { useEffect } from 'react';
 import { Replicache } from 'replicache';
 import { Mutators } from '@/mutators';
+import { useAuth } from '@/providers/AuthProvider';
 
 export function useEventSourcePoke(url: string, rep: Replicache<Mutators> | null) {
+  const { accessToken } = useAuth();
+
   useEffect(() => {
-    if (!rep || !url) return;
+    if (!rep || !url || !accessToken) return;
 
-    const eventSource = new EventSource(url);
+    // EventSource can't send an Authorization header, so the token goes in the query
+    const eventSource = new EventSource(`${url}&token=${encodeURIComponent(accessToken)}`);
     eventSource.onmessage = () => {
       void rep.pull();
     };
     return () => eventSource.close();
-  }, [url, rep]);
+  }, [url, rep, accessToken]);
 }
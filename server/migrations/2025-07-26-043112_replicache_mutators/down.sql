@@ -0,0 +1,22 @@
+ALTER TABLE tasks
+  DROP COLUMN title,
+  DROP COLUMN position,
+  DROP COLUMN urgency;
+
+ALTER TABLE itinerary_items
+  DROP COLUMN description,
+  DROP COLUMN all_day;
+
+ALTER TABLE expense_payers
+  DROP COLUMN id;
+
+ALTER TABLE expenses
+  DROP COLUMN description,
+  DROP COLUMN category;
+
+ALTER TABLE user_trip
+  DROP COLUMN id;
+
+ALTER TABLE trips
+  DROP COLUMN description,
+  DROP COLUMN updated_at;
@@ -0,0 +1,22 @@
+ALTER TABLE trips
+  ADD COLUMN description TEXT,
+  ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+ALTER TABLE user_trip
+  ADD COLUMN id UUID UNIQUE NOT NULL DEFAULT gen_random_uuid();
+
+ALTER TABLE expenses
+  ADD COLUMN description TEXT,
+  ADD COLUMN category TEXT;
+
+ALTER TABLE expense_payers
+  ADD COLUMN id UUID UNIQUE NOT NULL DEFAULT gen_random_uuid();
+
+ALTER TABLE itinerary_items
+  ADD COLUMN description TEXT,
+  ADD COLUMN all_day BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE tasks
+  ADD COLUMN title TEXT NOT NULL DEFAULT '',
+  ADD COLUMN position TEXT NOT NULL DEFAULT 'a',
+  ADD COLUMN urgency TEXT NOT NULL DEFAULT 'low';
@@ -0,0 +1,27 @@
+DROP TRIGGER IF EXISTS tasks_tombstone ON tasks;
+DROP TRIGGER IF EXISTS tasks_version ON tasks;
+DROP TRIGGER IF EXISTS expense_payers_tombstone ON expense_payers;
+DROP TRIGGER IF EXISTS expense_payers_version ON expense_payers;
+DROP TRIGGER IF EXISTS expenses_tombstone ON expenses;
+DROP TRIGGER IF EXISTS expenses_version ON expenses;
+DROP TRIGGER IF EXISTS itinerary_items_tombstone ON itinerary_items;
+DROP TRIGGER IF EXISTS itinerary_items_version ON itinerary_items;
+DROP TRIGGER IF EXISTS user_trip_tombstone ON user_trip;
+DROP TRIGGER IF EXISTS user_trip_version ON user_trip;
+DROP TRIGGER IF EXISTS trips_tombstone ON trips;
+DROP TRIGGER IF EXISTS trips_version ON trips;
+
+DROP FUNCTION IF EXISTS replicache_record_tombstone();
+DROP FUNCTION IF EXISTS replicache_stamp_version();
+DROP FUNCTION IF EXISTS replicache_next_version();
+
+DROP TABLE IF EXISTS replicache_tombstones;
+
+ALTER TABLE expense_payers DROP COLUMN trip_id;
+
+ALTER TABLE tasks DROP COLUMN version;
+ALTER TABLE expense_payers DROP COLUMN version;
+ALTER TABLE expenses DROP COLUMN version;
+ALTER TABLE itinerary_items DROP COLUMN version;
+ALTER TABLE user_trip DROP COLUMN version;
+ALTER TABLE trips DROP COLUMN version;
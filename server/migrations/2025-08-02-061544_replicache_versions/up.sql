@@ -0,0 +1,90 @@
+ALTER TABLE trips ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE user_trip ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE itinerary_items ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE expenses ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE expense_payers ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tasks ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+
+-- payers need their trip so a tombstone can still be attributed once the expense is gone
+ALTER TABLE expense_payers ADD COLUMN trip_id UUID REFERENCES trips(id) ON DELETE CASCADE;
+UPDATE expense_payers
+  SET trip_id = expenses.trip_id
+  FROM expenses
+  WHERE expenses.id = expense_payers.expense_id;
+ALTER TABLE expense_payers ALTER COLUMN trip_id SET NOT NULL;
+
+CREATE TABLE replicache_tombstones (
+  key TEXT PRIMARY KEY,
+  trip_id UUID NOT NULL,
+  version INTEGER NOT NULL
+);
+
+CREATE INDEX replicache_tombstones_version_idx ON replicache_tombstones (version);
+
+-- Hands out the next version of the sync space. The row lock taken here is held until the
+-- transaction commits, so versions become visible in order.
+CREATE FUNCTION replicache_next_version() RETURNS INTEGER AS $$
+DECLARE
+  next_version INTEGER;
+BEGIN
+  INSERT INTO replicache_space (id, version) VALUES ('default', 1)
+  ON CONFLICT (id) DO UPDATE SET version = replicache_space.version + 1
+  RETURNING version INTO next_version;
+
+  RETURN next_version;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION replicache_stamp_version() RETURNS TRIGGER AS $$
+BEGIN
+  NEW.version := replicache_next_version();
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- TG_ARGV[0] is the Replicache key prefix of the table, e.g. 'trip'.
+CREATE FUNCTION replicache_record_tombstone() RETURNS TRIGGER AS $$
+DECLARE
+  old_row JSONB := to_jsonb(OLD);
+BEGIN
+  INSERT INTO replicache_tombstones (key, trip_id, version)
+  VALUES (
+    TG_ARGV[0] || '/' || (old_row ->> 'id'),
+    COALESCE(old_row ->> 'trip_id', old_row ->> 'id')::UUID,
+    replicache_next_version()
+  )
+  ON CONFLICT (key) DO UPDATE SET version = EXCLUDED.version;
+
+  RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trips_version BEFORE INSERT OR UPDATE ON trips
+  FOR EACH ROW EXECUTE FUNCTION replicache_stamp_version();
+CREATE TRIGGER trips_tombstone AFTER DELETE ON trips
+  FOR EACH ROW EXECUTE FUNCTION replicache_record_tombstone('trip');
+
+CREATE TRIGGER user_trip_version BEFORE INSERT OR UPDATE ON user_trip
+  FOR EACH ROW EXECUTE FUNCTION replicache_stamp_version();
+CREATE TRIGGER user_trip_tombstone AFTER DELETE ON user_trip
+  FOR EACH ROW EXECUTE FUNCTION replicache_record_tombstone('collaborator');
+
+CREATE TRIGGER itinerary_items_version BEFORE INSERT OR UPDATE ON itinerary_items
+  FOR EACH ROW EXECUTE FUNCTION replicache_stamp_version();
+CREATE TRIGGER itinerary_items_tombstone AFTER DELETE ON itinerary_items
+  FOR EACH ROW EXECUTE FUNCTION replicache_record_tombstone('itineraryItem');
+
+CREATE TRIGGER expenses_version BEFORE INSERT OR UPDATE ON expenses
+  FOR EACH ROW EXECUTE FUNCTION replicache_stamp_version();
+CREATE TRIGGER expenses_tombstone AFTER DELETE ON expenses
+  FOR EACH ROW EXECUTE FUNCTION replicache_record_tombstone('expense');
+
+CREATE TRIGGER expense_payers_version BEFORE INSERT OR UPDATE ON expense_payers
+  FOR EACH ROW EXECUTE FUNCTION replicache_stamp_version();
+CREATE TRIGGER expense_payers_tombstone AFTER DELETE ON expense_payers
+  FOR EACH ROW EXECUTE FUNCTION replicache_record_tombstone('expensePayer');
+
+CREATE TRIGGER tasks_version BEFORE INSERT OR UPDATE ON tasks
+  FOR EACH ROW EXECUTE FUNCTION replicache_stamp_version();
+CREATE TRIGGER tasks_tombstone AFTER DELETE ON tasks
+  FOR EACH ROW EXECUTE FUNCTION replicache_record_tombstone('task');
@@ -0,0 +1,47 @@
+DROP INDEX IF EXISTS replicache_tombstones_trip_id_version_idx;
+CREATE INDEX replicache_tombstones_version_idx ON replicache_tombstones (version);
+
+INSERT INTO replicache_space (id, version)
+  SELECT 'default', COALESCE(MAX(version), 0) FROM replicache_space
+ON CONFLICT (id) DO NOTHING;
+
+DELETE FROM replicache_space WHERE id <> 'default';
+
+UPDATE replicache_client_group SET space_id = 'default';
+
+CREATE FUNCTION replicache_next_version() RETURNS INTEGER AS $$
+DECLARE
+  next_version INTEGER;
+BEGIN
+  INSERT INTO replicache_space (id, version) VALUES ('default', 1)
+  ON CONFLICT (id) DO UPDATE SET version = replicache_space.version + 1
+  RETURNING version INTO next_version;
+
+  RETURN next_version;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION replicache_stamp_version() RETURNS TRIGGER AS $$
+BEGIN
+  NEW.version := replicache_next_version();
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION replicache_record_tombstone() RETURNS TRIGGER AS $$
+DECLARE
+  old_row JSONB := to_jsonb(OLD);
+BEGIN
+  INSERT INTO replicache_tombstones (key, trip_id, version)
+  VALUES (
+    TG_ARGV[0] || '/' || (old_row ->> 'id'),
+    COALESCE(old_row ->> 'trip_id', old_row ->> 'id')::UUID,
+    replicache_next_version()
+  )
+  ON CONFLICT (key) DO UPDATE SET version = EXCLUDED.version;
+
+  RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP FUNCTION IF EXISTS replicache_next_version(TEXT);
@@ -0,0 +1,60 @@
+-- Every trip syncs as its own space, keyed by the trip ID, so rows are versioned per trip and a
+-- pull only has to look at the trips the user is on. Client groups get a space per user, which
+-- versions the last mutation IDs of their clients.
+CREATE FUNCTION replicache_next_version(space TEXT) RETURNS INTEGER AS $$
+DECLARE
+  next_version INTEGER;
+BEGIN
+  INSERT INTO replicache_space (id, version) VALUES (space, 1)
+  ON CONFLICT (id) DO UPDATE SET version = replicache_space.version + 1
+  RETURNING version INTO next_version;
+
+  RETURN next_version;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION replicache_stamp_version() RETURNS TRIGGER AS $$
+DECLARE
+  new_row JSONB := to_jsonb(NEW);
+BEGIN
+  NEW.version := replicache_next_version(COALESCE(new_row ->> 'trip_id', new_row ->> 'id'));
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION replicache_record_tombstone() RETURNS TRIGGER AS $$
+DECLARE
+  old_row JSONB := to_jsonb(OLD);
+  space TEXT := COALESCE(old_row ->> 'trip_id', old_row ->> 'id');
+BEGIN
+  INSERT INTO replicache_tombstones (key, trip_id, version)
+  VALUES (
+    TG_ARGV[0] || '/' || (old_row ->> 'id'),
+    space::UUID,
+    replicache_next_version(space)
+  )
+  ON CONFLICT (key) DO UPDATE
+    SET trip_id = EXCLUDED.trip_id, version = EXCLUDED.version;
+
+  RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP FUNCTION replicache_next_version();
+
+-- rows were stamped from the shared counter so far, new spaces have to start past it
+INSERT INTO replicache_space (id, version)
+  SELECT space_id, COALESCE((SELECT version FROM replicache_space WHERE id = 'default'), 0)
+  FROM (
+    SELECT id::TEXT AS space_id FROM trips
+    UNION SELECT trip_id::TEXT FROM replicache_tombstones
+    UNION SELECT 'user/' || user_id FROM replicache_client_group
+  ) AS spaces
+ON CONFLICT (id) DO NOTHING;
+
+UPDATE replicache_client_group SET space_id = 'user/' || user_id;
+
+DELETE FROM replicache_space WHERE id = 'default';
+
+DROP INDEX replicache_tombstones_version_idx;
+CREATE INDEX replicache_tombstones_trip_id_version_idx ON replicache_tombstones (trip_id, version);
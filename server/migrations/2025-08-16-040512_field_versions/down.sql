@@ -0,0 +1,4 @@
+ALTER TABLE tasks DROP COLUMN field_versions;
+ALTER TABLE expenses DROP COLUMN field_versions;
+ALTER TABLE itinerary_items DROP COLUMN field_versions;
+ALTER TABLE trips DROP COLUMN field_versions;
@@ -0,0 +1,5 @@
+-- Client timestamp of the last write to each field, used to merge concurrent edits field by field.
+ALTER TABLE trips ADD COLUMN field_versions JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE itinerary_items ADD COLUMN field_versions JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE expenses ADD COLUMN field_versions JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE tasks ADD COLUMN field_versions JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,21 @@
+CREATE OR REPLACE FUNCTION replicache_record_tombstone() RETURNS TRIGGER AS $$
+DECLARE
+  old_row JSONB := to_jsonb(OLD);
+  space TEXT := COALESCE(old_row ->> 'trip_id', old_row ->> 'id');
+BEGIN
+  INSERT INTO replicache_tombstones (key, trip_id, version)
+  VALUES (
+    TG_ARGV[0] || '/' || (old_row ->> 'id'),
+    space::UUID,
+    replicache_next_version(space)
+  )
+  ON CONFLICT (key) DO UPDATE
+    SET trip_id = EXCLUDED.trip_id, version = EXCLUDED.version;
+
+  RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE replicache_tombstones DROP COLUMN data;
+
+DROP TABLE IF EXISTS sync_conflicts;
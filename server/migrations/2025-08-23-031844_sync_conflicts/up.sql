@@ -0,0 +1,44 @@
+-- Changes that lost a merge and need a person to decide, see `replicache::conflicts`.
+CREATE TABLE sync_conflicts (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  trip_id UUID NOT NULL REFERENCES trips(id) ON DELETE CASCADE,
+  -- Replicache key prefix of the entity, e.g. 'expense'
+  entity_type TEXT NOT NULL,
+  entity_id UUID NOT NULL,
+  -- NULL when the entity was deleted
+  field TEXT,
+  current_value JSONB,
+  proposed_value JSONB NOT NULL,
+  proposed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  version INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX sync_conflicts_trip_id_entity_idx ON sync_conflicts (trip_id, entity_type, entity_id);
+
+CREATE TRIGGER sync_conflicts_version BEFORE INSERT OR UPDATE ON sync_conflicts
+  FOR EACH ROW EXECUTE FUNCTION replicache_stamp_version();
+CREATE TRIGGER sync_conflicts_tombstone AFTER DELETE ON sync_conflicts
+  FOR EACH ROW EXECUTE FUNCTION replicache_record_tombstone('conflict');
+
+-- keep the deleted row around, so an edit that raced the delete can still be reviewed
+ALTER TABLE replicache_tombstones ADD COLUMN data JSONB;
+
+CREATE OR REPLACE FUNCTION replicache_record_tombstone() RETURNS TRIGGER AS $$
+DECLARE
+  old_row JSONB := to_jsonb(OLD);
+  space TEXT := COALESCE(old_row ->> 'trip_id', old_row ->> 'id');
+BEGIN
+  INSERT INTO replicache_tombstones (key, trip_id, version, data)
+  VALUES (
+    TG_ARGV[0] || '/' || (old_row ->> 'id'),
+    space::UUID,
+    replicache_next_version(space),
+    old_row
+  )
+  ON CONFLICT (key) DO UPDATE
+    SET trip_id = EXCLUDED.trip_id, version = EXCLUDED.version, data = EXCLUDED.data;
+
+  RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,24 @@
+CREATE OR REPLACE FUNCTION replicache_record_tombstone() RETURNS TRIGGER AS $$
+DECLARE
+  old_row JSONB := to_jsonb(OLD);
+  space TEXT := COALESCE(old_row ->> 'trip_id', old_row ->> 'id');
+BEGIN
+  INSERT INTO replicache_tombstones (key, trip_id, version, data)
+  VALUES (
+    TG_ARGV[0] || '/' || (old_row ->> 'id'),
+    space::UUID,
+    replicache_next_version(space),
+    old_row
+  )
+  ON CONFLICT (key) DO UPDATE
+    SET trip_id = EXCLUDED.trip_id, version = EXCLUDED.version, data = EXCLUDED.data;
+
+  RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP INDEX replicache_tombstones_deleted_at_idx;
+DROP INDEX replicache_client_group_last_pulled_at_idx;
+
+ALTER TABLE replicache_tombstones DROP COLUMN deleted_at;
+ALTER TABLE replicache_client_group DROP COLUMN last_pulled_at;
@@ -0,0 +1,29 @@
+-- Client groups that haven't pulled for a while, and tombstones older than that, are garbage
+-- collected, see `replicache::gc`.
+ALTER TABLE replicache_client_group ADD COLUMN last_pulled_at TIMESTAMPTZ NOT NULL DEFAULT now();
+ALTER TABLE replicache_tombstones ADD COLUMN deleted_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE INDEX replicache_client_group_last_pulled_at_idx ON replicache_client_group (last_pulled_at);
+CREATE INDEX replicache_tombstones_deleted_at_idx ON replicache_tombstones (deleted_at);
+
+CREATE OR REPLACE FUNCTION replicache_record_tombstone() RETURNS TRIGGER AS $$
+DECLARE
+  old_row JSONB := to_jsonb(OLD);
+  space TEXT := COALESCE(old_row ->> 'trip_id', old_row ->> 'id');
+BEGIN
+  INSERT INTO replicache_tombstones (key, trip_id, version, data)
+  VALUES (
+    TG_ARGV[0] || '/' || (old_row ->> 'id'),
+    space::UUID,
+    replicache_next_version(space),
+    old_row
+  )
+  ON CONFLICT (key) DO UPDATE
+    SET trip_id = EXCLUDED.trip_id,
+        version = EXCLUDED.version,
+        data = EXCLUDED.data,
+        deleted_at = EXCLUDED.deleted_at;
+
+  RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,13 @@
+DROP INDEX trip_invites_user_id_idx;
+DROP INDEX trip_invites_invitee_email_idx;
+
+ALTER TABLE trip_invites DROP CONSTRAINT trip_invites_user_id_fkey;
+ALTER TABLE trip_invites ADD CONSTRAINT trip_invites_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users(id);
+
+ALTER TABLE trip_invites ALTER COLUMN created_at DROP NOT NULL;
+ALTER TABLE trip_invites ALTER COLUMN status DROP NOT NULL;
+
+ALTER TABLE trip_invites
+  DROP COLUMN invited_by,
+  DROP COLUMN permission;
@@ -0,0 +1,17 @@
+-- the permission the invitee gets on accepting, and who sent the invite
+ALTER TABLE trip_invites
+  ADD COLUMN permission TEXT NOT NULL DEFAULT 'viewer',
+  ADD COLUMN invited_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+UPDATE trip_invites SET status = 'pending' WHERE status IS NULL;
+ALTER TABLE trip_invites ALTER COLUMN status SET NOT NULL;
+UPDATE trip_invites SET created_at = now() WHERE created_at IS NULL;
+ALTER TABLE trip_invites ALTER COLUMN created_at SET NOT NULL;
+
+-- invites for a deleted user go with them
+ALTER TABLE trip_invites DROP CONSTRAINT trip_invites_user_id_fkey;
+ALTER TABLE trip_invites ADD CONSTRAINT trip_invites_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+CREATE INDEX trip_invites_invitee_email_idx ON trip_invites (invitee_email) WHERE status = 'pending';
+CREATE INDEX trip_invites_user_id_idx ON trip_invites (user_id) WHERE status = 'pending';
@@ -0,0 +1,6 @@
+ALTER TABLE trip_invites DROP CONSTRAINT trip_invites_permission_check;
+
+ALTER TABLE user_trip
+  DROP CONSTRAINT user_trip_permission_check,
+  ALTER COLUMN permission DROP NOT NULL,
+  ALTER COLUMN permission DROP DEFAULT;
@@ -0,0 +1,22 @@
+-- `user_trip.permission` becomes one of the roles in `models::user_trip::Role`
+UPDATE user_trip SET permission = 'owner'
+  FROM trips
+  WHERE trips.id = user_trip.trip_id AND trips.owner_id = user_trip.user_id;
+
+UPDATE user_trip SET permission = 'editor'
+  FROM trips
+  WHERE trips.id = user_trip.trip_id
+    AND trips.owner_id <> user_trip.user_id
+    AND user_trip.permission = 'owner';
+
+UPDATE user_trip SET permission = 'viewer'
+  WHERE permission IS NULL OR permission NOT IN ('owner', 'editor', 'viewer');
+
+ALTER TABLE user_trip
+  ALTER COLUMN permission SET DEFAULT 'viewer',
+  ALTER COLUMN permission SET NOT NULL,
+  ADD CONSTRAINT user_trip_permission_check CHECK (permission IN ('owner', 'editor', 'viewer'));
+
+-- ownership is handed over rather than invited into
+ALTER TABLE trip_invites
+  ADD CONSTRAINT trip_invites_permission_check CHECK (permission IN ('editor', 'viewer'));
@@ -0,0 +1,3 @@
+DROP INDEX user_trip_one_owner;
+
+ALTER TABLE user_trip DROP COLUMN joined_at;
@@ -0,0 +1,15 @@
+-- how long someone has been on a trip decides who inherits it when the owner leaves
+ALTER TABLE user_trip ADD COLUMN joined_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+UPDATE user_trip SET joined_at = COALESCE(trips.created_at, now())
+  FROM trips
+  WHERE trips.id = user_trip.trip_id;
+
+UPDATE user_trip SET joined_at = trip_invites.created_at
+  FROM trip_invites
+  WHERE trip_invites.trip_id = user_trip.trip_id
+    AND trip_invites.user_id = user_trip.user_id
+    AND trip_invites.status = 'accepted'
+    AND trip_invites.created_at > user_trip.joined_at;
+
+CREATE UNIQUE INDEX user_trip_one_owner ON user_trip (trip_id) WHERE permission = 'owner';
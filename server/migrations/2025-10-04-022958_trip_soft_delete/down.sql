@@ -0,0 +1,5 @@
+DROP INDEX trips_deleted_at;
+
+ALTER TABLE trips
+  DROP COLUMN archived_at,
+  DROP COLUMN deleted_at;
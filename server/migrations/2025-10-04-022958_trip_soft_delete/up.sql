@@ -0,0 +1,6 @@
+-- deleted trips are kept for a grace period before they're purged, see `purge`
+ALTER TABLE trips
+  ADD COLUMN archived_at TIMESTAMPTZ,
+  ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX trips_deleted_at ON trips (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,2 @@
+-- IANA zone the coordinates are in, filled in by the server, see `timezones`
+ALTER TABLE locations ADD COLUMN timezone TEXT;
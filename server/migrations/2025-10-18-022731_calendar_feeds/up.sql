@@ -0,0 +1,5 @@
+CREATE TABLE calendar_feeds (
+  token TEXT PRIMARY KEY,
+  user_id UUID NOT NULL UNIQUE REFERENCES users(id) ON DELETE CASCADE,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
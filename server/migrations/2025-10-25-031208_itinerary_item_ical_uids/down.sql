@@ -0,0 +1,3 @@
+DROP INDEX itinerary_items_trip_id_ical_uid;
+
+ALTER TABLE itinerary_items DROP COLUMN ical_uid;
@@ -0,0 +1,3 @@
+ALTER TABLE itinerary_items ADD COLUMN ical_uid TEXT;
+
+CREATE UNIQUE INDEX itinerary_items_trip_id_ical_uid ON itinerary_items (trip_id, ical_uid);
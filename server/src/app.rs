@@ -4,6 +4,7 @@ use crate::{
     config::Server,
     db::{self, get_connection_pool},
     email::Emails,
+    replicache::poke::Pokes,
     s3_client::S3Client,
     util::errors::AppError,
 };
@@ -19,6 +20,7 @@ pub type PoolResult =
 pub struct App {
     pub database: Pool<AsyncPgConnection>,
     pub redis: RedisClient,
+    pub pokes: Pokes,
     pub emails: Option<Emails>,
     pub s3: Option<S3Client>,
     pub config: Server,
@@ -41,10 +43,12 @@ impl App {
         };
 
         let redis = redis::Client::open(config.redis_config.address.clone()).unwrap();
+        let pokes = Pokes::new(redis.clone());
 
         Self {
             database,
             redis,
+            pokes,
             emails,
             s3,
             config,
@@ -1,13 +1,22 @@
 use actix_web::{FromRequest, HttpRequest, dev::Payload, web::Data};
 use chrono::{Duration, TimeZone, Utc};
+use diesel::OptionalExtension;
 use jsonwebtoken::{
     Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation, decode, encode,
 };
 use serde::{Deserialize, Serialize};
-use std::future::ready;
+use std::{future::ready, marker::PhantomData};
 use uuid::Uuid;
 
-use crate::{app::AppState, models::user::User, util::errors::AppError};
+use crate::{
+    app::AppState,
+    models::{
+        trip::Trip,
+        user::User,
+        user_trip::{Role, UserTrip},
+    },
+    util::errors::AppError,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
@@ -68,6 +77,48 @@ impl AuthenticatedUser {
         }
         false
     }
+
+    /// Authenticates an access token, for requests that can't send it in the `Authorization`
+    /// header.
+    pub async fn from_token(state: &AppState, token: &str) -> Result<Self, AppError> {
+        let Ok(token_data) = verify_jwt(token, &state.config.jwt_config.access_secret) else {
+            return Err(AppError::Unauthorized("Missing authorization header"));
+        };
+
+        let issued_at = Utc.timestamp_opt(token_data.claims.iat, 0).unwrap();
+        let expiration_time = Utc.timestamp_opt(token_data.claims.exp, 0).unwrap();
+
+        if expiration_time < Utc::now() {
+            return Err(AppError::Unauthorized("Access token expired"));
+        }
+
+        if issued_at > Utc::now() {
+            return Err(AppError::Unauthorized("Invalid access token"));
+        }
+
+        match state.db_connection().await {
+            Ok(mut conn) => {
+                let result = User::find(&mut conn, &token_data.claims.sub).await;
+
+                match result {
+                    Ok(user) => {
+                        if user.verified || state.emails.is_none() {
+                            Ok(AuthenticatedUser {
+                                user,
+                                role: token_data.claims.role,
+                            })
+                        } else {
+                            Err(AppError::UnverifiedUser(
+                                "User has not verified their email.",
+                            ))
+                        }
+                    }
+                    Err(_) => Err(AppError::NotFound),
+                }
+            }
+            Err(_) => Err(AppError::InternalError),
+        }
+    }
 }
 
 impl FromRequest for AuthenticatedUser {
@@ -82,8 +133,6 @@ impl FromRequest for AuthenticatedUser {
             None => return server_error,
         };
 
-        let config = state.config.clone();
-
         let unauthorized_error = Box::pin(ready(Err(AppError::Unauthorized(
             "Missing authorization header",
         ))));
@@ -94,49 +143,94 @@ impl FromRequest for AuthenticatedUser {
         };
 
         let token = match header.strip_prefix("Bearer ") {
-            Some(token) => token,
+            Some(token) => token.to_string(),
             None => return unauthorized_error,
         };
 
-        if let Ok(token_data) = verify_jwt(token, &config.jwt_config.access_secret) {
-            return Box::pin(async move {
-                let issued_at = Utc.timestamp_opt(token_data.claims.iat, 0).unwrap();
-                let expiration_time = Utc.timestamp_opt(token_data.claims.exp, 0).unwrap();
+        Box::pin(async move { AuthenticatedUser::from_token(&state, &token).await })
+    }
+}
 
-                if expiration_time < Utc::now() {
-                    return Err(AppError::Unauthorized("Access token expired"));
-                }
+/// The lowest role a `TripMember` accepts.
+pub trait MinimumRole {
+    const ROLE: Role;
+    const FORBIDDEN: &'static str;
+}
 
-                if issued_at > Utc::now() {
-                    return Err(AppError::Unauthorized("Invalid access token"));
-                }
+pub struct Viewer;
 
-                match state.db_connection().await {
-                    Ok(mut conn) => {
-                        let result = User::find(&mut conn, &token_data.claims.sub).await;
-
-                        match result {
-                            Ok(user) => {
-                                if user.verified || state.emails.is_none() {
-                                    Ok(AuthenticatedUser {
-                                        user,
-                                        role: token_data.claims.role,
-                                    })
-                                } else {
-                                    Err(AppError::UnverifiedUser(
-                                        "User has not verified their email.",
-                                    ))
-                                }
-                            }
-                            Err(_) => Err(AppError::NotFound),
-                        }
-                    }
-                    Err(_) => Err(AppError::InternalError),
-                }
-            });
-        }
+pub struct Editor;
+
+pub struct Owner;
+
+impl MinimumRole for Viewer {
+    const ROLE: Role = Role::Viewer;
+    const FORBIDDEN: &'static str = "Not a member of this trip.";
+}
+
+impl MinimumRole for Editor {
+    const ROLE: Role = Role::Editor;
+    const FORBIDDEN: &'static str = "Viewers can't change this trip.";
+}
+
+impl MinimumRole for Owner {
+    const ROLE: Role = Role::Owner;
+    const FORBIDDEN: &'static str = "Only the owner can do this.";
+}
+
+/// An authenticated user who is on the trip in the `{trip_id}` path segment with at least the
+/// role `R`, e.g. `TripMember<Editor>` for handlers that change the trip.
+pub struct TripMember<R: MinimumRole> {
+    pub user: User,
+    pub membership: UserTrip,
+    role: PhantomData<R>,
+}
+
+impl<R: MinimumRole> TripMember<R> {
+    pub fn trip_id(&self) -> Uuid {
+        self.membership.trip_id
+    }
+}
+
+impl<R: MinimumRole + 'static> FromRequest for TripMember<R> {
+    type Error = AppError;
+    type Future = futures_util::future::LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let authenticated = AuthenticatedUser::from_request(req, payload);
+        let state = req.app_data::<Data<AppState>>().cloned();
+        let trip_id = req
+            .match_info()
+            .get("trip_id")
+            .and_then(|trip_id| trip_id.parse::<Uuid>().ok());
+
+        Box::pin(async move {
+            let user = authenticated.await?.user;
+            let state = state.ok_or(AppError::InternalError)?;
+            let trip_id = trip_id.ok_or(AppError::NotFound)?;
+
+            let mut conn = state.db_connection().await?;
+
+            // trips that don't exist or were deleted are not found rather than off limits
+            Trip::find(&mut conn, &trip_id).await?;
+
+            let Some(membership) = UserTrip::find(&mut conn, &user.id, &trip_id)
+                .await
+                .optional()?
+            else {
+                return Err(AppError::Forbidden(Viewer::FORBIDDEN));
+            };
+
+            if membership.role < R::ROLE {
+                return Err(AppError::Forbidden(R::FORBIDDEN));
+            }
 
-        unauthorized_error
+            Ok(TripMember {
+                user,
+                membership,
+                role: PhantomData,
+            })
+        })
     }
 }
 
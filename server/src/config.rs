@@ -1,3 +1,5 @@
+use std::num::{NonZeroU32, NonZeroU64};
+
 use config::Config;
 use serde::Deserialize;
 
@@ -21,6 +23,8 @@ pub struct BaseConfig {
     pub port: String,
     pub allowed_origins: Vec<String>,
     pub workers: Option<usize>,
+    /// Signs share link tokens, see `TripShareLink::token`
+    pub share_link_secret: String,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -32,6 +36,10 @@ pub struct Server {
     pub jwt_config: JwtConfig,
     pub s3_config: Option<S3Config>,
     pub redis_config: RedisConfig,
+    #[serde(default)]
+    pub replicache: ReplicacheConfig,
+    #[serde(default)]
+    pub trip_purge: TripPurgeConfig,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -39,6 +47,44 @@ pub struct RedisConfig {
     pub address: String,
 }
 
+/// How long Replicache sync state is kept, see `replicache::gc`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ReplicacheConfig {
+    /// Days a client group can go without pulling before it's collected and has to start over
+    pub client_retention_days: NonZeroU32,
+    /// Minutes between garbage collection runs
+    pub gc_interval_minutes: NonZeroU64,
+}
+
+impl Default for ReplicacheConfig {
+    fn default() -> Self {
+        Self {
+            client_retention_days: NonZeroU32::new(30).unwrap(),
+            gc_interval_minutes: NonZeroU64::new(60).unwrap(),
+        }
+    }
+}
+
+/// How long deleted trips can be restored, see `purge`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TripPurgeConfig {
+    /// Days a deleted trip can be restored before it's purged for good
+    pub grace_period_days: NonZeroU32,
+    /// Minutes between purge runs
+    pub purge_interval_minutes: NonZeroU64,
+}
+
+impl Default for TripPurgeConfig {
+    fn default() -> Self {
+        Self {
+            grace_period_days: NonZeroU32::new(30).unwrap(),
+            purge_interval_minutes: NonZeroU64::new(60).unwrap(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct SmtpConfig {
     pub login: Option<String>,
@@ -83,3 +129,33 @@ impl PgConfig {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use config::{File, FileFormat};
+    use serde::de::DeserializeOwned;
+
+    use super::*;
+
+    fn parse<T: DeserializeOwned>(toml: &str) -> Result<T, config::ConfigError> {
+        Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()?
+            .try_deserialize()
+    }
+
+    #[test]
+    fn replicache_periods_cant_be_zero() {
+        assert!(parse::<ReplicacheConfig>("client_retention_days=7").is_ok());
+        assert!(parse::<ReplicacheConfig>("client_retention_days=0").is_err());
+        assert!(parse::<ReplicacheConfig>("gc_interval_minutes=0").is_err());
+    }
+
+    #[test]
+    fn trip_purge_periods_cant_be_zero() {
+        // a deleted trip always gets at least a day to be restored
+        assert!(parse::<TripPurgeConfig>("grace_period_days=1").is_ok());
+        assert!(parse::<TripPurgeConfig>("grace_period_days=0").is_err());
+        assert!(parse::<TripPurgeConfig>("purge_interval_minutes=0").is_err());
+    }
+}
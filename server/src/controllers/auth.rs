@@ -5,6 +5,7 @@ use crate::{
     google_oauth::{get_google_user, request_token},
     models::{
         refresh_tokens::RefreshToken,
+        trip_invite::TripInvite,
         user::{NewUser, User, UserVerificationCode, VerificationEmail},
     },
     util::{
@@ -107,6 +108,8 @@ pub async fn register_user(
 
     match result {
         Ok(user) => {
+            TripInvite::claim(&mut conn, &user.id, &user.email).await?;
+
             if let Some(emails) = &state.emails {
                 let verification_code = UserVerificationCode::generate(&mut conn, &user.email)
                     .await
@@ -451,6 +454,7 @@ pub async fn google_oauth(
             match new_user.insert(&mut conn).await {
                 Ok(user) => {
                     user_id = user.id;
+                    TripInvite::claim(&mut conn, &user.id, &user.email).await?;
                     Ok(())
                 }
                 Err(_) => {
@@ -616,7 +620,7 @@ pub async fn refresh(req: HttpRequest, state: web::Data<AppState>) -> AppResult<
     }
 }
 
-fn build_refresh_cookie(token: &str, max_age: Duration) -> Cookie {
+fn build_refresh_cookie(token: &str, max_age: Duration) -> Cookie<'_> {
     Cookie::build("refresh_token", token)
         .http_only(true)
         .secure(true)
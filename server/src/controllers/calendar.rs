@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+
+use actix_web::{
+    HttpResponse,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web::{self, Json},
+};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    app::AppState,
+    auth::{AuthenticatedUser, TripMember, Viewer},
+    controllers::helper::OkResponse,
+    ical::{Calendar, Event, EventTime},
+    models::{calendar_feed::CalendarFeed, trip::Trip, user_trip::UserTrip},
+    schema::{accommodations, flights, itinerary_items, locations},
+    util::errors::{AppError, AppResult, ErrorResponse},
+};
+
+const CALENDAR: &str = "calendar";
+
+const FEED_NAME: &str = "Journly";
+
+/// Where an event happens, as calendars show it.
+#[derive(Clone)]
+struct Place {
+    name: String,
+    geo: (f64, f64),
+    zone: Option<Tz>,
+}
+
+type LocationRow = (Uuid, String, Option<String>, f64, f64, Option<String>);
+
+impl Place {
+    fn new(
+        address: String,
+        display_name: Option<String>,
+        latitude: f64,
+        longitude: f64,
+        zone: Option<String>,
+    ) -> Self {
+        let name = match display_name {
+            Some(display_name) if display_name != address => format!("{display_name}, {address}"),
+            _ => address,
+        };
+
+        Place {
+            name,
+            geo: (latitude, longitude),
+            zone: zone.and_then(|zone| zone.parse().ok()),
+        }
+    }
+}
+
+/// The date the item is on, on the clock where it happens.
+fn local_date(time: DateTime<Utc>, zone: Option<Tz>) -> NaiveDate {
+    match zone {
+        Some(zone) => time.with_timezone(&zone).date_naive(),
+        None => time.date_naive(),
+    }
+}
+
+/// The trips' itinerary, flights and accommodation check-ins and check-outs as calendar events.
+/// Takes the same few queries however many trips there are.
+pub async fn load_events(
+    conn: &mut AsyncPgConnection,
+    trip_ids: &[Uuid],
+) -> QueryResult<Vec<Event>> {
+    let items = itinerary_items::table
+        .left_join(locations::table)
+        .filter(itinerary_items::trip_id.eq_any(trip_ids))
+        .order(itinerary_items::start_time.asc())
+        .select((
+            itinerary_items::id,
+            itinerary_items::title,
+            itinerary_items::start_time,
+            itinerary_items::end_time,
+            itinerary_items::all_day,
+            itinerary_items::notes,
+            itinerary_items::description,
+            itinerary_items::version,
+            (
+                locations::address,
+                locations::display_name,
+                locations::latitude,
+                locations::longitude,
+                locations::timezone,
+            )
+                .nullable(),
+        ))
+        .load::<(
+            Uuid,
+            String,
+            DateTime<Utc>,
+            Option<DateTime<Utc>>,
+            bool,
+            String,
+            Option<String>,
+            i32,
+            Option<(String, Option<String>, f64, f64, Option<String>)>,
+        )>(conn)
+        .await?;
+
+    let flights = flights::table
+        .filter(flights::trip_id.eq_any(trip_ids))
+        .order(flights::departure_datetime.asc())
+        .select((
+            flights::id,
+            flights::flight_code,
+            flights::departure_datetime,
+            flights::arrival_datetime,
+            flights::departure_location,
+            flights::arrival_location,
+        ))
+        .load::<(
+            Uuid,
+            Option<String>,
+            Option<DateTime<Utc>>,
+            Option<DateTime<Utc>>,
+            Option<Uuid>,
+            Option<Uuid>,
+        )>(conn)
+        .await?;
+
+    let stays = accommodations::table
+        .filter(accommodations::trip_id.eq_any(trip_ids))
+        .order(accommodations::check_in_datetime.asc())
+        .select((
+            accommodations::id,
+            accommodations::check_in_datetime,
+            accommodations::check_out_datetime,
+            accommodations::location,
+        ))
+        .load::<(
+            Uuid,
+            Option<DateTime<Utc>>,
+            Option<DateTime<Utc>>,
+            Option<Uuid>,
+        )>(conn)
+        .await?;
+
+    let location_ids: Vec<Uuid> = flights
+        .iter()
+        .flat_map(|flight| [flight.4, flight.5])
+        .chain(stays.iter().map(|stay| stay.3))
+        .flatten()
+        .collect();
+
+    let places: HashMap<Uuid, Place> = locations::table
+        .filter(locations::id.eq_any(location_ids))
+        .select((
+            locations::id,
+            locations::address,
+            locations::display_name,
+            locations::latitude,
+            locations::longitude,
+            locations::timezone,
+        ))
+        .load::<LocationRow>(conn)
+        .await?
+        .into_iter()
+        .map(|(id, address, display_name, latitude, longitude, zone)| {
+            (
+                id,
+                Place::new(address, display_name, latitude, longitude, zone),
+            )
+        })
+        .collect();
+    let place = |id: Option<Uuid>| id.and_then(|id| places.get(&id).cloned());
+
+    let mut events = Vec::new();
+
+    for (id, title, start, end, all_day, notes, description, version, location) in items {
+        let place = location.map(|(address, display_name, latitude, longitude, zone)| {
+            Place::new(address, display_name, latitude, longitude, zone)
+        });
+        let zone = place.as_ref().and_then(|place| place.zone);
+
+        let (start, end) = if all_day {
+            let first = local_date(start, zone);
+            let last = local_date(end.unwrap_or(start), zone).max(first);
+
+            // the end of an all-day event is the day after it
+            (
+                EventTime::Date(first),
+                last.checked_add_days(Days::new(1)).map(EventTime::Date),
+            )
+        } else {
+            (EventTime::DateTime(start), end.map(EventTime::DateTime))
+        };
+
+        let description = [description.unwrap_or_default(), notes]
+            .into_iter()
+            .filter(|text| !text.trim().is_empty())
+            .collect::<Vec<_>>();
+
+        events.push(Event {
+            uid: format!("itinerary-item-{id}@journly"),
+            summary: title,
+            start,
+            end,
+            location: place.as_ref().map(|place| place.name.clone()),
+            geo: place.map(|place| place.geo),
+            description: (!description.is_empty()).then(|| description.join("\n\n")),
+            sequence: Some(version),
+        });
+    }
+
+    for (id, code, departure, arrival, from, to) in flights {
+        let Some(start) = departure.or(arrival) else {
+            continue;
+        };
+
+        let from = place(from);
+        let to = place(to);
+
+        let description = [
+            from.as_ref()
+                .map(|from| format!("Departs from {}", from.name)),
+            to.as_ref().map(|to| format!("Arrives at {}", to.name)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+        events.push(Event {
+            uid: format!("flight-{id}@journly"),
+            summary: match code {
+                Some(code) => format!("Flight {code}"),
+                None => "Flight".to_string(),
+            },
+            start: EventTime::DateTime(start),
+            end: arrival
+                .filter(|arrival| *arrival >= start)
+                .map(EventTime::DateTime),
+            location: from.as_ref().map(|from| from.name.clone()),
+            geo: from.map(|from| from.geo),
+            description: (!description.is_empty()).then(|| description.join("\n")),
+            sequence: None,
+        });
+    }
+
+    for (id, check_in, check_out, location) in stays {
+        let place = place(location);
+
+        for (time, event, summary) in [
+            (check_in, "check-in", "Check in"),
+            (check_out, "check-out", "Check out"),
+        ] {
+            let Some(time) = time else {
+                continue;
+            };
+
+            events.push(Event {
+                uid: format!("accommodation-{id}-{event}@journly"),
+                summary: match &place {
+                    Some(place) => format!("{summary}: {}", place.name),
+                    None => summary.to_string(),
+                },
+                start: EventTime::DateTime(time),
+                end: None,
+                location: place.as_ref().map(|place| place.name.clone()),
+                geo: place.as_ref().map(|place| place.geo),
+                description: None,
+                sequence: None,
+            });
+        }
+    }
+
+    Ok(events)
+}
+
+/// A file name for the trip's calendar, made from the trip's title.
+fn file_name(title: Option<&str>) -> String {
+    let name = title
+        .unwrap_or_default()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if name.is_empty() {
+        "trip.ics".to_string()
+    } else {
+        format!("{name}.ics")
+    }
+}
+
+#[utoipa::path(
+    tag = CALENDAR,
+    get,
+    path = "/api/v1/trips/{trip_id}/calendar.ics",
+    responses(
+        (status = 200, description = "The trip's itinerary, flights and accommodation check-ins and check-outs as an iCalendar file", content_type = "text/calendar", body = String),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_trip_calendar(
+    member: TripMember<Viewer>,
+    state: web::Data<AppState>,
+) -> AppResult<HttpResponse> {
+    let mut conn = state.db_connection().await?;
+
+    let trip = Trip::find(&mut conn, &member.trip_id()).await?;
+    let calendar = Calendar {
+        name: trip.title.clone(),
+        events: load_events(&mut conn, &[trip.id]).await?,
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name(trip.title.as_deref()))],
+        })
+        .body(calendar.render(Utc::now())))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CalendarFeedResponse {
+    /// The feed's address, the token in it is only shown this once
+    pub url: String,
+    /// The same address for calendar apps that subscribe to `webcal://` links
+    pub webcal_url: String,
+}
+
+#[utoipa::path(
+    tag = CALENDAR,
+    post,
+    path = "/api/v1/users/{user_id}/calendar-feed",
+    responses(
+        (status = 200, description = "A new feed address, the previous one stops working", body = CalendarFeedResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the user's own feed", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn rotate_calendar_feed(
+    authenticated: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> AppResult<Json<CalendarFeedResponse>> {
+    // the token is a secret of the user's, not even admins get to make one for them
+    if authenticated.user.id != path.into_inner() {
+        return Err(AppError::Forbidden("Insufficient permissions"));
+    }
+
+    let mut conn = state.db_connection().await?;
+
+    let token = CalendarFeed::rotate(&mut conn, &authenticated.user.id).await?;
+
+    // the configured domain rather than the request's Host header, which the client controls
+    let base = &state.config.base;
+    let address = format!("{}/api/v1/calendar-feeds/{token}.ics", base.domain_name);
+    let scheme = if base.production { "https" } else { "http" };
+
+    Ok(Json(CalendarFeedResponse {
+        url: format!("{scheme}://{address}"),
+        webcal_url: format!("webcal://{address}"),
+    }))
+}
+
+#[utoipa::path(
+    tag = CALENDAR,
+    delete,
+    path = "/api/v1/users/{user_id}/calendar-feed",
+    responses(
+        (status = 200, description = "The feed address stopped working", body = OkResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the user's own feed", body = ErrorResponse),
+        (status = 404, description = "The user has no feed", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn revoke_calendar_feed(
+    authenticated: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> AppResult<OkResponse> {
+    let user_id = path.into_inner();
+
+    if !authenticated.is_admin() && authenticated.user.id != user_id {
+        return Err(AppError::Forbidden("Insufficient permissions"));
+    }
+
+    let mut conn = state.db_connection().await?;
+
+    if !CalendarFeed::revoke(&mut conn, &user_id).await? {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(OkResponse::new())
+}
+
+#[utoipa::path(
+    tag = CALENDAR,
+    get,
+    path = "/api/v1/calendar-feeds/{token}.ics",
+    responses(
+        (status = 200, description = "Every trip the feed's user is on as an iCalendar file, deleted trips left out", content_type = "text/calendar", body = String),
+        (status = 404, description = "No feed has the token, or it was rotated or revoked", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+pub async fn get_calendar_feed(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> AppResult<HttpResponse> {
+    // calendar apps can't send a bearer token, the secret token in the address stands in for one
+    let mut conn = state.db_connection().await?;
+
+    let feed = CalendarFeed::find(&mut conn, &path.into_inner()).await?;
+
+    let trip_ids = UserTrip::find_trip_ids(&mut conn, &feed.user_id).await?;
+
+    let calendar = Calendar {
+        name: Some(FEED_NAME.to_string()),
+        events: load_events(&mut conn, &trip_ids).await?,
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .body(calendar.render(Utc::now())))
+}
@@ -0,0 +1,114 @@
+use actix_web::web::{self, Json};
+use diesel_async::{AsyncConnection, scoped_futures::ScopedFutureExt};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    app::AppState,
+    auth::{Editor, TripMember, Viewer},
+    controllers::helper::OkResponse,
+    models::sync_conflict::SyncConflict,
+    replicache::conflicts::{self, ConflictWinner},
+    util::errors::{AppError, AppResult, ErrorResponse},
+    views::EncodableConflict,
+};
+
+const CONFLICTS: &str = "conflicts";
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetConflictsResponse {
+    pub conflicts: Vec<EncodableConflict>,
+}
+
+#[utoipa::path(
+    tag = CONFLICTS,
+    get,
+    path = "/api/v1/trips/{trip_id}/conflicts",
+    responses(
+        (status = 200, description = "Conflicts waiting for review, oldest first", body = GetConflictsResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member of the trip", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_conflicts(
+    member: TripMember<Viewer>,
+    state: web::Data<AppState>,
+) -> AppResult<Json<GetConflictsResponse>> {
+    let mut conn = state.db_connection().await?;
+
+    let conflicts = SyncConflict::find_by_trip(&mut conn, &member.trip_id())
+        .await?
+        .into_iter()
+        .map(|conflict| EncodableConflict {
+            id: conflict.id,
+            entity_type: conflict.entity_type,
+            entity_id: conflict.entity_id,
+            field: conflict.field,
+            current_value: conflict.current_value,
+            proposed_value: conflict.proposed_value,
+            proposed_by: conflict.proposed_by,
+            created_at: conflict.created_at,
+        })
+        .collect();
+
+    Ok(Json(GetConflictsResponse { conflicts }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ResolveConflictBody {
+    /// `current` keeps things as they are, `proposed` applies the change that lost
+    pub winner: ConflictWinner,
+}
+
+#[utoipa::path(
+    tag = CONFLICTS,
+    post,
+    path = "/api/v1/trips/{trip_id}/conflicts/{conflict_id}/resolve",
+    request_body = ResolveConflictBody,
+    responses(
+        (status = 200, description = "Conflict was resolved", body = OkResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not an owner or editor of the trip", body = ErrorResponse),
+        (status = 404, description = "Conflict not found", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn resolve_conflict(
+    member: TripMember<Editor>,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    body: web::Json<ResolveConflictBody>,
+) -> AppResult<OkResponse> {
+    let (trip_id, conflict_id) = path.into_inner();
+    let user_id = member.user.id;
+    let winner = body.winner;
+
+    let mut conn = state.db_connection().await?;
+
+    conn.transaction(|conn| {
+        async move {
+            let conflict = SyncConflict::find(conn, &conflict_id).await?;
+
+            if conflict.trip_id != trip_id {
+                return Err(AppError::NotFound);
+            }
+
+            conflicts::resolve(conn, &conflict, winner).await
+        }
+        .scope_boxed()
+    })
+    .await?;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[trip_id], &user_id)
+        .await?;
+
+    Ok(OkResponse::new())
+}
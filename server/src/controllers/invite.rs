@@ -0,0 +1,325 @@
+use actix_web::web::{self, Json};
+use diesel::OptionalExtension;
+use diesel_async::{AsyncConnection, scoped_futures::ScopedFutureExt};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    app::AppState,
+    auth::{AuthenticatedUser, Editor, TripMember},
+    controllers::helper::OkResponse,
+    models::{
+        trip::Trip,
+        trip_invite::{ACCEPTED, DECLINED, NewTripInvite, TripInvite, TripInviteEmail},
+        user::User,
+        user_trip::{NewUserTrip, Role, UserTrip},
+    },
+    util::{
+        auth::is_valid_email,
+        errors::{AppError, AppResult, ErrorResponse},
+    },
+    views::EncodableTripInvite,
+};
+
+const INVITES: &str = "invites";
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CreateInviteBody {
+    /// Who to invite, either this or `user_id`
+    pub email: Option<String>,
+    pub user_id: Option<Uuid>,
+    /// `editor` or `viewer`, defaults to `viewer`
+    pub permission: Option<Role>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CreateInviteResponse {
+    pub invite: EncodableTripInvite,
+}
+
+#[utoipa::path(
+    tag = INVITES,
+    post,
+    path = "/api/v1/trips/{trip_id}/invites",
+    request_body = CreateInviteBody,
+    responses(
+        (status = 200, description = "Invite was created and emailed", body = CreateInviteResponse),
+        (status = 400, description = "Malformed email, permission, or not exactly one of email and user_id", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not an owner or editor of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip or user not found", body = ErrorResponse),
+        (status = 409, description = "Already on the trip or already invited", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn create_invite(
+    member: TripMember<Editor>,
+    state: web::Data<AppState>,
+    body: web::Json<CreateInviteBody>,
+) -> AppResult<Json<CreateInviteResponse>> {
+    let mut conn = state.db_connection().await?;
+
+    let trip = Trip::find(&mut conn, &member.trip_id()).await?;
+
+    let permission = body.permission.unwrap_or(Role::Viewer);
+
+    if permission == Role::Owner {
+        return Err(AppError::BadRequest(
+            "Invites can only be for editors or viewers.",
+        ));
+    }
+
+    let (email, invitee) = match (&body.email, &body.user_id) {
+        (Some(email), None) => {
+            let email = email.trim().to_lowercase();
+
+            if !is_valid_email(&email) {
+                return Err(AppError::BadRequest("Malformed email address"));
+            }
+
+            let invitee = User::find_by_email(&mut conn, &email).await.optional()?;
+
+            (email, invitee)
+        }
+        (None, Some(user_id)) => {
+            let invitee = User::find(&mut conn, user_id).await?;
+
+            (invitee.email.clone(), Some(invitee))
+        }
+        _ => {
+            return Err(AppError::BadRequest("Invite either an email or a user ID."));
+        }
+    };
+
+    if let Some(invitee) = &invitee
+        && UserTrip::find(&mut conn, &invitee.id, &trip.id)
+            .await
+            .optional()?
+            .is_some()
+    {
+        return Err(AppError::Conflict);
+    }
+
+    if TripInvite::pending_exists(&mut conn, &trip.id, &email).await? {
+        return Err(AppError::Conflict);
+    }
+
+    let invite = NewTripInvite {
+        trip_id: trip.id,
+        user_id: invitee.map(|invitee| invitee.id),
+        invitee_email: &email,
+        permission,
+        invited_by: member.user.id,
+    }
+    .insert(&mut conn)
+    .await?;
+
+    let trip_title = trip.title.as_deref().unwrap_or("a trip");
+
+    if let Some(emails) = &state.emails {
+        let invite_email = TripInviteEmail {
+            inviter: &member.user.username,
+            trip_title,
+            invites_url: &format!("{}/invites", state.config.base.frontend_origin),
+        };
+
+        if let Err(e) = emails.send(&email, invite_email).await {
+            log::warn!("invite email failed to send: {e:?}");
+        }
+    }
+
+    Ok(Json(CreateInviteResponse {
+        invite: EncodableTripInvite::new(invite, trip.title),
+    }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetInvitesResponse {
+    pub invites: Vec<EncodableTripInvite>,
+}
+
+#[utoipa::path(
+    tag = INVITES,
+    get,
+    path = "/api/v1/trips/{trip_id}/invites",
+    responses(
+        (status = 200, description = "Pending invites to the trip, oldest first", body = GetInvitesResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not an owner or editor of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_trip_invites(
+    member: TripMember<Editor>,
+    state: web::Data<AppState>,
+) -> AppResult<Json<GetInvitesResponse>> {
+    let mut conn = state.db_connection().await?;
+
+    let trip = Trip::find(&mut conn, &member.trip_id()).await?;
+
+    let invites = TripInvite::find_pending_by_trip(&mut conn, &trip.id)
+        .await?
+        .into_iter()
+        .map(|invite| EncodableTripInvite::new(invite, trip.title.clone()))
+        .collect();
+
+    Ok(Json(GetInvitesResponse { invites }))
+}
+
+#[utoipa::path(
+    tag = INVITES,
+    delete,
+    path = "/api/v1/trips/{trip_id}/invites/{invite_id}",
+    responses(
+        (status = 200, description = "Invite was revoked", body = OkResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not an owner or editor of the trip", body = ErrorResponse),
+        (status = 404, description = "No pending invite with this ID on the trip", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn revoke_invite(
+    _member: TripMember<Editor>,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> AppResult<OkResponse> {
+    let (trip_id, invite_id) = path.into_inner();
+    let mut conn = state.db_connection().await?;
+
+    let invite = TripInvite::find(&mut conn, &invite_id).await?;
+
+    if invite.trip_id != trip_id || !invite.revoke(&mut conn).await? {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(OkResponse::new())
+}
+
+#[utoipa::path(
+    tag = INVITES,
+    get,
+    path = "/api/v1/invites",
+    responses(
+        (status = 200, description = "Pending invites for the user, newest first", body = GetInvitesResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_my_invites(
+    authenticated: AuthenticatedUser,
+    state: web::Data<AppState>,
+) -> AppResult<Json<GetInvitesResponse>> {
+    let mut conn = state.db_connection().await?;
+
+    let invites = TripInvite::find_pending_for_user(&mut conn, &authenticated.user.id)
+        .await?
+        .into_iter()
+        .map(|(invite, trip_title)| EncodableTripInvite::new(invite, trip_title))
+        .collect();
+
+    Ok(Json(GetInvitesResponse { invites }))
+}
+
+#[utoipa::path(
+    tag = INVITES,
+    post,
+    path = "/api/v1/invites/{invite_id}/accept",
+    responses(
+        (status = 200, description = "Invite was accepted and the user joined the trip", body = OkResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No pending invite with this ID for the user, or the trip was deleted", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn accept_invite(
+    authenticated: AuthenticatedUser,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> AppResult<OkResponse> {
+    let invite_id = path.into_inner();
+    let user_id = authenticated.user.id;
+    let mut conn = state.db_connection().await?;
+
+    let trip_id = conn
+        .transaction::<_, AppError, _>(|conn| {
+            async move {
+                // deleted trips can't be joined until they're restored
+                let invite = TripInvite::find_on_live_trip(conn, &invite_id).await?;
+
+                if !invite.respond(conn, &user_id, ACCEPTED).await? {
+                    return Err(AppError::NotFound);
+                }
+
+                NewUserTrip {
+                    id: None,
+                    user_id,
+                    trip_id: invite.trip_id,
+                    role: invite.permission,
+                }
+                .insert(conn)
+                .await?;
+
+                Ok(invite.trip_id)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[trip_id], &user_id)
+        .await?;
+
+    Ok(OkResponse::new())
+}
+
+#[utoipa::path(
+    tag = INVITES,
+    post,
+    path = "/api/v1/invites/{invite_id}/decline",
+    responses(
+        (status = 200, description = "Invite was declined", body = OkResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No pending invite with this ID for the user", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn decline_invite(
+    authenticated: AuthenticatedUser,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> AppResult<OkResponse> {
+    let invite_id = path.into_inner();
+    let mut conn = state.db_connection().await?;
+
+    let invite = TripInvite::find(&mut conn, &invite_id).await?;
+
+    if !invite
+        .respond(&mut conn, &authenticated.user.id, DECLINED)
+        .await?
+    {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(OkResponse::new())
+}
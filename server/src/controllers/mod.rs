@@ -1,5 +1,11 @@
 pub mod auth;
+pub mod calendar;
+pub mod conflict;
 pub mod helper;
+pub mod invite;
+pub mod replicache;
+pub mod share;
+pub mod trip;
 pub mod trip_plan;
 pub mod user;
 
@@ -0,0 +1,211 @@
+use std::{collections::HashSet, time::Duration};
+
+use actix_web::{
+    Either, Responder,
+    web::{self, Json},
+};
+use actix_web_lab::sse::Sse;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app::AppState,
+    auth::AuthenticatedUser,
+    controllers::helper::OkResponse,
+    replicache::{
+        Mutation, ReplicacheError, gc, poke, process_mutation,
+        pull::{self, PullResponse},
+        upgrades::mutator_version,
+    },
+    util::errors::{AppError, AppResult, ErrorResponse},
+};
+
+const REPLICACHE: &str = "replicache";
+
+const PUSH_VERSION: i32 = 1;
+const PULL_VERSION: i32 = 1;
+
+// keeps idle event streams from being cut off by proxies
+const POKE_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PushRequest {
+    pub push_version: i32,
+    #[serde(rename = "clientGroupID")]
+    pub client_group_id: String,
+    pub mutations: Vec<Mutation>,
+    #[serde(rename = "profileID")]
+    pub profile_id: Option<String>,
+    pub schema_version: Option<String>,
+}
+
+#[utoipa::path(
+    tag = REPLICACHE,
+    post,
+    path = "/api/v1/replicache/push",
+    request_body = PushRequest,
+    responses(
+        (status = 200, description = "Mutations were processed, or the client has to start over or wait for a newer server", body = OkResponse),
+        (status = 400, description = "Malformed push request", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Client group belongs to another user", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn push(
+    authenticated: AuthenticatedUser,
+    state: web::Data<AppState>,
+    body: web::Json<PushRequest>,
+) -> AppResult<Either<OkResponse, Json<ReplicacheError>>> {
+    if body.push_version != PUSH_VERSION {
+        return Err(AppError::BadRequest("Unsupported push version."));
+    }
+
+    let Some(version) = mutator_version(body.schema_version.as_deref()) else {
+        return Ok(Either::Right(Json(ReplicacheError::VersionNotSupported {
+            version_type: "schema".to_string(),
+        })));
+    };
+
+    let mut conn = state.db_connection().await?;
+
+    // clients number their mutations from 1, later ones can only come from an existing group
+    let resuming = body
+        .mutations
+        .first()
+        .is_some_and(|mutation| mutation.id > 1);
+
+    if gc::client_group_collected(&mut conn, &body.client_group_id, resuming).await? {
+        return Ok(Either::Right(Json(ReplicacheError::ClientStateNotFound)));
+    }
+
+    let mut trip_ids = HashSet::new();
+
+    for mutation in body.mutations.iter() {
+        let trip_id = process_mutation(
+            &mut conn,
+            &authenticated.user.id,
+            &body.client_group_id,
+            mutation,
+            version,
+        )
+        .await?;
+
+        trip_ids.extend(trip_id);
+    }
+
+    if !trip_ids.is_empty() {
+        let trip_ids: Vec<_> = trip_ids.into_iter().collect();
+
+        state
+            .pokes
+            .trips_changed(&mut conn, &trip_ids, &authenticated.user.id)
+            .await?;
+    }
+
+    Ok(Either::Left(OkResponse::new()))
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequest {
+    pub pull_version: i32,
+    #[serde(rename = "clientGroupID")]
+    pub client_group_id: String,
+    /// A cookie from an earlier pull. Anything that isn't one starts the client over.
+    #[schema(value_type = Option<Object>)]
+    pub cookie: Option<serde_json::Value>,
+    #[serde(rename = "profileID")]
+    pub profile_id: Option<String>,
+    pub schema_version: Option<String>,
+}
+
+#[utoipa::path(
+    tag = REPLICACHE,
+    post,
+    path = "/api/v1/replicache/pull",
+    request_body = PullRequest,
+    responses(
+        (status = 200, description = "Changes since the given cookie, or the client has to start over", body = PullResponse),
+        (status = 400, description = "Malformed pull request", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Client group belongs to another user", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn pull(
+    authenticated: AuthenticatedUser,
+    state: web::Data<AppState>,
+    body: web::Json<PullRequest>,
+) -> AppResult<Either<Json<PullResponse>, Json<ReplicacheError>>> {
+    if body.pull_version != PULL_VERSION {
+        return Err(AppError::BadRequest("Unsupported pull version."));
+    }
+
+    let mut conn = state.db_connection().await?;
+
+    let cookie = body
+        .cookie
+        .clone()
+        .and_then(|cookie| serde_json::from_value(cookie).ok());
+
+    if gc::client_group_collected(&mut conn, &body.client_group_id, cookie.is_some()).await? {
+        return Ok(Either::Right(Json(ReplicacheError::ClientStateNotFound)));
+    }
+
+    let response = pull::pull(
+        &mut conn,
+        &authenticated.user.id,
+        &body.client_group_id,
+        cookie,
+    )
+    .await?;
+
+    Ok(Either::Left(Json(response)))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PokeQuery {
+    /// A trip ID, or `user/<user ID>` for every trip the user is on
+    pub channel: String,
+    /// An access token, event streams can't send an `Authorization` header
+    pub token: String,
+}
+
+#[utoipa::path(
+    tag = REPLICACHE,
+    get,
+    path = "/api/v1/replicache/poke",
+    params(PokeQuery),
+    responses(
+        (status = 200, description = "Server-sent events, one whenever the channel should pull", content_type = "text/event-stream"),
+        (status = 400, description = "Missing channel or token", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member of the trip, or another user's channel", body = ErrorResponse),
+    )
+)]
+pub async fn poke(
+    state: web::Data<AppState>,
+    query: web::Query<PokeQuery>,
+) -> AppResult<impl Responder> {
+    let PokeQuery { channel, token } = query.into_inner();
+
+    let authenticated = AuthenticatedUser::from_token(&state, &token).await?;
+
+    let mut conn = state.db_connection().await?;
+
+    if !poke::may_listen(&mut conn, &authenticated.user.id, &channel).await? {
+        return Err(AppError::Forbidden("Can't listen to this channel."));
+    }
+
+    let pokes = state.pokes.subscribe(channel);
+
+    Ok(Sse::from_infallible_receiver(pokes).with_keep_alive(POKE_KEEP_ALIVE))
+}
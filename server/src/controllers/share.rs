@@ -0,0 +1,198 @@
+use actix_web::web::{self, Json};
+use chrono::{TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    app::AppState,
+    auth::{Owner, TripMember},
+    controllers::{
+        helper::OkResponse,
+        trip::{GetTripResponse, trip_data},
+    },
+    models::{
+        trip::Trip,
+        trip_share_link::{NewTripShareLink, TripShareLink},
+    },
+    util::errors::{AppError, AppResult, ErrorResponse},
+    views::{EncodableBudgetPlan, EncodableShareLink, EncodableTripData},
+};
+
+const SHARES: &str = "shares";
+
+const DEFAULT_EXPIRY_DAYS: i64 = 30;
+const MAX_EXPIRY_DAYS: i64 = 365;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CreateShareLinkBody {
+    /// Days until the link stops working, 30 if left out and at most 365
+    pub expires_in_days: Option<i64>,
+    /// Show expenses, the budget and itinerary costs, off by default
+    pub include_expenses: Option<bool>,
+    /// Show the trip's documents, off by default
+    pub include_documents: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CreateShareLinkResponse {
+    pub link: EncodableShareLink,
+}
+
+#[utoipa::path(
+    tag = SHARES,
+    post,
+    path = "/api/v1/trips/{trip_id}/shares",
+    request_body = CreateShareLinkBody,
+    responses(
+        (status = 200, description = "Share link was created", body = CreateShareLinkResponse),
+        (status = 400, description = "Expiry is out of range", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the owner of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn create_share_link(
+    member: TripMember<Owner>,
+    state: web::Data<AppState>,
+    body: web::Json<CreateShareLinkBody>,
+) -> AppResult<Json<CreateShareLinkResponse>> {
+    let expires_in_days = body.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS);
+
+    if !(1..=MAX_EXPIRY_DAYS).contains(&expires_in_days) {
+        return Err(AppError::BadRequest(
+            "Share links expire after 1 to 365 days.",
+        ));
+    }
+
+    let mut conn = state.db_connection().await?;
+
+    let link = NewTripShareLink {
+        trip_id: member.trip_id(),
+        created_by: member.user.id,
+        include_expenses: body.include_expenses.unwrap_or(false),
+        include_documents: body.include_documents.unwrap_or(false),
+        expires_at: Utc::now() + TimeDelta::days(expires_in_days),
+    }
+    .insert(&mut conn)
+    .await?;
+
+    Ok(Json(CreateShareLinkResponse {
+        link: EncodableShareLink::new(link, &state.config.base.share_link_secret),
+    }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetShareLinksResponse {
+    pub links: Vec<EncodableShareLink>,
+}
+
+#[utoipa::path(
+    tag = SHARES,
+    get,
+    path = "/api/v1/trips/{trip_id}/shares",
+    responses(
+        (status = 200, description = "Share links that haven't expired or been revoked, newest first", body = GetShareLinksResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the owner of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_share_links(
+    member: TripMember<Owner>,
+    state: web::Data<AppState>,
+) -> AppResult<Json<GetShareLinksResponse>> {
+    let mut conn = state.db_connection().await?;
+
+    let links = TripShareLink::find_active_by_trip(&mut conn, &member.trip_id())
+        .await?
+        .into_iter()
+        .map(|link| EncodableShareLink::new(link, &state.config.base.share_link_secret))
+        .collect();
+
+    Ok(Json(GetShareLinksResponse { links }))
+}
+
+#[utoipa::path(
+    tag = SHARES,
+    delete,
+    path = "/api/v1/trips/{trip_id}/shares/{share_id}",
+    responses(
+        (status = 200, description = "Share link was revoked and stops working right away", body = OkResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the owner of the trip", body = ErrorResponse),
+        (status = 404, description = "No working share link with this ID on the trip", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn revoke_share_link(
+    _member: TripMember<Owner>,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> AppResult<OkResponse> {
+    let (trip_id, share_id) = path.into_inner();
+    let mut conn = state.db_connection().await?;
+
+    if !TripShareLink::revoke(&mut conn, &trip_id, &share_id).await? {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(OkResponse::new())
+}
+
+#[utoipa::path(
+    tag = SHARES,
+    get,
+    path = "/api/v1/shared/{token}",
+    responses(
+        (status = 200, description = "The shared trip. Expenses, the budget and documents are left out unless the link includes them.", body = GetTripResponse),
+        (status = 404, description = "The link doesn't exist, expired or was revoked", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+pub async fn get_shared_trip(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> AppResult<Json<GetTripResponse>> {
+    let mut conn = state.db_connection().await?;
+
+    let link = TripShareLink::find_by_token(
+        &mut conn,
+        &path.into_inner(),
+        &state.config.base.share_link_secret,
+    )
+    .await?;
+
+    let trip = Trip::find(&mut conn, &link.trip_id).await?;
+
+    let mut trip = trip_data(&mut conn, trip).await?;
+    redact(&mut trip, &link);
+
+    Ok(Json(GetTripResponse { trip }))
+}
+
+/// Leaves out what the link wasn't made to show.
+fn redact(trip: &mut EncodableTripData, link: &TripShareLink) {
+    if !link.include_expenses {
+        trip.budget_plan = EncodableBudgetPlan::default();
+
+        for item in trip.itinerary.iter_mut() {
+            item.cost = None;
+        }
+    }
+
+    if !link.include_documents {
+        trip.documents.clear();
+    }
+}
@@ -0,0 +1,1174 @@
+use std::collections::HashMap;
+
+use actix_multipart::form::{MultipartForm, tempfile::TempFile};
+use actix_web::web::{self, Json};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use diesel::prelude::*;
+use diesel_async::{
+    AsyncConnection, AsyncPgConnection, RunQueryDsl, scoped_futures::ScopedFutureExt,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    app::AppState,
+    auth::{AuthenticatedUser, Editor, MinimumRole, Owner, TripMember, Viewer},
+    controllers::helper::OkResponse,
+    feasibility,
+    models::{
+        expense::Expense,
+        itinerary_item::ItineraryItem,
+        task::{NewTask, Task},
+        trip::{
+            NewTrip, Trip, TripChanges, TripCursor, TripFilter, TripSort, TripState, TripStatus,
+        },
+        user_trip::{Role, UserTrip},
+    },
+    replicache::merge::FieldVersions,
+    schema::{
+        accommodations, budget_planners, documents, expense_payers, expenses, flights,
+        itinerary_items, locations, tasks, user_trip, users,
+    },
+    timezones::local_time,
+    util::{
+        banner,
+        errors::{AppError, AppResult, ErrorResponse},
+    },
+    views::{
+        EncodableBudgetPlan, EncodableCollaborator, EncodableDocument, EncodableExpense,
+        EncodableFlight, EncodableGroupBudget, EncodableItineraryItem, EncodableLocation,
+        EncodableStay, EncodableTripData, EncodableTripOverview, EncodableUserPreview,
+        ItineraryExpense,
+    },
+};
+
+const TRIPS: &str = "trips";
+
+fn check_dates(start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> AppResult<()> {
+    match (start_date, end_date) {
+        (Some(start_date), Some(end_date)) if end_date < start_date => Err(AppError::BadRequest(
+            "The end date can't be before the start date.",
+        )),
+        _ => Ok(()),
+    }
+}
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetTripsResponse {
+    pub trips: Vec<EncodableTripOverview>,
+    /// Pass as `cursor` to get the next page, left out on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct GetTripsQuery {
+    /// `active`, `archived` or `deleted`, defaults to `active`
+    pub state: Option<TripState>,
+    /// Only trips with this in their title, ignoring case
+    pub q: Option<String>,
+    /// `upcoming`, `ongoing` or `past`, trips without a start date have none
+    pub status: Option<TripStatus>,
+    /// Only trips the user is a `viewer`, `editor` or `owner` on
+    pub role: Option<Role>,
+    /// `updated` for most recently edited first, the default, or `start_date` for earliest first
+    pub sort: Option<TripSort>,
+    /// `next_cursor` of the previous page, with the same filters and sort
+    pub cursor: Option<String>,
+    /// Trips per page, 50 if left out and at most 100
+    pub limit: Option<i64>,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    get,
+    path = "/api/v1/trips",
+    params(GetTripsQuery),
+    responses(
+        (status = 200, description = "A page of the trips the user is on that match the filters", body = GetTripsResponse),
+        (status = 400, description = "Unknown filter value, limit out of range or a cursor that isn't for this sort", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_trips(
+    authenticated: AuthenticatedUser,
+    state: web::Data<AppState>,
+    query: web::Query<GetTripsQuery>,
+) -> AppResult<Json<GetTripsResponse>> {
+    let query = query.into_inner();
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::BadRequest("Pages hold 1 to 100 trips."));
+    }
+
+    let sort = query.sort.unwrap_or_default();
+
+    let cursor = query
+        .cursor
+        .map(|cursor| {
+            TripCursor::decode(&cursor, sort).ok_or(AppError::BadRequest("Invalid cursor."))
+        })
+        .transpose()?;
+
+    let filter = TripFilter {
+        state: query.state.unwrap_or_default(),
+        search: query.q.filter(|q| !q.trim().is_empty()),
+        status: query.status,
+        role: query.role,
+        sort,
+        cursor,
+        // one more than asked for tells whether there's another page
+        limit: limit + 1,
+    };
+
+    let mut conn = state.db_connection().await?;
+
+    let mut trips = Trip::find_for_user(&mut conn, &authenticated.user.id, &filter).await?;
+
+    let next_cursor = if trips.len() as i64 > limit {
+        trips.truncate(limit as usize);
+        trips
+            .last()
+            .map(|trip| TripCursor::after(trip, sort).encode())
+    } else {
+        None
+    };
+
+    Ok(Json(GetTripsResponse {
+        trips: trips.into_iter().map(EncodableTripOverview::from).collect(),
+        next_cursor,
+    }))
+}
+
+type BudgetRow = (
+    Option<String>,
+    Option<BigDecimal>,
+    Option<BigDecimal>,
+    Option<BigDecimal>,
+    Option<BigDecimal>,
+    Option<BigDecimal>,
+    Option<BigDecimal>,
+);
+
+type LocationRow = (Uuid, Option<String>, String, f64, f64, Option<String>);
+
+/// The trip's itinerary in order, with each item's location and cost.
+pub async fn load_itinerary(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+) -> QueryResult<Vec<EncodableItineraryItem>> {
+    let rows = itinerary_items::table
+        .left_join(locations::table)
+        .left_join(expenses::table)
+        .filter(itinerary_items::trip_id.eq(trip_id))
+        .order(itinerary_items::start_time.asc())
+        .select((
+            ItineraryItem::as_select(),
+            (
+                locations::id,
+                locations::display_name,
+                locations::address,
+                locations::longitude,
+                locations::latitude,
+                locations::timezone,
+            )
+                .nullable(),
+            (expenses::cost, expenses::currency).nullable(),
+        ))
+        .load::<(
+            ItineraryItem,
+            Option<LocationRow>,
+            Option<(BigDecimal, String)>,
+        )>(conn)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(item, location, cost)| {
+            let timezone = location.as_ref().and_then(|location| location.5.clone());
+            let local = |time| timezone.as_deref().and_then(|zone| local_time(time, zone));
+
+            EncodableItineraryItem {
+                id: Some(item.id),
+                activity_type: Some(item.activity_type),
+                title: Some(item.title),
+                location: location.map(|(id, display_name, address, longitude, latitude, _)| {
+                    EncodableLocation {
+                        id: Some(id),
+                        display_name,
+                        address,
+                        longitude,
+                        latitude,
+                    }
+                }),
+                start_time: Some(item.start_time),
+                end_time: item.end_time,
+                local_start_time: local(item.start_time),
+                local_end_time: item.end_time.and_then(local),
+                timezone,
+                cost: cost.map(|(cost, currency)| ItineraryExpense { cost, currency }),
+                notes: Some(item.notes),
+            }
+        })
+        .collect())
+}
+
+type FlightRow = (
+    Uuid,
+    Option<String>,
+    Option<DateTime<Utc>>,
+    Option<DateTime<Utc>>,
+    Option<Uuid>,
+    Option<Uuid>,
+);
+
+/// The trip's flights in order of departure, with times on the clocks at both airports.
+pub async fn load_flights(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+) -> QueryResult<Vec<EncodableFlight>> {
+    let flights = flights::table
+        .filter(flights::trip_id.eq(trip_id))
+        .order(flights::departure_datetime.asc())
+        .select((
+            flights::id,
+            flights::flight_code,
+            flights::departure_datetime,
+            flights::arrival_datetime,
+            flights::departure_location,
+            flights::arrival_location,
+        ))
+        .load::<FlightRow>(conn)
+        .await?;
+
+    let location_ids: Vec<Uuid> = flights
+        .iter()
+        .flat_map(|flight| [flight.4, flight.5])
+        .flatten()
+        .collect();
+
+    let timezones: HashMap<Uuid, String> = locations::table
+        .filter(locations::id.eq_any(location_ids))
+        .filter(locations::timezone.is_not_null())
+        .select((locations::id, locations::timezone.assume_not_null()))
+        .load(conn)
+        .await?
+        .into_iter()
+        .collect();
+    let timezone = |id: Option<Uuid>| id.and_then(|id| timezones.get(&id).cloned());
+
+    Ok(flights
+        .into_iter()
+        .map(|(id, flight_code, departure, arrival, from, to)| {
+            let departure_timezone = timezone(from);
+            let arrival_timezone = timezone(to);
+
+            EncodableFlight {
+                id,
+                flight_code,
+                departure_time: departure,
+                local_departure_time: departure
+                    .zip(departure_timezone.as_deref())
+                    .and_then(|(time, zone)| local_time(time, zone)),
+                departure_timezone,
+                arrival_time: arrival,
+                local_arrival_time: arrival
+                    .zip(arrival_timezone.as_deref())
+                    .and_then(|(time, zone)| local_time(time, zone)),
+                arrival_timezone,
+            }
+        })
+        .collect())
+}
+
+/// The trip's accommodations by check-in.
+pub async fn load_stays(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+) -> QueryResult<Vec<EncodableStay>> {
+    let stays = accommodations::table
+        .left_join(locations::table)
+        .filter(accommodations::trip_id.eq(trip_id))
+        .order(accommodations::check_in_datetime.asc())
+        .select((
+            accommodations::id,
+            accommodations::check_in_datetime,
+            accommodations::check_out_datetime,
+            (
+                locations::id,
+                locations::display_name,
+                locations::address,
+                locations::longitude,
+                locations::latitude,
+                locations::timezone,
+            )
+                .nullable(),
+        ))
+        .load::<(
+            Uuid,
+            Option<DateTime<Utc>>,
+            Option<DateTime<Utc>>,
+            Option<LocationRow>,
+        )>(conn)
+        .await?;
+
+    Ok(stays
+        .into_iter()
+        .map(|(id, check_in, check_out, location)| {
+            let timezone = location.as_ref().and_then(|location| location.5.clone());
+            let local = |time| timezone.as_deref().and_then(|zone| local_time(time, zone));
+
+            EncodableStay {
+                id,
+                location: location.map(|(id, display_name, address, longitude, latitude, _)| {
+                    EncodableLocation {
+                        id: Some(id),
+                        display_name,
+                        address,
+                        longitude,
+                        latitude,
+                    }
+                }),
+                check_in_time: check_in,
+                local_check_in_time: check_in.and_then(local),
+                check_out_time: check_out,
+                local_check_out_time: check_out.and_then(local),
+                timezone,
+            }
+        })
+        .collect())
+}
+
+/// Assembles everything on a trip. Each kind of row is loaded for the whole trip in one query,
+/// so the number of queries doesn't grow with the size of the trip.
+pub async fn trip_data(conn: &mut AsyncPgConnection, trip: Trip) -> QueryResult<EncodableTripData> {
+    let collaborators = user_trip::table
+        .inner_join(users::table)
+        .filter(user_trip::trip_id.eq(trip.id))
+        .order(users::username.asc())
+        .select((
+            users::id,
+            users::username,
+            users::avatar,
+            user_trip::permission,
+        ))
+        .load::<(Uuid, String, Option<String>, Role)>(conn)
+        .await?
+        .into_iter()
+        .map(|(id, username, avatar, permission)| EncodableCollaborator {
+            id,
+            username,
+            avatar,
+            permission,
+        })
+        .collect();
+
+    let budget: Option<BudgetRow> = budget_planners::table
+        .filter(budget_planners::trip_id.eq(trip.id))
+        .select((
+            budget_planners::currency,
+            budget_planners::total_budget,
+            budget_planners::accommodation_budget,
+            budget_planners::transportation_budget,
+            budget_planners::food_dining_budget,
+            budget_planners::activities_budget,
+            budget_planners::shopping_budget,
+        ))
+        .first(conn)
+        .await
+        .optional()?;
+
+    let (currency, total, accommodation, transportation, food_dining, activities, shopping) =
+        budget.unwrap_or_default();
+
+    let mut payers: HashMap<Uuid, Vec<EncodableUserPreview>> = HashMap::new();
+
+    expense_payers::table
+        .inner_join(users::table)
+        .filter(expense_payers::trip_id.eq(trip.id))
+        .order(users::username.asc())
+        .select((
+            expense_payers::expense_id,
+            users::id,
+            users::username,
+            users::avatar,
+        ))
+        .load::<(Uuid, Uuid, String, Option<String>)>(conn)
+        .await?
+        .into_iter()
+        .for_each(|(expense_id, id, username, avatar)| {
+            payers
+                .entry(expense_id)
+                .or_default()
+                .push(EncodableUserPreview {
+                    id,
+                    username,
+                    avatar,
+                })
+        });
+
+    let expenses = expenses::table
+        .filter(expenses::trip_id.eq(trip.id))
+        .order(expenses::title.asc())
+        .select(Expense::as_select())
+        .load(conn)
+        .await?
+        .into_iter()
+        .map(|expense| EncodableExpense {
+            payers: payers.remove(&expense.id).unwrap_or_default(),
+            id: expense.id,
+            title: expense.title,
+            cost: expense.cost,
+            currency: expense.currency,
+        })
+        .collect();
+
+    let itinerary = load_itinerary(conn, &trip.id).await?;
+    let flights = load_flights(conn, &trip.id).await?;
+    let warnings = feasibility::check_trip(conn, &trip).await?;
+
+    let documents = documents::table
+        .filter(documents::trip_id.eq(trip.id))
+        .order(documents::created_at.asc())
+        .select((documents::id, documents::filename, documents::file_size))
+        .load::<(Uuid, String, i64)>(conn)
+        .await?
+        .into_iter()
+        .map(|(id, filename, size_bytes)| EncodableDocument {
+            id,
+            filename,
+            size_bytes,
+        })
+        .collect();
+
+    Ok(EncodableTripData {
+        id: trip.id,
+        title: trip.title,
+        banner_image: trip.banner_image,
+        start_date: trip.start_date,
+        end_date: trip.end_date,
+        collaborators,
+        budget_plan: EncodableBudgetPlan {
+            group_budget: EncodableGroupBudget {
+                currency,
+                total_budget: total,
+                accommodation_budget: accommodation,
+                transportation_budget: transportation,
+                food_dining_budget: food_dining,
+                activities_budget: activities,
+                shopping_budget: shopping,
+            },
+            expenses,
+        },
+        itinerary,
+        flights,
+        documents,
+        warnings,
+    })
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetTripResponse {
+    pub trip: EncodableTripData,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    get,
+    path = "/api/v1/trips/{trip_id}",
+    responses(
+        (status = 200, description = "The trip with its collaborators, budget, itinerary and documents", body = GetTripResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_trip(
+    member: TripMember<Viewer>,
+    state: web::Data<AppState>,
+) -> AppResult<Json<GetTripResponse>> {
+    let mut conn = state.db_connection().await?;
+
+    let trip = Trip::find(&mut conn, &member.trip_id()).await?;
+
+    let trip = trip_data(&mut conn, trip).await?;
+
+    Ok(Json(GetTripResponse { trip }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CreateTripBody {
+    pub title: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CreateTripResponse {
+    pub trip: EncodableTripOverview,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    post,
+    path = "/api/v1/trips",
+    request_body = CreateTripBody,
+    responses(
+        (status = 200, description = "Trip was created, owned by the user", body = CreateTripResponse),
+        (status = 400, description = "End date is before the start date", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn create_trip(
+    authenticated: AuthenticatedUser,
+    state: web::Data<AppState>,
+    body: web::Json<CreateTripBody>,
+) -> AppResult<Json<CreateTripResponse>> {
+    check_dates(body.start_date, body.end_date)?;
+
+    let mut conn = state.db_connection().await?;
+
+    let trip = NewTrip {
+        id: None,
+        owner_id: authenticated.user.id,
+        title: body.title.as_deref(),
+        description: None,
+        banner_image: None,
+        start_date: body.start_date,
+        end_date: body.end_date,
+    }
+    .insert(&mut conn)
+    .await?;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[trip.id], &authenticated.user.id)
+        .await?;
+
+    Ok(Json(CreateTripResponse { trip: trip.into() }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct UpdateTripBody {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    put,
+    path = "/api/v1/trips/{trip_id}",
+    request_body = UpdateTripBody,
+    responses(
+        (status = 200, description = "Trip was updated, fields left out are kept as they are", body = OkResponse),
+        (status = 400, description = "End date is before the start date", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not an owner or editor of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn update_trip(
+    member: TripMember<Editor>,
+    state: web::Data<AppState>,
+    body: web::Json<UpdateTripBody>,
+) -> AppResult<OkResponse> {
+    let trip_id = member.trip_id();
+    let user_id = member.user.id;
+    let body = body.into_inner();
+    let mut conn = state.db_connection().await?;
+
+    conn.transaction::<_, AppError, _>(|conn| {
+        async move {
+            let trip = Trip::find(conn, &trip_id).await?;
+
+            check_dates(
+                body.start_date.or(trip.start_date),
+                body.end_date.or(trip.end_date),
+            )?;
+
+            // stamped like a mutation made now, so offline edits that are older lose to this one
+            let timestamp = Utc::now().timestamp_millis() as f64;
+            let mut versions = FieldVersions::new(Trip::lock_field_versions(conn, &trip.id).await?);
+
+            TripChanges {
+                title: versions.merge("title", body.title, timestamp),
+                description: versions.merge("description", body.description, timestamp),
+                banner_image: None,
+                start_date: versions.merge("start_date", body.start_date, timestamp),
+                end_date: versions.merge("end_date", body.end_date, timestamp),
+                updated_at: None,
+                field_versions: versions.into_changes(),
+            }
+            .apply(conn, &trip.id)
+            .await?;
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[trip_id], &user_id)
+        .await?;
+
+    Ok(OkResponse::new())
+}
+
+#[derive(Debug, MultipartForm, ToSchema)]
+pub struct BannerUploadForm {
+    #[multipart(limit = "10MB")]
+    #[schema(value_type = String, format = Binary, content_media_type = "application/octet-stream")]
+    pub file: TempFile,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct UploadBannerResponse {
+    pub banner_image: String,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    put,
+    path = "/api/v1/trips/{trip_id}/banner",
+    request_body(
+        content = BannerUploadForm,
+        content_type = "multipart/form-data",
+        description = "JPEG, PNG or WebP image of at most 10MB. It's scaled down to 1600px wide and stored as JPEG.",
+    ),
+    responses(
+        (status = 200, description = "Banner was replaced, the old one is deleted", body = UploadBannerResponse),
+        (status = 400, description = "Not a JPEG, PNG or WebP image, or it couldn't be read", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not an owner or editor of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn upload_banner(
+    member: TripMember<Editor>,
+    state: web::Data<AppState>,
+    MultipartForm(form): MultipartForm<BannerUploadForm>,
+) -> AppResult<Json<UploadBannerResponse>> {
+    let trip_id = member.trip_id();
+    let user_id = member.user.id;
+
+    let s3 = state.s3.as_ref().ok_or(AppError::InternalError)?;
+
+    // decoding and resizing is too slow for the async workers
+    let banner = web::block(move || {
+        let bytes = std::fs::read(form.file.file.path()).map_err(|_| AppError::InternalError)?;
+
+        banner::process(&bytes)
+    })
+    .await
+    .map_err(|_| AppError::InternalError)??;
+
+    let banner_url = s3
+        .upload_bytes(banner, "banner", "jpeg", "image/jpeg")
+        .await;
+
+    let mut conn = state.db_connection().await?;
+
+    let result = conn
+        .transaction::<_, AppError, _>(|conn| {
+            let banner_url = banner_url.clone();
+
+            async move {
+                let trip = Trip::find(conn, &trip_id).await?;
+
+                let timestamp = Utc::now().timestamp_millis() as f64;
+                let mut versions =
+                    FieldVersions::new(Trip::lock_field_versions(conn, &trip_id).await?);
+
+                TripChanges {
+                    banner_image: versions.merge("banner_image", Some(banner_url), timestamp),
+                    field_versions: versions.into_changes(),
+                    ..Default::default()
+                }
+                .apply(conn, &trip_id)
+                .await?;
+
+                Ok(trip.banner_image)
+            }
+            .scope_boxed()
+        })
+        .await;
+
+    let old_banner = match result {
+        Ok(old_banner) => old_banner,
+        Err(e) => {
+            s3.delete_file(&s3.get_key_from_url(&banner_url)).await;
+            return Err(e);
+        }
+    };
+
+    if let Some(old_banner) = old_banner {
+        s3.delete_file(&s3.get_key_from_url(&old_banner)).await;
+    }
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[trip_id], &user_id)
+        .await?;
+
+    Ok(Json(UploadBannerResponse {
+        banner_image: banner_url,
+    }))
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    delete,
+    path = "/api/v1/trips/{trip_id}",
+    responses(
+        (status = 200, description = "Trip was deleted, the owner can restore it until it's purged after the grace period", body = OkResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the owner of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn delete_trip(
+    member: TripMember<Owner>,
+    state: web::Data<AppState>,
+) -> AppResult<OkResponse> {
+    let mut conn = state.db_connection().await?;
+
+    Trip::delete(&mut conn, &member.trip_id()).await?;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[member.trip_id()], &member.user.id)
+        .await?;
+
+    Ok(OkResponse::new())
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    post,
+    path = "/api/v1/trips/{trip_id}/restore",
+    responses(
+        (status = 200, description = "Trip was restored", body = OkResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the owner of the trip", body = ErrorResponse),
+        (status = 404, description = "No deleted trip with this ID, or it was purged", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn restore_trip(
+    authenticated: AuthenticatedUser,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> AppResult<OkResponse> {
+    let user_id = authenticated.user.id;
+    let mut conn = state.db_connection().await?;
+
+    // `TripMember` doesn't find deleted trips, so membership is checked here
+    let trip = Trip::find_deleted(&mut conn, &path.into_inner()).await?;
+
+    let membership = UserTrip::find(&mut conn, &user_id, &trip.id)
+        .await
+        .optional()?
+        .ok_or(AppError::Forbidden(Viewer::FORBIDDEN))?;
+
+    if membership.role < Owner::ROLE {
+        return Err(AppError::Forbidden(Owner::FORBIDDEN));
+    }
+
+    Trip::restore(&mut conn, &trip.id).await?;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[trip.id], &user_id)
+        .await?;
+
+    Ok(OkResponse::new())
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    post,
+    path = "/api/v1/trips/{trip_id}/archive",
+    responses(
+        (status = 200, description = "Trip was archived, it's only listed with `state=archived` until it's unarchived", body = OkResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the owner of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn archive_trip(
+    member: TripMember<Owner>,
+    state: web::Data<AppState>,
+) -> AppResult<OkResponse> {
+    set_archived(member, state, true).await
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    post,
+    path = "/api/v1/trips/{trip_id}/unarchive",
+    responses(
+        (status = 200, description = "Trip was unarchived", body = OkResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the owner of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn unarchive_trip(
+    member: TripMember<Owner>,
+    state: web::Data<AppState>,
+) -> AppResult<OkResponse> {
+    set_archived(member, state, false).await
+}
+
+async fn set_archived(
+    member: TripMember<Owner>,
+    state: web::Data<AppState>,
+    archived: bool,
+) -> AppResult<OkResponse> {
+    let mut conn = state.db_connection().await?;
+
+    Trip::set_archived(&mut conn, &member.trip_id(), archived).await?;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[member.trip_id()], &member.user.id)
+        .await?;
+
+    Ok(OkResponse::new())
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TransferOwnershipBody {
+    /// The collaborator who becomes the owner
+    pub user_id: Uuid,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    put,
+    path = "/api/v1/trips/{trip_id}/owner",
+    request_body = TransferOwnershipBody,
+    responses(
+        (status = 200, description = "Ownership was handed over, the previous owner is now an editor", body = OkResponse),
+        (status = 400, description = "The new owner isn't a collaborator on the trip, or already owns it", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the owner of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn transfer_ownership(
+    member: TripMember<Owner>,
+    state: web::Data<AppState>,
+    body: web::Json<TransferOwnershipBody>,
+) -> AppResult<OkResponse> {
+    let trip_id = member.trip_id();
+    let new_owner = body.user_id;
+
+    if new_owner == member.user.id {
+        return Err(AppError::BadRequest("You already own this trip."));
+    }
+
+    let mut conn = state.db_connection().await?;
+
+    UserTrip::find(&mut conn, &new_owner, &trip_id)
+        .await
+        .optional()?
+        .ok_or(AppError::BadRequest(
+            "The new owner has to be a collaborator on this trip.",
+        ))?;
+
+    Trip::transfer_ownership(&mut conn, &trip_id, &new_owner).await?;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[trip_id], &member.user.id)
+        .await?;
+
+    Ok(OkResponse::new())
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CloneTripBody {
+    /// Title of the copy, the original's title if left out
+    pub title: Option<String>,
+    /// Moves the copy and its itinerary so that it starts on this date
+    pub start_date: Option<NaiveDate>,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    post,
+    path = "/api/v1/trips/{trip_id}/clone",
+    request_body = CloneTripBody,
+    responses(
+        (status = 200, description = "A copy of the trip owned by the user, with its itinerary, tasks, budget and expenses. Collaborators, payers, documents and the banner image are left out and tasks start unchecked.", body = CreateTripResponse),
+        (status = 400, description = "The new start date is after the trip's end date", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn clone_trip(
+    member: TripMember<Viewer>,
+    state: web::Data<AppState>,
+    body: web::Json<CloneTripBody>,
+) -> AppResult<Json<CreateTripResponse>> {
+    let trip_id = member.trip_id();
+    let user_id = member.user.id;
+    let body = body.into_inner();
+    let mut conn = state.db_connection().await?;
+
+    let copy = conn
+        .transaction::<_, AppError, _>(|conn| {
+            async move {
+                let trip = Trip::find(conn, &trip_id).await?;
+
+                copy_trip(conn, trip, &user_id, body).await
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[copy.id], &user_id)
+        .await?;
+
+    Ok(Json(CreateTripResponse { trip: copy.into() }))
+}
+
+fn shift_date(date: NaiveDate, shift: TimeDelta) -> AppResult<NaiveDate> {
+    date.checked_add_signed(shift)
+        .ok_or(AppError::BadRequest("The new start date is out of range."))
+}
+
+fn shift_time(time: DateTime<Utc>, shift: TimeDelta) -> AppResult<DateTime<Utc>> {
+    time.checked_add_signed(shift)
+        .ok_or(AppError::BadRequest("The new start date is out of range."))
+}
+
+/// Copies `trip` into a new trip owned by `owner_id`, everything under a new ID. Has to run in a
+/// transaction so a failed copy doesn't leave half a trip behind.
+async fn copy_trip(
+    conn: &mut AsyncPgConnection,
+    trip: Trip,
+    owner_id: &Uuid,
+    body: CloneTripBody,
+) -> AppResult<Trip> {
+    let items = ItineraryItem::find_by_trip(conn, &trip.id).await?;
+
+    // a trip without dates is moved by its first itinerary item
+    let shift = match (
+        body.start_date,
+        trip.start_date
+            .or_else(|| items.iter().map(|item| item.start_time.date_naive()).min()),
+    ) {
+        (Some(start_date), Some(from)) => start_date - from,
+        _ => TimeDelta::zero(),
+    };
+
+    let start_date = body.start_date.or(trip.start_date);
+    let end_date = trip
+        .end_date
+        .map(|date| shift_date(date, shift))
+        .transpose()?;
+
+    check_dates(start_date, end_date)?;
+
+    let copy = NewTrip {
+        id: None,
+        owner_id: *owner_id,
+        title: body.title.as_deref().or(trip.title.as_deref()),
+        description: trip.description.as_deref(),
+        banner_image: None,
+        start_date,
+        end_date,
+    }
+    .insert(conn)
+    .await?;
+
+    diesel::insert_into(budget_planners::table)
+        .values(
+            budget_planners::table
+                .filter(budget_planners::trip_id.eq(trip.id))
+                .select((
+                    copy.id.into_sql::<diesel::sql_types::Uuid>(),
+                    budget_planners::total_budget,
+                    budget_planners::currency,
+                    budget_planners::accommodation_budget,
+                    budget_planners::transportation_budget,
+                    budget_planners::food_dining_budget,
+                    budget_planners::activities_budget,
+                    budget_planners::shopping_budget,
+                )),
+        )
+        .into_columns((
+            budget_planners::trip_id,
+            budget_planners::total_budget,
+            budget_planners::currency,
+            budget_planners::accommodation_budget,
+            budget_planners::transportation_budget,
+            budget_planners::food_dining_budget,
+            budget_planners::activities_budget,
+            budget_planners::shopping_budget,
+        ))
+        .execute(conn)
+        .await?;
+
+    let mut expense_ids = HashMap::new();
+
+    let expenses: Vec<Expense> = expenses::table
+        .filter(expenses::trip_id.eq(trip.id))
+        .select(Expense::as_select())
+        .load::<Expense>(conn)
+        .await?
+        .into_iter()
+        .map(|expense| {
+            let id = Uuid::new_v4();
+            expense_ids.insert(expense.id, id);
+
+            Expense {
+                id,
+                trip_id: copy.id,
+                ..expense
+            }
+        })
+        .collect();
+
+    diesel::insert_into(expenses::table)
+        .values(&expenses)
+        .execute(conn)
+        .await?;
+
+    // locations aren't scoped to a trip, the copy gets its own so editing one leaves the other be
+    let mut location_ids = HashMap::new();
+
+    for location_id in items.iter().filter_map(|item| item.location_id) {
+        if location_ids.contains_key(&location_id) {
+            continue;
+        }
+
+        let id = Uuid::new_v4();
+
+        diesel::insert_into(locations::table)
+            .values(locations::table.find(location_id).select((
+                id.into_sql::<diesel::sql_types::Uuid>(),
+                locations::address,
+                locations::display_name,
+                locations::longitude,
+                locations::latitude,
+                locations::timezone,
+            )))
+            .into_columns((
+                locations::id,
+                locations::address,
+                locations::display_name,
+                locations::longitude,
+                locations::latitude,
+                locations::timezone,
+            ))
+            .execute(conn)
+            .await?;
+
+        location_ids.insert(location_id, id);
+    }
+
+    let items = items
+        .into_iter()
+        .map(|item| {
+            Ok(ItineraryItem {
+                id: Uuid::new_v4(),
+                trip_id: copy.id,
+                location_id: item.location_id.map(|id| location_ids[&id]),
+                start_time: shift_time(item.start_time, shift)?,
+                end_time: item
+                    .end_time
+                    .map(|time| shift_time(time, shift))
+                    .transpose()?,
+                expense_id: item.expense_id.and_then(|id| expense_ids.get(&id).copied()),
+                // the calendar event belongs to the original, importing it again updates that one
+                ical_uid: None,
+                ..item
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    diesel::insert_into(itinerary_items::table)
+        .values(&items)
+        .execute(conn)
+        .await?;
+
+    let tasks = Task::find_by_trip(conn, &trip.id).await?;
+    let tasks: Vec<_> = tasks
+        .iter()
+        .map(|task| NewTask {
+            id: Uuid::new_v4(),
+            trip_id: copy.id,
+            title: &task.title,
+            description: &task.description,
+            completed: false,
+            position: &task.position,
+            urgency: &task.urgency,
+        })
+        .collect();
+
+    diesel::insert_into(tasks::table)
+        .values(&tasks)
+        .execute(conn)
+        .await?;
+
+    Ok(copy)
+}
@@ -1,56 +1,920 @@
-// use actix_web::web;
-// use serde::Deserialize;
-// use utoipa::ToSchema;
-// use uuid::Uuid;
-//
-// use crate::{
-//     app::AppState,
-//     auth::AuthenticatedUser,
-//     util::errors::{AppError, AppResult},
-//     views::EncodableItineraryItem,
-// };
-//
-// use super::helper::OkResponse;
-//
-// const TRIPS: &str = "trips";
-//
-// #[derive(Deserialize, ToSchema)]
-// pub struct UpdateItineraryBody {
-//     r#type: String,
-//     item_id: Option<Uuid>,
-//     item: Option<EncodableItineraryItem>,
-// }
-//
-// #[utoipa::path(
-//     tag = TRIPS,
-//     patch,
-//     path = "/api/v1/trips/{trip_id}/itinerary",
-//     responses (
-//         (status = 200, description = "Trip itinerary successfully updated", body = inline(OkResponse))
-//     ),
-//     security(
-//         ("jwt" = [])
-//     )
-//
-// )]
-// pub async fn update_itinerary(
-//     authenticated: AuthenticatedUser,
-//     path: web::Path<Uuid>,
-//     state: web::Data<AppState>,
-//     body: web::Json<UpdateItineraryBody>,
-// ) -> AppResult<OkResponse> {
-//     match body.r#type.as_str() {
-//         "add_item" => {
-//             let new_item = match body.item {
-//                 Some(item) => Ok(item),
-//                 None => Err(AppError::BadRequest("Missing itinerary item.".to_string())),
-//             }?;
-//         }
-//         "remove_item" => {}
-//         "update_item" => {}
-//         _ => Err(AppError::BadRequest(
-//             "Invalid type. Use one of the valid types 'add_item', 'remove_item', 'update_item'"
-//                 .to_string(),
-//         )),
-//     }
-// }
+use std::collections::HashMap;
+
+use actix_multipart::form::{MultipartForm, tempfile::TempFile};
+use actix_web::web::{self, Json};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+use diesel_async::{
+    AsyncConnection, AsyncPgConnection, RunQueryDsl, scoped_futures::ScopedFutureExt,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    app::AppState,
+    auth::{Editor, TripMember, Viewer},
+    controllers::trip::{load_flights, load_itinerary, load_stays},
+    feasibility,
+    ical::{self, EventTime},
+    models::{
+        expense::{Expense, ExpenseChanges},
+        itinerary_item::{DEFAULT_ACTIVITY_TYPE, ItineraryItem, ItineraryItemChanges},
+        trip::Trip,
+    },
+    replicache::merge::FieldVersions,
+    schedule,
+    schema::{itinerary_items, locations},
+    timezones::zone_at,
+    util::errors::{AppError, AppResult, ErrorResponse},
+    views::{
+        EncodableItineraryItem, EncodableItineraryWarning, EncodableLocation, EncodableTripDay,
+        ItineraryExpense,
+    },
+};
+
+const TRIPS: &str = "trips";
+
+const MAX_OPERATIONS: usize = 200;
+
+const MAX_IMPORTED_EVENTS: usize = 200;
+
+/// Longer trips aren't laid out by day, a year of days is already a lot to send.
+const MAX_DAYS: i64 = 366;
+
+const UNTITLED_EVENT: &str = "Untitled event";
+
+/// One change to the itinerary. Locations and costs sent with an item are stored along with it.
+#[derive(Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ItineraryOperation {
+    /// Adds an item, `title` and `start_time` are required. The item gets the `id` sent with it,
+    /// or a new one.
+    AddItem { item: EncodableItineraryItem },
+    /// Changes the fields sent in `item`, the rest are kept. A location replaces the item's
+    /// location, a cost updates the item's expense or adds one.
+    UpdateItem {
+        item_id: Uuid,
+        item: EncodableItineraryItem,
+    },
+    /// Removes the item. Its expense stays in the budget.
+    RemoveItem { item_id: Uuid },
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateItineraryBody {
+    /// Applied in order, either all of them or none
+    pub operations: Vec<ItineraryOperation>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct UpdateItineraryResponse {
+    /// The whole itinerary after the operations
+    pub itinerary: Vec<EncodableItineraryItem>,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    patch,
+    path = "/api/v1/trips/{trip_id}/itinerary",
+    request_body = UpdateItineraryBody,
+    responses(
+        (status = 200, description = "All operations were applied", body = UpdateItineraryResponse),
+        (status = 400, description = "An operation is missing a field, ends before it starts or there are more than 200 of them. Nothing was applied.", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not an owner or editor of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found, or an item isn't on the trip. Nothing was applied.", body = ErrorResponse),
+        (status = 409, description = "An added item's ID is already taken. Nothing was applied.", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn update_itinerary(
+    member: TripMember<Editor>,
+    state: web::Data<AppState>,
+    body: web::Json<UpdateItineraryBody>,
+) -> AppResult<Json<UpdateItineraryResponse>> {
+    let trip_id = member.trip_id();
+    let operations = body.into_inner().operations;
+
+    if operations.len() > MAX_OPERATIONS {
+        return Err(AppError::BadRequest(
+            "At most 200 operations can be applied at once.",
+        ));
+    }
+
+    let mut conn = state.db_connection().await?;
+
+    let itinerary = conn
+        .transaction::<_, AppError, _>(|conn| {
+            async move {
+                // stamped like mutations made now, so offline edits that are older lose to these
+                let timestamp = Utc::now().timestamp_millis() as f64;
+
+                for operation in operations {
+                    match operation {
+                        ItineraryOperation::AddItem { item } => {
+                            add_item(conn, &trip_id, item).await?
+                        }
+                        ItineraryOperation::UpdateItem { item_id, item } => {
+                            update_item(conn, &trip_id, &item_id, item, timestamp).await?
+                        }
+                        ItineraryOperation::RemoveItem { item_id } => {
+                            find_item(conn, &trip_id, &item_id).await?;
+                            ItineraryItem::delete(conn, &item_id).await?;
+                        }
+                    }
+                }
+
+                Trip::touch(conn, &trip_id).await?;
+
+                Ok(load_itinerary(conn, &trip_id).await?)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[trip_id], &member.user.id)
+        .await?;
+
+    Ok(Json(UpdateItineraryResponse { itinerary }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetItineraryWarningsResponse {
+    pub warnings: Vec<EncodableItineraryWarning>,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    get,
+    path = "/api/v1/trips/{trip_id}/itinerary/warnings",
+    responses(
+        (status = 200, description = "Overlapping entries, entries outside the trip's dates and places that can't be reached in time, over the itinerary, flights and accommodations", body = GetItineraryWarningsResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_itinerary_warnings(
+    member: TripMember<Viewer>,
+    state: web::Data<AppState>,
+) -> AppResult<Json<GetItineraryWarningsResponse>> {
+    let mut conn = state.db_connection().await?;
+
+    let trip = Trip::find(&mut conn, &member.trip_id()).await?;
+    let warnings = feasibility::check_trip(&mut conn, &trip).await?;
+
+    Ok(Json(GetItineraryWarningsResponse { warnings }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetTripDaysResponse {
+    /// IANA zone dates are taken in for entries without a location, left out when it's UTC
+    #[schema(example = "Asia/Tokyo")]
+    pub timezone: Option<String>,
+    /// Empty when the trip has no start or end date
+    pub days: Vec<EncodableTripDay>,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    get,
+    path = "/api/v1/trips/{trip_id}/days",
+    responses(
+        (status = 200, description = "Every date from the trip's start to its end, with the items, flights, accommodation and spending on it. Dates are on the clock where each entry takes place.", body = GetTripDaysResponse),
+        (status = 400, description = "The trip is longer than 366 days", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_trip_days(
+    member: TripMember<Viewer>,
+    state: web::Data<AppState>,
+) -> AppResult<Json<GetTripDaysResponse>> {
+    let mut conn = state.db_connection().await?;
+
+    let trip = Trip::find(&mut conn, &member.trip_id()).await?;
+
+    let (Some(start_date), Some(end_date)) = (trip.start_date, trip.end_date) else {
+        return Ok(Json(GetTripDaysResponse {
+            timezone: None,
+            days: Vec::new(),
+        }));
+    };
+
+    if (end_date - start_date).num_days() >= MAX_DAYS {
+        return Err(AppError::BadRequest(
+            "Only trips of at most 366 days can be shown by day.",
+        ));
+    }
+
+    let schedule = schedule::trip_days(
+        start_date,
+        end_date,
+        load_itinerary(&mut conn, &trip.id).await?,
+        load_flights(&mut conn, &trip.id).await?,
+        load_stays(&mut conn, &trip.id).await?,
+    );
+
+    Ok(Json(GetTripDaysResponse {
+        timezone: schedule.timezone.map(|zone| zone.name().to_string()),
+        days: schedule.days,
+    }))
+}
+
+#[derive(Debug, MultipartForm, ToSchema)]
+pub struct ItineraryImportForm {
+    #[multipart(limit = "1MB")]
+    #[schema(value_type = String, format = Binary, content_media_type = "text/calendar")]
+    pub file: TempFile,
+}
+
+/// Where an imported event takes place.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ImportedLocation {
+    /// The event's `LOCATION` text
+    pub address: String,
+    /// From the event's `GEO`, needed unless `location_id` is set
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// A location already on the trip with the same address, it's used rather than a new one
+    pub location_id: Option<Uuid>,
+}
+
+/// An event from an imported calendar, as it would go in the itinerary.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ImportedEvent {
+    /// Kept with the item, so importing the event again updates it
+    pub uid: String,
+    pub title: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// All-day items start at midnight where they take place, or in UTC without a location
+    pub all_day: bool,
+    pub notes: Option<String>,
+    pub location: Option<ImportedLocation>,
+    /// The item the event was imported into before, it's updated rather than added again
+    pub item_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct PreviewItineraryImportResponse {
+    pub events: Vec<ImportedEvent>,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    post,
+    path = "/api/v1/trips/{trip_id}/itinerary/import/preview",
+    request_body(
+        content = ItineraryImportForm,
+        content_type = "multipart/form-data",
+        description = "An iCalendar (.ics) file of at most 1MB",
+    ),
+    responses(
+        (status = 200, description = "The events the file would add or update, nothing is changed yet. A `LOCATION` that isn't on the trip and has no `GEO` coordinates is kept in the notes, since locations need coordinates.", body = PreviewItineraryImportResponse),
+        (status = 400, description = "Not an iCalendar file, or it has more than 200 events", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not an owner or editor of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn preview_itinerary_import(
+    member: TripMember<Editor>,
+    state: web::Data<AppState>,
+    MultipartForm(form): MultipartForm<ItineraryImportForm>,
+) -> AppResult<Json<PreviewItineraryImportResponse>> {
+    let trip_id = member.trip_id();
+
+    let events = web::block(move || {
+        let ics = std::fs::read(form.file.file.path()).map_err(|_| AppError::InternalError)?;
+
+        String::from_utf8(ics)
+            .ok()
+            .and_then(|ics| ical::parse(&ics))
+            .ok_or(AppError::BadRequest("The file isn't an iCalendar file."))
+    })
+    .await
+    .map_err(|_| AppError::InternalError)??;
+
+    if events.len() > MAX_IMPORTED_EVENTS {
+        return Err(AppError::BadRequest(
+            "At most 200 events can be imported at once.",
+        ));
+    }
+
+    let mut conn = state.db_connection().await?;
+
+    let places = trip_locations(&mut conn, &trip_id).await?;
+    let uids: Vec<&str> = events.iter().map(|event| event.uid.as_str()).collect();
+    let items = imported_items(&mut conn, &trip_id, &uids).await?;
+
+    let events = events
+        .into_iter()
+        .map(|event| imported_event(event, &places, &items))
+        .collect();
+
+    Ok(Json(PreviewItineraryImportResponse { events }))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ImportItineraryBody {
+    /// Events from the preview, left out ones aren't imported
+    pub events: Vec<ImportedEvent>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ImportItineraryResponse {
+    pub added: usize,
+    pub updated: usize,
+    /// The whole itinerary after the import
+    pub itinerary: Vec<EncodableItineraryItem>,
+}
+
+#[utoipa::path(
+    tag = TRIPS,
+    post,
+    path = "/api/v1/trips/{trip_id}/itinerary/import",
+    request_body = ImportItineraryBody,
+    responses(
+        (status = 200, description = "Events were added to the itinerary, ones imported before updated their items", body = ImportItineraryResponse),
+        (status = 400, description = "An event ends before it starts, has a location without coordinates or there are more than 200 of them. Nothing was imported.", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not an owner or editor of the trip", body = ErrorResponse),
+        (status = 404, description = "Trip not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn import_itinerary(
+    member: TripMember<Editor>,
+    state: web::Data<AppState>,
+    body: web::Json<ImportItineraryBody>,
+) -> AppResult<Json<ImportItineraryResponse>> {
+    let trip_id = member.trip_id();
+    let events = body.into_inner().events;
+
+    if events.len() > MAX_IMPORTED_EVENTS {
+        return Err(AppError::BadRequest(
+            "At most 200 events can be imported at once.",
+        ));
+    }
+
+    let mut conn = state.db_connection().await?;
+
+    let response = conn
+        .transaction::<_, AppError, _>(|conn| {
+            async move {
+                let timestamp = Utc::now().timestamp_millis() as f64;
+
+                let mut places = trip_locations(conn, &trip_id).await?;
+                let uids: Vec<&str> = events.iter().map(|event| event.uid.as_str()).collect();
+                let items = imported_items(conn, &trip_id, &uids).await?;
+
+                let (mut added, mut updated) = (0, 0);
+
+                for event in events {
+                    let item_id = items.get(&event.uid).copied();
+
+                    import_event(conn, &trip_id, event, item_id, &mut places, timestamp).await?;
+
+                    match item_id {
+                        Some(_) => updated += 1,
+                        None => added += 1,
+                    }
+                }
+
+                Trip::touch(conn, &trip_id).await?;
+
+                Ok(ImportItineraryResponse {
+                    added,
+                    updated,
+                    itinerary: load_itinerary(conn, &trip_id).await?,
+                })
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &[trip_id], &member.user.id)
+        .await?;
+
+    Ok(Json(response))
+}
+
+/// Adds the event to the itinerary, or updates `item_id` when it was imported before.
+async fn import_event(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+    event: ImportedEvent,
+    item_id: Option<Uuid>,
+    places: &mut HashMap<String, (Uuid, Option<Tz>)>,
+    timestamp: f64,
+) -> AppResult<()> {
+    check_times(event.start_time, event.end_time)?;
+
+    let location_id = match event.location {
+        Some(location) => {
+            let key = location_key(&location.address);
+            // matched again, the preview may be older than locations added since
+            let on_trip = location
+                .location_id
+                .filter(|id| places.values().any(|(place, _)| place == id))
+                .or_else(|| places.get(&key).map(|(id, _)| *id));
+
+            match on_trip {
+                Some(id) => Some(id),
+                None => {
+                    let (Some(latitude), Some(longitude)) = (location.latitude, location.longitude)
+                    else {
+                        return Err(AppError::BadRequest(
+                            "Imported locations need coordinates or a location on the trip.",
+                        ));
+                    };
+
+                    let location = EncodableLocation {
+                        id: None,
+                        display_name: None,
+                        address: location.address,
+                        longitude,
+                        latitude,
+                    };
+
+                    let zone = zone_at(location.latitude, location.longitude);
+                    let id = insert_location(conn, location).await?;
+
+                    places.insert(key, (id, zone));
+
+                    Some(id)
+                }
+            }
+        }
+        None => None,
+    };
+
+    let Some(item_id) = item_id else {
+        ItineraryItem {
+            id: Uuid::new_v4(),
+            trip_id: *trip_id,
+            title: event.title,
+            activity_type: DEFAULT_ACTIVITY_TYPE.to_string(),
+            location_id,
+            start_time: event.start_time,
+            end_time: event.end_time,
+            expense_id: None,
+            notes: event.notes.unwrap_or_default(),
+            description: None,
+            all_day: event.all_day,
+            ical_uid: Some(event.uid),
+        }
+        .insert(conn)
+        .await?;
+
+        return Ok(());
+    };
+
+    let mut versions =
+        FieldVersions::new(ItineraryItem::lock_field_versions(conn, &item_id).await?);
+
+    ItineraryItemChanges {
+        title: versions.merge("title", Some(event.title), timestamp),
+        location_id: versions.merge("location_id", location_id, timestamp),
+        start_time: versions.merge("start_time", Some(event.start_time), timestamp),
+        end_time: versions.merge("end_time", event.end_time, timestamp),
+        notes: versions.merge("notes", event.notes, timestamp),
+        all_day: versions.merge("all_day", Some(event.all_day), timestamp),
+        field_versions: versions.into_changes(),
+        ..Default::default()
+    }
+    .apply(conn, &item_id)
+    .await?;
+
+    Ok(())
+}
+
+/// Addresses and names of the locations the trip's itinerary, flights and accommodations use,
+/// so imported events can be matched to them.
+async fn trip_locations(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+) -> QueryResult<HashMap<String, (Uuid, Option<Tz>)>> {
+    let ids = Trip::location_ids(conn, trip_id).await?;
+
+    let locations = locations::table
+        .filter(locations::id.eq_any(ids))
+        .select((
+            locations::id,
+            locations::address,
+            locations::display_name,
+            locations::timezone,
+        ))
+        .load::<(Uuid, String, Option<String>, Option<String>)>(conn)
+        .await?;
+
+    let mut places = HashMap::new();
+
+    for (id, address, display_name, zone) in locations {
+        let zone = zone.and_then(|zone| zone.parse().ok());
+
+        for name in [Some(address), display_name].into_iter().flatten() {
+            places.entry(location_key(&name)).or_insert((id, zone));
+        }
+    }
+
+    Ok(places)
+}
+
+fn location_key(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Items on the trip that events with these UIDs were imported into, by UID. Events exported from
+/// the trip find the item they were made from.
+async fn imported_items(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+    uids: &[&str],
+) -> QueryResult<HashMap<String, Uuid>> {
+    let exported: HashMap<Uuid, &str> = uids
+        .iter()
+        .filter_map(|uid| {
+            let id = uid
+                .strip_prefix("itinerary-item-")?
+                .strip_suffix("@journly")?;
+
+            Some((Uuid::parse_str(id).ok()?, *uid))
+        })
+        .collect();
+
+    let mut items: HashMap<String, Uuid> = itinerary_items::table
+        .filter(itinerary_items::trip_id.eq(trip_id))
+        .filter(itinerary_items::ical_uid.eq_any(uids))
+        .select((
+            itinerary_items::ical_uid.assume_not_null(),
+            itinerary_items::id,
+        ))
+        .load::<(String, Uuid)>(conn)
+        .await?
+        .into_iter()
+        .collect();
+
+    let exported_ids: Vec<Uuid> = itinerary_items::table
+        .filter(itinerary_items::trip_id.eq(trip_id))
+        .filter(itinerary_items::id.eq_any(exported.keys().copied().collect::<Vec<_>>()))
+        .select(itinerary_items::id)
+        .load(conn)
+        .await?;
+
+    for id in exported_ids {
+        items.entry(exported[&id].to_string()).or_insert(id);
+    }
+
+    Ok(items)
+}
+
+/// The time on the clock in `zone`, as UTC.
+fn from_local(time: NaiveDateTime, zone: Option<Tz>) -> DateTime<Utc> {
+    zone.and_then(|zone| zone.from_local_datetime(&time).earliest())
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(|| time.and_utc())
+}
+
+fn midnight(date: NaiveDate, zone: Option<Tz>) -> DateTime<Utc> {
+    from_local(date.and_time(Default::default()), zone)
+}
+
+fn imported_event(
+    event: ical::Event,
+    places: &HashMap<String, (Uuid, Option<Tz>)>,
+    items: &HashMap<String, Uuid>,
+) -> ImportedEvent {
+    let mut notes = event.description;
+    let mut zone = None;
+
+    let address = event.location.or_else(|| {
+        event
+            .geo
+            .map(|(latitude, longitude)| format!("{latitude}, {longitude}"))
+    });
+
+    let location = address.and_then(|address| {
+        let (latitude, longitude) = event.geo.unzip();
+
+        if let Some((id, place_zone)) = places.get(&location_key(&address)) {
+            zone = *place_zone;
+
+            return Some(ImportedLocation {
+                address,
+                latitude,
+                longitude,
+                location_id: Some(*id),
+            });
+        }
+
+        if let Some((latitude, longitude)) = event.geo {
+            zone = zone_at(latitude, longitude);
+
+            return Some(ImportedLocation {
+                address,
+                latitude: Some(latitude),
+                longitude: Some(longitude),
+                location_id: None,
+            });
+        }
+
+        // locations need coordinates, so the address is only kept as text
+        notes = Some(match notes.take() {
+            Some(notes) => format!("{notes}\n\n{address}"),
+            None => address,
+        });
+
+        None
+    });
+
+    let (start_time, end_time, all_day) = match (event.start, event.end) {
+        (EventTime::Date(first), end) => {
+            // the end of an all-day event is the day after it
+            let last = match end {
+                Some(EventTime::Date(end)) => end.pred_opt().filter(|last| *last > first),
+                _ => None,
+            };
+
+            (
+                midnight(first, zone),
+                last.map(|last| midnight(last, zone)),
+                true,
+            )
+        }
+        (start, end) => {
+            let utc = |time| match time {
+                EventTime::DateTime(time) => time,
+                EventTime::Floating(time) => from_local(time, zone),
+                EventTime::Date(date) => midnight(date, zone),
+            };
+
+            let start = utc(start);
+            let end = end.map(utc).filter(|end| *end >= start);
+
+            (start, end, false)
+        }
+    };
+
+    ImportedEvent {
+        item_id: items.get(&event.uid).copied(),
+        uid: event.uid,
+        title: match event.summary.trim() {
+            "" => UNTITLED_EVENT.to_string(),
+            summary => summary.to_string(),
+        },
+        start_time,
+        end_time,
+        all_day,
+        notes,
+        location,
+    }
+}
+
+fn check_times(start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>) -> AppResult<()> {
+    match end_time {
+        Some(end_time) if end_time < start_time => Err(AppError::BadRequest(
+            "An itinerary item can't end before it starts.",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The item, as long as it's on the trip.
+async fn find_item(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+    item_id: &Uuid,
+) -> AppResult<ItineraryItem> {
+    Ok(itinerary_items::table
+        .find(item_id)
+        .filter(itinerary_items::trip_id.eq(trip_id))
+        .select(ItineraryItem::as_select())
+        .first(conn)
+        .await?)
+}
+
+/// The trip's location when `location.id` is set, the item's `current` one when nothing about it
+/// changed, and a new one otherwise.
+async fn link_location(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+    current: Option<Uuid>,
+    location: EncodableLocation,
+) -> AppResult<Uuid> {
+    if let Some(id) = location.id {
+        if !Trip::location_ids(conn, trip_id).await?.contains(&id) {
+            return Err(AppError::BadRequest(
+                "Linked locations must be on the trip.",
+            ));
+        }
+
+        return Ok(id);
+    }
+
+    if let Some(current) = current {
+        let (address, display_name, longitude, latitude) = locations::table
+            .find(current)
+            .select((
+                locations::address,
+                locations::display_name,
+                locations::longitude,
+                locations::latitude,
+            ))
+            .first::<(String, Option<String>, f64, f64)>(conn)
+            .await?;
+
+        if address == location.address
+            && display_name == location.display_name
+            && longitude == location.longitude
+            && latitude == location.latitude
+        {
+            return Ok(current);
+        }
+    }
+
+    Ok(insert_location(conn, location).await?)
+}
+
+async fn insert_location(
+    conn: &mut AsyncPgConnection,
+    location: EncodableLocation,
+) -> QueryResult<Uuid> {
+    diesel::insert_into(locations::table)
+        .values((
+            locations::id.eq(Uuid::new_v4()),
+            locations::address.eq(location.address),
+            locations::display_name.eq(location.display_name),
+            locations::longitude.eq(location.longitude),
+            locations::latitude.eq(location.latitude),
+            locations::timezone
+                .eq(zone_at(location.latitude, location.longitude).map(|zone| zone.name())),
+        ))
+        .returning(locations::id)
+        .get_result(conn)
+        .await
+}
+
+/// Adds the cost to the budget as an expense named after the item.
+async fn insert_expense(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+    title: &str,
+    cost: ItineraryExpense,
+) -> QueryResult<Uuid> {
+    let expense = Expense {
+        id: Uuid::new_v4(),
+        trip_id: *trip_id,
+        title: Some(title.to_string()),
+        cost: cost.cost,
+        currency: cost.currency,
+        description: None,
+        category: None,
+    };
+
+    expense.insert(conn).await?;
+
+    Ok(expense.id)
+}
+
+async fn add_item(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+    item: EncodableItineraryItem,
+) -> AppResult<()> {
+    let (Some(title), Some(start_time)) = (item.title, item.start_time) else {
+        return Err(AppError::BadRequest(
+            "New itinerary items need a title and a start time.",
+        ));
+    };
+
+    check_times(start_time, item.end_time)?;
+
+    let location_id = match item.location {
+        Some(location) => Some(link_location(conn, trip_id, None, location).await?),
+        None => None,
+    };
+
+    let expense_id = match item.cost {
+        Some(cost) => Some(insert_expense(conn, trip_id, &title, cost).await?),
+        None => None,
+    };
+
+    let inserted = ItineraryItem {
+        id: item.id.unwrap_or_else(Uuid::new_v4),
+        trip_id: *trip_id,
+        title,
+        activity_type: item
+            .activity_type
+            .unwrap_or_else(|| DEFAULT_ACTIVITY_TYPE.to_string()),
+        location_id,
+        start_time,
+        end_time: item.end_time,
+        expense_id,
+        notes: item.notes.unwrap_or_default(),
+        description: None,
+        all_day: false,
+        ical_uid: None,
+    }
+    .insert(conn)
+    .await?;
+
+    if inserted == 0 {
+        return Err(AppError::Conflict);
+    }
+
+    Ok(())
+}
+
+async fn update_item(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+    item_id: &Uuid,
+    update: EncodableItineraryItem,
+    timestamp: f64,
+) -> AppResult<()> {
+    let item = find_item(conn, trip_id, item_id).await?;
+
+    check_times(
+        update.start_time.unwrap_or(item.start_time),
+        update.end_time.or(item.end_time),
+    )?;
+
+    let location_id = match update.location {
+        Some(location) => Some(link_location(conn, trip_id, item.location_id, location).await?),
+        None => None,
+    };
+
+    let mut expense_id = None;
+
+    if let Some(cost) = update.cost {
+        match item.expense_id {
+            Some(id) => {
+                let mut versions =
+                    FieldVersions::new(Expense::lock_field_versions(conn, &id).await?);
+
+                ExpenseChanges {
+                    cost: versions.merge("cost", Some(cost.cost), timestamp),
+                    currency: versions.merge("currency", Some(cost.currency), timestamp),
+                    field_versions: versions.into_changes(),
+                    ..Default::default()
+                }
+                .apply(conn, &id)
+                .await?;
+            }
+            None => {
+                let title = update.title.as_deref().unwrap_or(&item.title);
+
+                expense_id = Some(insert_expense(conn, trip_id, title, cost).await?);
+            }
+        }
+    }
+
+    let mut versions =
+        FieldVersions::new(ItineraryItem::lock_field_versions(conn, &item.id).await?);
+
+    ItineraryItemChanges {
+        title: versions.merge("title", update.title, timestamp),
+        activity_type: versions.merge("activity_type", update.activity_type, timestamp),
+        location_id: versions.merge("location_id", location_id, timestamp),
+        start_time: versions.merge("start_time", update.start_time, timestamp),
+        end_time: versions.merge("end_time", update.end_time, timestamp),
+        expense_id: versions.merge("expense_id", expense_id, timestamp),
+        notes: versions.merge("notes", update.notes, timestamp),
+        field_versions: versions.into_changes(),
+        ..Default::default()
+    }
+    .apply(conn, &item.id)
+    .await?;
+
+    Ok(())
+}
@@ -2,7 +2,8 @@ use crate::{
     app::AppState,
     auth::AuthenticatedUser,
     controllers::helper::OkResponse,
-    models::user::User,
+    models::{trip::Trip, user::User, user_trip::UserTrip},
+    purge,
     s3_client::get_file_extension,
     util::errors::{AppError, AppResult, ErrorResponse},
     views::EncodableUser,
@@ -15,7 +16,7 @@ use argon2::{
 };
 use base64::{Engine, engine::general_purpose};
 use diesel::{ExpressionMethods, result::Error::NotFound};
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncConnection, RunQueryDsl, scoped_futures::ScopedFutureExt};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
@@ -144,13 +145,33 @@ pub async fn delete_user(
 
     let mut conn = state.db_connection().await?;
 
-    let result = User::delete(&mut conn, &user_id).await;
+    let trip_ids = UserTrip::find_trip_ids(&mut conn, &user_id).await?;
 
-    match result {
-        Ok(_) => Ok(OkResponse::new()),
-        Err(NotFound) => Err(AppError::NotFound),
-        Err(_) => Err(AppError::InternalError),
+    // trips can't be left without an owner, so they're handed over in the same transaction
+    let (deleted, files) = conn
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+            async move {
+                let files = Trip::hand_over_owned(conn, &user_id).await?;
+                let deleted = User::delete(conn, &user_id).await?;
+
+                Ok((deleted, files))
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    if deleted == 0 {
+        return Err(AppError::NotFound);
     }
+
+    purge::delete_files(state.s3.as_ref(), &files).await;
+
+    state
+        .pokes
+        .trips_changed(&mut conn, &trip_ids, &user_id)
+        .await?;
+
+    Ok(OkResponse::new())
 }
 
 #[derive(Deserialize, Serialize, ToSchema)]
@@ -415,8 +436,8 @@ pub async fn change_profile_picture(
             }
 
             // delete old pfp
-            if user.avatar.is_some() {
-                let key = s3_client.get_key_from_url(&user.avatar.unwrap());
+            if let Some(old_avatar) = user.avatar {
+                let key = s3_client.get_key_from_url(&old_avatar);
 
                 s3_client.delete_file(&key).await;
             }
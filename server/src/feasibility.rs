@@ -0,0 +1,542 @@
+//! Checks whether a trip's itinerary can be followed as planned.
+//!
+//! Itinerary items, flights and accommodations are laid out on one timeline and checked for
+//! entries that overlap, entries outside the trip's dates, and consecutive places too far apart
+//! to travel between in the time left. Flights cover the distance between their airports, any
+//! other travel is assumed to go at `GROUND_SPEED_KMH`.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::{
+    models::trip::Trip,
+    schema::{accommodations, flights, itinerary_items, locations},
+    views::{EncodableItineraryWarning, EncodableTimelineEntry, TimelineEntryType},
+};
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Generous for getting around by road, anything further in the time needs a flight.
+const GROUND_SPEED_KMH: f64 = 80.0;
+
+/// Places closer than this are taken to be the same place.
+const NEARBY_KM: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Great-circle distance between two points, using the haversine formula.
+pub fn distance_km(a: Point, b: Point) -> f64 {
+    let (lat_a, lat_b) = (a.latitude.to_radians(), b.latitude.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (b.longitude - a.longitude).to_radians();
+
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    reference: EncodableTimelineEntry,
+    start: DateTime<Utc>,
+    /// Same as `start` for entries without a duration
+    end: DateTime<Utc>,
+    /// Where the entry starts and ends, only a flight's differ
+    from: Option<Point>,
+    to: Option<Point>,
+    /// Zones of `from` and `to`, trip dates are compared with local dates where they're known
+    from_zone: Option<Tz>,
+    to_zone: Option<Tz>,
+}
+
+impl Entry {
+    fn start_date(&self) -> NaiveDate {
+        local_date(self.start, self.from_zone)
+    }
+
+    fn end_date(&self) -> NaiveDate {
+        local_date(self.end, self.to_zone)
+    }
+}
+
+fn local_date(time: DateTime<Utc>, zone: Option<Tz>) -> NaiveDate {
+    match zone {
+        Some(zone) => time.with_timezone(&zone).date_naive(),
+        None => time.date_naive(),
+    }
+}
+
+#[derive(Debug, Default)]
+struct Timeline {
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+    /// Itinerary items with a time of day, and flights
+    timed: Vec<Entry>,
+    /// All-day itinerary items, only their dates matter
+    all_day: Vec<Entry>,
+    /// Accommodations from check-in to check-out. Staying somewhere doesn't clash with doing
+    /// things meanwhile, so these only overlap with each other.
+    stays: Vec<Entry>,
+}
+
+impl Timeline {
+    fn warnings(mut self) -> Vec<EncodableItineraryWarning> {
+        let mut warnings = Vec::new();
+
+        overlaps(&mut self.timed, &mut warnings);
+        overlaps(&mut self.stays, &mut warnings);
+
+        for entry in self.timed.iter().chain(&self.all_day).chain(&self.stays) {
+            let too_early = self
+                .start_date
+                .is_some_and(|start_date| entry.start_date() < start_date);
+            let too_late = self
+                .end_date
+                .is_some_and(|end_date| entry.end_date() > end_date);
+
+            if too_early || too_late {
+                warnings.push(EncodableItineraryWarning::OutsideTripDates {
+                    entry: entry.reference.clone(),
+                });
+            }
+        }
+
+        // a stay is somewhere to be at check-in and at check-out
+        let mut stops = self.timed;
+        stops.extend(self.stays.into_iter().flat_map(|stay| {
+            [
+                Entry {
+                    end: stay.start,
+                    ..stay.clone()
+                },
+                Entry {
+                    start: stay.end,
+                    ..stay
+                },
+            ]
+        }));
+        stops.sort_by_key(|stop| stop.start);
+
+        let mut previous: Option<(&Entry, Point)> = None;
+
+        for stop in stops.iter() {
+            if let (Some((last, at)), Some(from)) = (previous, stop.from) {
+                let distance = distance_km(at, from);
+                let available_minutes = (stop.start - last.end).num_minutes();
+                let required_minutes = (distance / GROUND_SPEED_KMH * 60.0).ceil() as i64;
+
+                // entries that overlap were already reported as such
+                if distance > NEARBY_KM
+                    && available_minutes >= 0
+                    && required_minutes > available_minutes
+                {
+                    warnings.push(EncodableItineraryWarning::Unreachable {
+                        from: last.reference.clone(),
+                        to: stop.reference.clone(),
+                        distance_km: (distance * 10.0).round() / 10.0,
+                        available_minutes,
+                        required_minutes,
+                    });
+                }
+            }
+
+            // places aren't known for every entry, travel is checked from the last known one
+            if let Some(to) = stop.to {
+                previous = Some((stop, to));
+            }
+        }
+
+        warnings
+    }
+}
+
+/// Reports every pair of entries that share some time.
+fn overlaps(entries: &mut [Entry], warnings: &mut Vec<EncodableItineraryWarning>) {
+    // longer entries go first on ties, so an instant at the start of a span is inside it
+    entries.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+
+    for (i, first) in entries.iter().enumerate() {
+        for second in entries[i + 1..]
+            .iter()
+            .take_while(|second| second.start < first.end)
+        {
+            warnings.push(EncodableItineraryWarning::Overlap {
+                first: first.reference.clone(),
+                second: second.reference.clone(),
+            });
+        }
+    }
+}
+
+fn entry(entry_type: TimelineEntryType, id: Uuid, title: Option<String>) -> EncodableTimelineEntry {
+    EncodableTimelineEntry {
+        entry_type,
+        id,
+        title,
+    }
+}
+
+fn parse_zone(zone: Option<String>) -> Option<Tz> {
+    zone?.parse().ok()
+}
+
+type ItemRow = (
+    Uuid,
+    String,
+    DateTime<Utc>,
+    Option<DateTime<Utc>>,
+    bool,
+    Option<(f64, f64, Option<String>)>,
+);
+
+type FlightRow = (
+    Uuid,
+    Option<String>,
+    Option<DateTime<Utc>>,
+    Option<DateTime<Utc>>,
+    Option<Uuid>,
+    Option<Uuid>,
+);
+
+type StayRow = (
+    Uuid,
+    Option<DateTime<Utc>>,
+    Option<DateTime<Utc>>,
+    Option<Uuid>,
+);
+
+/// Warnings for the trip's itinerary, flights and accommodations.
+pub async fn check_trip(
+    conn: &mut AsyncPgConnection,
+    trip: &Trip,
+) -> QueryResult<Vec<EncodableItineraryWarning>> {
+    let items = itinerary_items::table
+        .left_join(locations::table)
+        .filter(itinerary_items::trip_id.eq(trip.id))
+        .select((
+            itinerary_items::id,
+            itinerary_items::title,
+            itinerary_items::start_time,
+            itinerary_items::end_time,
+            itinerary_items::all_day,
+            (
+                locations::latitude,
+                locations::longitude,
+                locations::timezone,
+            )
+                .nullable(),
+        ))
+        .load::<ItemRow>(conn)
+        .await?;
+
+    let flights = flights::table
+        .filter(flights::trip_id.eq(trip.id))
+        .select((
+            flights::id,
+            flights::flight_code,
+            flights::departure_datetime,
+            flights::arrival_datetime,
+            flights::departure_location,
+            flights::arrival_location,
+        ))
+        .load::<FlightRow>(conn)
+        .await?;
+
+    let stays = accommodations::table
+        .filter(accommodations::trip_id.eq(trip.id))
+        .select((
+            accommodations::id,
+            accommodations::check_in_datetime,
+            accommodations::check_out_datetime,
+            accommodations::location,
+        ))
+        .load::<StayRow>(conn)
+        .await?;
+
+    let location_ids: Vec<Uuid> = flights
+        .iter()
+        .flat_map(|flight| [flight.4, flight.5])
+        .chain(stays.iter().map(|stay| stay.3))
+        .flatten()
+        .collect();
+
+    let places: HashMap<Uuid, (Point, Option<Tz>)> = locations::table
+        .filter(locations::id.eq_any(location_ids))
+        .select((
+            locations::id,
+            locations::latitude,
+            locations::longitude,
+            locations::timezone,
+        ))
+        .load::<(Uuid, f64, f64, Option<String>)>(conn)
+        .await?
+        .into_iter()
+        .map(|(id, latitude, longitude, zone)| {
+            let point = Point {
+                latitude,
+                longitude,
+            };
+
+            (id, (point, parse_zone(zone)))
+        })
+        .collect();
+    let place = |id: Option<Uuid>| id.and_then(|id| places.get(&id).copied()).unzip();
+
+    let mut timeline = Timeline {
+        start_date: trip.start_date,
+        end_date: trip.end_date,
+        ..Default::default()
+    };
+
+    for (id, title, start, end, all_day, location) in items {
+        let (point, zone) = location
+            .map(|(latitude, longitude, zone)| {
+                let point = Point {
+                    latitude,
+                    longitude,
+                };
+
+                (point, parse_zone(zone))
+            })
+            .unzip();
+        let zone = zone.flatten();
+
+        let item = Entry {
+            reference: entry(TimelineEntryType::ItineraryItem, id, Some(title)),
+            start,
+            end: end.unwrap_or(start),
+            from: point,
+            to: point,
+            from_zone: zone,
+            to_zone: zone,
+        };
+
+        if all_day {
+            timeline.all_day.push(item);
+        } else {
+            timeline.timed.push(item);
+        }
+    }
+
+    for (id, code, departure, arrival, from, to) in flights {
+        let (Some(start), Some(end)) = (departure.or(arrival), arrival.or(departure)) else {
+            continue;
+        };
+
+        let (from, from_zone) = place(from);
+        let (to, to_zone) = place(to);
+
+        timeline.timed.push(Entry {
+            reference: entry(TimelineEntryType::Flight, id, code),
+            start,
+            end,
+            from,
+            to,
+            from_zone: from_zone.flatten(),
+            to_zone: to_zone.flatten(),
+        });
+    }
+
+    for (id, check_in, check_out, location) in stays {
+        let (Some(start), Some(end)) = (check_in.or(check_out), check_out.or(check_in)) else {
+            continue;
+        };
+
+        let (point, zone) = place(location);
+
+        timeline.stays.push(Entry {
+            reference: entry(TimelineEntryType::Accommodation, id, None),
+            start,
+            end,
+            from: point,
+            to: point,
+            from_zone: zone.flatten(),
+            to_zone: zone.flatten(),
+        });
+    }
+
+    Ok(timeline.warnings())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, TimeZone};
+
+    use super::*;
+
+    const AMSTERDAM: Point = Point {
+        latitude: 52.3676,
+        longitude: 4.9041,
+    };
+    const ROTTERDAM: Point = Point {
+        latitude: 51.9244,
+        longitude: 4.4777,
+    };
+    const TOKYO: Point = Point {
+        latitude: 35.6762,
+        longitude: 139.6503,
+    };
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, day, hour, 0, 0).unwrap()
+    }
+
+    fn item(title: &str, start: DateTime<Utc>, end: DateTime<Utc>, place: Point) -> Entry {
+        Entry {
+            reference: entry(
+                TimelineEntryType::ItineraryItem,
+                Uuid::new_v4(),
+                Some(title.to_string()),
+            ),
+            start,
+            end,
+            from: Some(place),
+            to: Some(place),
+            from_zone: None,
+            to_zone: None,
+        }
+    }
+
+    fn kinds(warnings: &[EncodableItineraryWarning]) -> Vec<&'static str> {
+        warnings
+            .iter()
+            .map(|warning| match warning {
+                EncodableItineraryWarning::Overlap { .. } => "overlap",
+                EncodableItineraryWarning::OutsideTripDates { .. } => "outside",
+                EncodableItineraryWarning::Unreachable { .. } => "unreachable",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn distances_are_great_circle() {
+        assert!((distance_km(AMSTERDAM, ROTTERDAM) - 57.0).abs() < 1.0);
+        assert!((distance_km(AMSTERDAM, TOKYO) - 9_300.0).abs() < 50.0);
+        assert_eq!(distance_km(TOKYO, TOKYO), 0.0);
+    }
+
+    #[test]
+    fn a_feasible_day_has_no_warnings() {
+        let timeline = Timeline {
+            start_date: NaiveDate::from_ymd_opt(2025, 6, 1),
+            end_date: NaiveDate::from_ymd_opt(2025, 6, 2),
+            timed: vec![
+                item("Museum", at(1, 9), at(1, 12), AMSTERDAM),
+                item("Harbour", at(1, 14), at(1, 16), ROTTERDAM),
+            ],
+            ..Default::default()
+        };
+
+        assert!(timeline.warnings().is_empty());
+    }
+
+    #[test]
+    fn overlaps_and_dates_outside_the_trip_are_reported() {
+        let timeline = Timeline {
+            start_date: NaiveDate::from_ymd_opt(2025, 6, 2),
+            end_date: NaiveDate::from_ymd_opt(2025, 6, 3),
+            timed: vec![
+                item("Museum", at(2, 9), at(2, 12), AMSTERDAM),
+                item("Lunch", at(2, 11), at(2, 13), AMSTERDAM),
+                item("Late", at(4, 9), at(4, 10), AMSTERDAM),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(kinds(&timeline.warnings()), ["overlap", "outside"]);
+    }
+
+    #[test]
+    fn trip_dates_are_local_dates() {
+        // 23:00 UTC on the 31st is already the 1st in Tokyo
+        let mut tour = item(
+            "Night tour",
+            at(1, 0) - TimeDelta::hours(1),
+            at(1, 1),
+            TOKYO,
+        );
+        tour.from_zone = Some(Tz::Asia__Tokyo);
+        tour.to_zone = Some(Tz::Asia__Tokyo);
+
+        let timeline = Timeline {
+            start_date: NaiveDate::from_ymd_opt(2025, 6, 1),
+            timed: vec![tour],
+            ..Default::default()
+        };
+
+        assert!(timeline.warnings().is_empty());
+    }
+
+    #[test]
+    fn flights_cover_distance_but_ground_travel_has_to_fit() {
+        let flight = Entry {
+            reference: entry(TimelineEntryType::Flight, Uuid::new_v4(), None),
+            start: at(1, 12),
+            end: at(2, 6),
+            from: Some(AMSTERDAM),
+            to: Some(TOKYO),
+            from_zone: None,
+            to_zone: None,
+        };
+
+        let timeline = Timeline {
+            timed: vec![
+                item("Canals", at(1, 8), at(1, 10), AMSTERDAM),
+                flight.clone(),
+                item("Shrine", at(2, 8), at(2, 10), TOKYO),
+            ],
+            ..Default::default()
+        };
+
+        assert!(timeline.warnings().is_empty());
+
+        let timeline = Timeline {
+            timed: vec![
+                item("Canals", at(1, 8), at(1, 10), AMSTERDAM),
+                item("Shrine", at(1, 11), at(1, 12), TOKYO),
+            ],
+            ..Default::default()
+        };
+
+        let warnings = timeline.warnings();
+        assert_eq!(kinds(&warnings), ["unreachable"]);
+
+        let EncodableItineraryWarning::Unreachable {
+            available_minutes, ..
+        } = warnings[0]
+        else {
+            unreachable!();
+        };
+        assert_eq!(available_minutes, 60);
+    }
+
+    #[test]
+    fn stays_only_overlap_with_each_other() {
+        let stay = |start, end| Entry {
+            reference: entry(TimelineEntryType::Accommodation, Uuid::new_v4(), None),
+            start,
+            end,
+            from: Some(AMSTERDAM),
+            to: Some(AMSTERDAM),
+            from_zone: None,
+            to_zone: None,
+        };
+
+        let timeline = Timeline {
+            timed: vec![item("Museum", at(2, 9), at(2, 12), AMSTERDAM)],
+            stays: vec![stay(at(1, 15), at(3, 10)), stay(at(3, 9), at(5, 10))],
+            ..Default::default()
+        };
+
+        assert_eq!(kinds(&timeline.warnings()), ["overlap"]);
+    }
+}
@@ -0,0 +1,583 @@
+//! iCalendar (RFC 5545) rendering of trips, and reading events from calendars that are imported.
+//!
+//! Only what calendar apps need to show a trip is written: one `VEVENT` per entry, with times in
+//! UTC so no `VTIMEZONE` blocks are needed. UIDs are derived from row IDs, so importing a trip
+//! again updates its events rather than adding them twice.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use sha2::{Digest, Sha256};
+
+const PRODUCT_ID: &str = "-//Journly//Trip Calendar//EN";
+
+/// Content lines longer than this many octets are folded.
+const MAX_LINE_OCTETS: usize = 75;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventTime {
+    DateTime(DateTime<Utc>),
+    /// All-day, the date is on the traveler's clock
+    Date(NaiveDate),
+    /// On the clock wherever the event happens, for imported times without a known zone
+    Floating(NaiveDateTime),
+}
+
+impl EventTime {
+    fn add(&self, duration: TimeDelta) -> Option<EventTime> {
+        match self {
+            EventTime::DateTime(time) => time.checked_add_signed(duration).map(EventTime::DateTime),
+            EventTime::Date(date) => date
+                .checked_add_signed(TimeDelta::days(duration.num_days()))
+                .map(EventTime::Date),
+            EventTime::Floating(time) => time.checked_add_signed(duration).map(EventTime::Floating),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub uid: String,
+    pub summary: String,
+    pub start: EventTime,
+    pub end: Option<EventTime>,
+    pub location: Option<String>,
+    /// Latitude and longitude
+    pub geo: Option<(f64, f64)>,
+    pub description: Option<String>,
+    /// Bumped on every change, so calendars know which copy of an event is newer
+    pub sequence: Option<i32>,
+}
+
+#[derive(Debug, Default)]
+pub struct Calendar {
+    pub name: Option<String>,
+    pub events: Vec<Event>,
+}
+
+impl Calendar {
+    /// The calendar as an `.ics` file, `stamp` is when it was made.
+    pub fn render(&self, stamp: DateTime<Utc>) -> String {
+        let mut ics = String::new();
+
+        line(&mut ics, "BEGIN", "VCALENDAR");
+        line(&mut ics, "VERSION", "2.0");
+        line(&mut ics, "PRODID", PRODUCT_ID);
+        line(&mut ics, "CALSCALE", "GREGORIAN");
+        line(&mut ics, "METHOD", "PUBLISH");
+
+        if let Some(name) = &self.name {
+            line(&mut ics, "X-WR-CALNAME", &escape(name));
+        }
+
+        for event in self.events.iter() {
+            line(&mut ics, "BEGIN", "VEVENT");
+            line(&mut ics, "UID", &escape(&event.uid));
+            line(&mut ics, "DTSTAMP", &format_date_time(stamp));
+            time_line(&mut ics, "DTSTART", &event.start);
+
+            if let Some(end) = &event.end {
+                time_line(&mut ics, "DTEND", end);
+            }
+
+            line(&mut ics, "SUMMARY", &escape(&event.summary));
+
+            if let Some(location) = &event.location {
+                line(&mut ics, "LOCATION", &escape(location));
+            }
+
+            if let Some((latitude, longitude)) = event.geo {
+                line(&mut ics, "GEO", &format!("{latitude};{longitude}"));
+            }
+
+            if let Some(description) = &event.description {
+                line(&mut ics, "DESCRIPTION", &escape(description));
+            }
+
+            if let Some(sequence) = event.sequence {
+                line(&mut ics, "SEQUENCE", &sequence.to_string());
+            }
+
+            line(&mut ics, "END", "VEVENT");
+        }
+
+        line(&mut ics, "END", "VCALENDAR");
+
+        ics
+    }
+}
+
+fn time_line(ics: &mut String, name: &str, time: &EventTime) {
+    match time {
+        EventTime::DateTime(time) => line(ics, name, &format_date_time(*time)),
+        EventTime::Date(date) => line(
+            ics,
+            &format!("{name};VALUE=DATE"),
+            &date.format("%Y%m%d").to_string(),
+        ),
+        EventTime::Floating(time) => line(ics, name, &time.format(LOCAL_FORMAT).to_string()),
+    }
+}
+
+fn format_date_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+const LOCAL_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Reads the events in an `.ics` file, or `None` when it isn't a calendar. Cancelled events are
+/// left out and recurring ones only give their first occurrence. Events without a UID get one
+/// made from their start and summary, so importing them again still finds them.
+pub fn parse(ics: &str) -> Option<Vec<Event>> {
+    let lines = unfold(ics);
+
+    if !lines
+        .first()
+        .is_some_and(|line| line.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return None;
+    }
+
+    let mut events = Vec::new();
+    let mut uids = HashSet::new();
+    let mut event: Option<Vec<Property>> = None;
+    // components inside the event, like alarms, whose properties aren't the event's
+    let mut nested = 0;
+
+    for line in lines.iter() {
+        let Some(property) = Property::parse(line) else {
+            continue;
+        };
+
+        match (property.name.as_str(), &mut event) {
+            ("BEGIN", None) if property.value.eq_ignore_ascii_case("VEVENT") => {
+                event = Some(Vec::new());
+            }
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(properties)) if nested == 0 => {
+                // a UID that was seen already is a changed occurrence of a recurring event
+                if let Some(read) = read_event(properties)
+                    && uids.insert(read.uid.clone())
+                {
+                    events.push(read);
+                }
+
+                event = None;
+            }
+            ("END", Some(_)) => nested -= 1,
+            (_, Some(properties)) if nested == 0 => properties.push(property),
+            _ => {}
+        }
+    }
+
+    Some(events)
+}
+
+fn read_event(properties: &[Property]) -> Option<Event> {
+    let get = |name: &str| properties.iter().find(|property| property.name == name);
+    let text = |name: &str| {
+        get(name)
+            .map(|property| unescape(&property.value))
+            .filter(|text| !text.trim().is_empty())
+    };
+
+    if get("STATUS").is_some_and(|status| status.value.eq_ignore_ascii_case("CANCELLED"))
+        || get("RECURRENCE-ID").is_some()
+    {
+        return None;
+    }
+
+    let dtstart = get("DTSTART")?;
+    let start = dtstart.time()?;
+
+    let end = match get("DTEND") {
+        Some(dtend) => dtend.time(),
+        None => get("DURATION")
+            .and_then(|duration| parse_duration(&duration.value))
+            .and_then(|duration| start.add(duration)),
+    };
+
+    let summary = text("SUMMARY").unwrap_or_default();
+
+    let uid = text("UID").unwrap_or_else(|| {
+        let digest = Sha256::digest(format!("{}\n{summary}", dtstart.value).as_bytes());
+
+        format!("{}@import", hex::encode(&digest[..16]))
+    });
+
+    let geo = get("GEO").and_then(|geo| {
+        let (latitude, longitude) = geo.value.split_once([';', ','])?;
+
+        Some((
+            latitude.trim().parse().ok()?,
+            longitude.trim().parse().ok()?,
+        ))
+    });
+
+    Some(Event {
+        uid,
+        summary,
+        start,
+        end,
+        location: text("LOCATION"),
+        geo,
+        description: text("DESCRIPTION"),
+        sequence: get("SEQUENCE").and_then(|sequence| sequence.value.trim().parse().ok()),
+    })
+}
+
+/// A content line split into its name, parameters and value.
+#[derive(Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Option<Property> {
+        let mut quoted = false;
+        let mut parts = Vec::new();
+        let mut part = String::new();
+        let mut chars = line.chars();
+
+        // `;` and `:` inside quoted parameter values don't end anything
+        for c in chars.by_ref() {
+            match c {
+                '"' => quoted = !quoted,
+                ';' | ':' if !quoted => {
+                    parts.push(std::mem::take(&mut part));
+
+                    if c == ':' {
+                        break;
+                    }
+
+                    continue;
+                }
+                _ => part.push(c),
+            }
+        }
+
+        let value: String = chars.collect();
+        let mut parts = parts.into_iter();
+        let name = parts.next()?.trim().to_ascii_uppercase();
+
+        if name.is_empty() {
+            return None;
+        }
+
+        let params = parts
+            .filter_map(|param| {
+                let (key, value) = param.split_once('=')?;
+
+                Some((key.trim().to_ascii_uppercase(), value.to_string()))
+            })
+            .collect();
+
+        Some(Property {
+            name,
+            params,
+            value,
+        })
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The value as a date or time. Times in a zone the server doesn't know are left floating.
+    fn time(&self) -> Option<EventTime> {
+        let value = self.value.trim();
+
+        if self
+            .param("VALUE")
+            .is_some_and(|kind| kind.eq_ignore_ascii_case("DATE"))
+            || value.len() == 8
+        {
+            return NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()
+                .map(EventTime::Date);
+        }
+
+        if let Some(value) = value.strip_suffix(['Z', 'z']) {
+            return NaiveDateTime::parse_from_str(value, LOCAL_FORMAT)
+                .ok()
+                .map(|time| EventTime::DateTime(time.and_utc()));
+        }
+
+        let time = NaiveDateTime::parse_from_str(value, LOCAL_FORMAT).ok()?;
+
+        let zone = self
+            .param("TZID")
+            .and_then(|zone| zone.trim_start_matches('/').parse::<Tz>().ok());
+
+        match zone.and_then(|zone| zone.from_local_datetime(&time).earliest()) {
+            Some(local) => Some(EventTime::DateTime(local.with_timezone(&Utc))),
+            None => Some(EventTime::Floating(time)),
+        }
+    }
+}
+
+/// Joins folded lines back together, see `line`.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in ics.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continued), Some(last)) => last.push_str(continued),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+/// Undoes `escape`.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => {}
+        }
+    }
+
+    unescaped
+}
+
+/// Reads a duration like `PT1H30M` or `P2D`, see RFC 5545 section 3.3.6.
+fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let mut duration = TimeDelta::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+
+    for c in value.strip_prefix('P')?.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        if c == 'T' {
+            in_time = true;
+            continue;
+        }
+
+        let amount: i64 = std::mem::take(&mut number).parse().ok()?;
+
+        duration += match (c, in_time) {
+            ('W', false) => TimeDelta::try_weeks(amount)?,
+            ('D', false) => TimeDelta::try_days(amount)?,
+            ('H', true) => TimeDelta::try_hours(amount)?,
+            ('M', true) => TimeDelta::try_minutes(amount)?,
+            ('S', true) => TimeDelta::try_seconds(amount)?,
+            _ => return None,
+        };
+    }
+
+    if !number.is_empty() {
+        return None;
+    }
+
+    Some(if negative { -duration } else { duration })
+}
+
+/// Escapes text values, see RFC 5545 section 3.3.11.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Writes a content line, folded so no line is longer than `MAX_LINE_OCTETS`.
+fn line(ics: &mut String, name: &str, value: &str) {
+    let content = format!("{name}:{value}");
+    let mut octets = 0;
+
+    for c in content.chars() {
+        // continuation lines start with a space, which counts towards their length
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            ics.push_str("\r\n ");
+            octets = 1;
+        }
+
+        ics.push(c);
+        octets += c.len_utf8();
+    }
+
+    ics.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn event() -> Event {
+        Event {
+            uid: "itinerary-item-1@journly".to_string(),
+            summary: "Tour; museum, then lunch".to_string(),
+            start: EventTime::DateTime(Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap()),
+            end: Some(EventTime::DateTime(
+                Utc.with_ymd_and_hms(2025, 6, 1, 2, 30, 0).unwrap(),
+            )),
+            location: None,
+            geo: Some((35.68, 139.65)),
+            description: Some("Meet at the gate\nBring tickets".to_string()),
+            sequence: Some(3),
+        }
+    }
+
+    #[test]
+    fn events_are_rendered_with_crlf_lines() {
+        let ics = Calendar {
+            name: Some("Japan".to_string()),
+            events: vec![event()],
+        }
+        .render(Utc.with_ymd_and_hms(2025, 5, 1, 12, 0, 0).unwrap());
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert!(ics.contains("\r\nDTSTAMP:20250501T120000Z\r\n"));
+        assert!(ics.contains("\r\nDTSTART:20250601T000000Z\r\nDTEND:20250601T023000Z\r\n"));
+        assert!(ics.contains("\r\nSUMMARY:Tour\\; museum\\, then lunch\r\n"));
+        assert!(ics.contains("\r\nDESCRIPTION:Meet at the gate\\nBring tickets\r\n"));
+        assert!(ics.contains("\r\nGEO:35.68;139.65\r\n"));
+        assert!(ics.contains("\r\nSEQUENCE:3\r\n"));
+        assert!(!ics.replace("\r\n", "").contains('\n'));
+    }
+
+    #[test]
+    fn all_day_events_use_dates() {
+        let mut ics = String::new();
+        time_line(
+            &mut ics,
+            "DTSTART",
+            &EventTime::Date(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()),
+        );
+
+        assert_eq!(ics, "DTSTART;VALUE=DATE:20250601\r\n");
+    }
+
+    #[test]
+    fn long_lines_are_folded_on_character_boundaries() {
+        let mut ics = String::new();
+        line(&mut ics, "SUMMARY", &"東京".repeat(30));
+
+        for folded in ics.trim_end_matches("\r\n").split("\r\n") {
+            assert!(folded.len() <= MAX_LINE_OCTETS);
+        }
+
+        assert_eq!(
+            ics.trim_end_matches("\r\n").replace("\r\n ", ""),
+            format!("SUMMARY:{}", "東京".repeat(30))
+        );
+    }
+
+    #[test]
+    fn rendered_calendars_parse_back() {
+        let mut all_day = event();
+        all_day.uid = "itinerary-item-2@journly".to_string();
+        all_day.summary = "東京".repeat(30);
+        all_day.start = EventTime::Date(NaiveDate::from_ymd_opt(2025, 6, 2).unwrap());
+        all_day.end = Some(EventTime::Date(
+            NaiveDate::from_ymd_opt(2025, 6, 3).unwrap(),
+        ));
+
+        let ics = Calendar {
+            name: None,
+            events: vec![event(), all_day.clone()],
+        }
+        .render(Utc::now());
+
+        assert_eq!(parse(&ics), Some(vec![event(), all_day]));
+    }
+
+    #[test]
+    fn imported_times_follow_their_zone() {
+        let ics = "BEGIN:VCALENDAR\n\
+            BEGIN:VEVENT\n\
+            UID:booking-1\n\
+            SUMMARY:Hotel\n\
+            DTSTART;TZID=Asia/Tokyo:20250601T150000\n\
+            DURATION:PT1H30M\n\
+            BEGIN:VALARM\n\
+            DESCRIPTION:Reminder\n\
+            END:VALARM\n\
+            END:VEVENT\n\
+            BEGIN:VEVENT\n\
+            SUMMARY:Dinner\n\
+            DTSTART;TZID=\"Pacific Standard Time\":20250601T190000\n\
+            END:VEVENT\n\
+            BEGIN:VEVENT\n\
+            UID:booking-1\n\
+            RECURRENCE-ID;TZID=Asia/Tokyo:20250608T150000\n\
+            DTSTART;TZID=Asia/Tokyo:20250608T160000\n\
+            END:VEVENT\n\
+            BEGIN:VEVENT\n\
+            UID:booking-2\n\
+            STATUS:CANCELLED\n\
+            DTSTART:20250601T190000Z\n\
+            END:VEVENT\n\
+            END:VCALENDAR\n";
+
+        let events = parse(ics).unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0].start,
+            EventTime::DateTime(Utc.with_ymd_and_hms(2025, 6, 1, 6, 0, 0).unwrap())
+        );
+        assert_eq!(
+            events[0].end,
+            Some(EventTime::DateTime(
+                Utc.with_ymd_and_hms(2025, 6, 1, 7, 30, 0).unwrap()
+            ))
+        );
+        assert_eq!(events[0].description, None);
+        assert_eq!(
+            events[1].start,
+            EventTime::Floating(
+                NaiveDate::from_ymd_opt(2025, 6, 1)
+                    .unwrap()
+                    .and_hms_opt(19, 0, 0)
+                    .unwrap()
+            )
+        );
+        assert_eq!(parse(ics).unwrap()[1].uid, events[1].uid);
+        assert_eq!(parse("not a calendar"), None);
+    }
+
+    #[test]
+    fn durations_are_read() {
+        assert_eq!(parse_duration("PT1H30M"), Some(TimeDelta::minutes(90)));
+        assert_eq!(parse_duration("P1W2D"), Some(TimeDelta::days(9)));
+        assert_eq!(parse_duration("-PT15M"), Some(TimeDelta::minutes(-15)));
+        assert_eq!(parse_duration("PT"), Some(TimeDelta::zero()));
+        assert_eq!(parse_duration("P1H"), None);
+        assert_eq!(parse_duration("1H"), None);
+    }
+}
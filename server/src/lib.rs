@@ -19,18 +19,24 @@ pub mod config;
 pub mod controllers;
 pub mod db;
 pub mod email;
+pub mod feasibility;
 pub mod google_oauth;
+pub mod ical;
 pub mod middleware;
 pub mod models;
+pub mod purge;
+pub mod replicache;
 pub mod routes;
 pub mod s3_client;
+pub mod schedule;
 pub mod schema;
+pub mod timezones;
 pub mod util;
 pub mod views;
 
 fn cors_with_allowed_origins(config: config::Server) -> Cors {
     let mut cors = Cors::default()
-        .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
+        .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"])
         .allowed_headers(vec![
             http::header::AUTHORIZATION,
             http::header::CONTENT_TYPE,
@@ -57,6 +63,18 @@ pub async fn run(listener: TcpListener, app: Arc<App>) -> Result<Server, std::io
         None => std::thread::available_parallelism().unwrap().get(),
     };
 
+    actix_web::rt::spawn(app.pokes.clone().listen());
+    actix_web::rt::spawn(replicache::gc::run(
+        app.database.clone(),
+        app.config.replicache.clone(),
+    ));
+    actix_web::rt::spawn(purge::run(
+        app.database.clone(),
+        app.s3.clone(),
+        app.config.trip_purge.clone(),
+    ));
+    actix_web::rt::spawn(timezones::run_backfill(app.database.clone()));
+
     let state = AppState(app);
 
     let server = HttpServer::new(move || {
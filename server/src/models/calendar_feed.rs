@@ -0,0 +1,67 @@
+use crate::schema::calendar_feeds;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, upsert::excluded};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+const TOKEN_BYTES: usize = 32;
+
+/// A secret link to a calendar of every trip the user is on, for calendar apps that can't sign
+/// in. Users have at most one. Only a hash of the token is stored, like `RefreshToken`.
+#[derive(Debug, Queryable, Selectable, Identifiable)]
+#[diesel(primary_key(token))]
+pub struct CalendarFeed {
+    pub token: String,
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CalendarFeed {
+    pub async fn find(conn: &mut AsyncPgConnection, token: &str) -> QueryResult<CalendarFeed> {
+        let token_hash = hex::encode(Sha256::digest(token.as_bytes()));
+
+        calendar_feeds::table
+            .find(token_hash)
+            .select(CalendarFeed::as_select())
+            .first(conn)
+            .await
+    }
+
+    /// Gives the user a new token, the one they had stops working. Returns the token.
+    pub async fn rotate(conn: &mut AsyncPgConnection, user_id: &Uuid) -> QueryResult<String> {
+        let mut token = [0u8; TOKEN_BYTES];
+        rand::rng().fill(&mut token);
+
+        let token = URL_SAFE_NO_PAD.encode(token);
+        let token_hash = hex::encode(Sha256::digest(token.as_bytes()));
+
+        diesel::insert_into(calendar_feeds::table)
+            .values((
+                calendar_feeds::token.eq(token_hash),
+                calendar_feeds::user_id.eq(user_id),
+            ))
+            .on_conflict(calendar_feeds::user_id)
+            .do_update()
+            .set((
+                calendar_feeds::token.eq(excluded(calendar_feeds::token)),
+                calendar_feeds::created_at.eq(Utc::now()),
+            ))
+            .execute(conn)
+            .await?;
+
+        Ok(token)
+    }
+
+    /// Turns off the user's feed. Returns whether they had one.
+    pub async fn revoke(conn: &mut AsyncPgConnection, user_id: &Uuid) -> QueryResult<bool> {
+        let revoked =
+            diesel::delete(calendar_feeds::table.filter(calendar_feeds::user_id.eq(user_id)))
+                .execute(conn)
+                .await?;
+
+        Ok(revoked > 0)
+    }
+}
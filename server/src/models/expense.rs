@@ -0,0 +1,110 @@
+use crate::schema::{expense_payers, expenses};
+use bigdecimal::BigDecimal;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = expenses)]
+pub struct Expense {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub title: Option<String>,
+    pub cost: BigDecimal,
+    pub currency: String,
+    pub description: Option<String>,
+    pub category: Option<String>,
+}
+
+impl Expense {
+    pub async fn find(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<Expense> {
+        expenses::table
+            .find(id)
+            .select(Expense::as_select())
+            .first(conn)
+            .await
+    }
+
+    pub async fn insert(&self, conn: &mut AsyncPgConnection) -> QueryResult<usize> {
+        diesel::insert_into(expenses::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await
+    }
+
+    /// Reads the expense's field versions and locks the row until the transaction ends, see
+    /// `replicache::merge`.
+    pub async fn lock_field_versions(
+        conn: &mut AsyncPgConnection,
+        id: &Uuid,
+    ) -> QueryResult<serde_json::Value> {
+        expenses::table
+            .find(id)
+            .select(expenses::field_versions)
+            .for_update()
+            .first(conn)
+            .await
+    }
+
+    pub async fn delete(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        diesel::delete(expenses::table.find(id)).execute(conn).await
+    }
+}
+
+#[derive(Debug, Default, PartialEq, AsChangeset)]
+#[diesel(table_name = expenses)]
+pub struct ExpenseChanges {
+    pub title: Option<String>,
+    pub cost: Option<BigDecimal>,
+    pub currency: Option<String>,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub field_versions: Option<serde_json::Value>,
+}
+
+impl ExpenseChanges {
+    pub async fn apply(self, conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        if self == Self::default() {
+            return Ok(0);
+        }
+
+        diesel::update(expenses::table.find(id))
+            .set(self)
+            .execute(conn)
+            .await
+    }
+}
+
+#[derive(Clone, Debug, Queryable, Selectable, Insertable)]
+#[diesel(table_name = expense_payers)]
+pub struct ExpensePayer {
+    pub expense_id: Uuid,
+    pub user_id: Uuid,
+    pub id: Uuid,
+    pub trip_id: Uuid,
+}
+
+impl ExpensePayer {
+    pub async fn find(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<ExpensePayer> {
+        expense_payers::table
+            .filter(expense_payers::id.eq(id))
+            .select(ExpensePayer::as_select())
+            .first(conn)
+            .await
+    }
+
+    pub async fn insert(&self, conn: &mut AsyncPgConnection) -> QueryResult<usize> {
+        diesel::insert_into(expense_payers::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await
+    }
+
+    pub async fn delete(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        diesel::delete(expense_payers::table.filter(expense_payers::id.eq(id)))
+            .execute(conn)
+            .await
+    }
+}
@@ -0,0 +1,104 @@
+use crate::schema::itinerary_items;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Items that weren't given an activity type are plain activities.
+pub const DEFAULT_ACTIVITY_TYPE: &str = "activity";
+
+#[derive(Clone, Debug, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = itinerary_items)]
+pub struct ItineraryItem {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub title: String,
+    pub activity_type: String,
+    pub location_id: Option<Uuid>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub expense_id: Option<Uuid>,
+    pub notes: String,
+    pub description: Option<String>,
+    pub all_day: bool,
+    /// UID of the calendar event the item was imported from
+    pub ical_uid: Option<String>,
+}
+
+impl ItineraryItem {
+    pub async fn find(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<ItineraryItem> {
+        itinerary_items::table
+            .find(id)
+            .select(ItineraryItem::as_select())
+            .first(conn)
+            .await
+    }
+
+    pub async fn find_by_trip(
+        conn: &mut AsyncPgConnection,
+        trip_id: &Uuid,
+    ) -> QueryResult<Vec<ItineraryItem>> {
+        itinerary_items::table
+            .filter(itinerary_items::trip_id.eq(trip_id))
+            .order(itinerary_items::start_time.asc())
+            .select(ItineraryItem::as_select())
+            .load(conn)
+            .await
+    }
+
+    pub async fn insert(&self, conn: &mut AsyncPgConnection) -> QueryResult<usize> {
+        diesel::insert_into(itinerary_items::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await
+    }
+
+    /// Reads the item's field versions and locks the row until the transaction ends, see
+    /// `replicache::merge`.
+    pub async fn lock_field_versions(
+        conn: &mut AsyncPgConnection,
+        id: &Uuid,
+    ) -> QueryResult<serde_json::Value> {
+        itinerary_items::table
+            .find(id)
+            .select(itinerary_items::field_versions)
+            .for_update()
+            .first(conn)
+            .await
+    }
+
+    pub async fn delete(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        diesel::delete(itinerary_items::table.find(id))
+            .execute(conn)
+            .await
+    }
+}
+
+#[derive(Debug, Default, PartialEq, AsChangeset)]
+#[diesel(table_name = itinerary_items)]
+pub struct ItineraryItemChanges {
+    pub title: Option<String>,
+    pub activity_type: Option<String>,
+    pub location_id: Option<Uuid>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub expense_id: Option<Uuid>,
+    pub notes: Option<String>,
+    pub description: Option<String>,
+    pub all_day: Option<bool>,
+    pub field_versions: Option<serde_json::Value>,
+}
+
+impl ItineraryItemChanges {
+    pub async fn apply(self, conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        if self == Self::default() {
+            return Ok(0);
+        }
+
+        diesel::update(itinerary_items::table.find(id))
+            .set(self)
+            .execute(conn)
+            .await
+    }
+}
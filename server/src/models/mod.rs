@@ -1,3 +1,12 @@
+pub mod calendar_feed;
+pub mod expense;
+pub mod itinerary_item;
 pub mod refresh_tokens;
+pub mod replicache;
+pub mod sync_conflict;
+pub mod task;
+pub mod trip;
+pub mod trip_invite;
+pub mod trip_share_link;
 pub mod user;
 pub mod user_trip;
@@ -0,0 +1,241 @@
+use crate::schema::{
+    replicache_client, replicache_client_group, replicache_space, replicache_tombstones,
+};
+use chrono::{DateTime, Utc};
+use diesel::{dsl::now, prelude::*};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+define_sql_function! {
+    fn replicache_next_version(space: diesel::sql_types::Text) -> Integer;
+}
+
+#[derive(Debug, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = replicache_space)]
+pub struct ReplicacheSpace {
+    pub id: String,
+    pub version: i32,
+}
+
+impl ReplicacheSpace {
+    /// Fetches the space and holds a row lock on it until the surrounding transaction ends, so
+    /// pushes to the same space are applied one at a time. The space is created on first use.
+    pub async fn lock(conn: &mut AsyncPgConnection, id: &str) -> QueryResult<ReplicacheSpace> {
+        diesel::insert_into(replicache_space::table)
+            .values(ReplicacheSpace {
+                id: id.to_string(),
+                version: 0,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await?;
+
+        replicache_space::table
+            .find(id)
+            .for_update()
+            .select(ReplicacheSpace::as_select())
+            .first(conn)
+            .await
+    }
+
+    pub async fn find(conn: &mut AsyncPgConnection, id: &str) -> QueryResult<ReplicacheSpace> {
+        replicache_space::table
+            .find(id)
+            .select(ReplicacheSpace::as_select())
+            .first(conn)
+            .await
+    }
+
+    pub async fn find_many(
+        conn: &mut AsyncPgConnection,
+        ids: &[String],
+    ) -> QueryResult<Vec<ReplicacheSpace>> {
+        replicache_space::table
+            .filter(replicache_space::id.eq_any(ids))
+            .select(ReplicacheSpace::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Advances the space version, see the `replicache_next_version` SQL function. Row changes
+    /// get their versions from their trip's space through triggers.
+    pub async fn next_version(conn: &mut AsyncPgConnection, id: &str) -> QueryResult<i32> {
+        diesel::select(replicache_next_version(id))
+            .get_result(conn)
+            .await
+    }
+}
+
+#[derive(Debug, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = replicache_client_group)]
+pub struct ReplicacheClientGroup {
+    pub id: String,
+    pub user_id: Uuid,
+    pub space_id: String,
+}
+
+impl ReplicacheClientGroup {
+    pub async fn find(
+        conn: &mut AsyncPgConnection,
+        id: &str,
+    ) -> QueryResult<ReplicacheClientGroup> {
+        replicache_client_group::table
+            .find(id)
+            .select(ReplicacheClientGroup::as_select())
+            .first(conn)
+            .await
+    }
+
+    pub async fn find_or_create(
+        conn: &mut AsyncPgConnection,
+        id: &str,
+        user_id: &Uuid,
+        space_id: &str,
+    ) -> QueryResult<ReplicacheClientGroup> {
+        diesel::insert_into(replicache_client_group::table)
+            .values(ReplicacheClientGroup {
+                id: id.to_string(),
+                user_id: *user_id,
+                space_id: space_id.to_string(),
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await?;
+
+        Self::find(conn, id).await
+    }
+
+    /// Like `find_or_create`, and records that the group pulled just now.
+    pub async fn touch(
+        conn: &mut AsyncPgConnection,
+        id: &str,
+        user_id: &Uuid,
+        space_id: &str,
+    ) -> QueryResult<ReplicacheClientGroup> {
+        diesel::insert_into(replicache_client_group::table)
+            .values(ReplicacheClientGroup {
+                id: id.to_string(),
+                user_id: *user_id,
+                space_id: space_id.to_string(),
+            })
+            .on_conflict(replicache_client_group::id)
+            .do_update()
+            .set(replicache_client_group::last_pulled_at.eq(now))
+            .returning(ReplicacheClientGroup::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// Deletes groups that haven't pulled since `before`, their clients go with them.
+    pub async fn delete_inactive(
+        conn: &mut AsyncPgConnection,
+        before: DateTime<Utc>,
+    ) -> QueryResult<usize> {
+        diesel::delete(
+            replicache_client_group::table
+                .filter(replicache_client_group::last_pulled_at.lt(before)),
+        )
+        .execute(conn)
+        .await
+    }
+}
+
+#[derive(Debug, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = replicache_client)]
+pub struct ReplicacheClient {
+    pub id: String,
+    pub client_group_id: String,
+    pub last_mutation_id: i32,
+    pub last_modified_version: i32,
+}
+
+impl ReplicacheClient {
+    pub async fn find_or_create(
+        conn: &mut AsyncPgConnection,
+        id: &str,
+        client_group_id: &str,
+    ) -> QueryResult<ReplicacheClient> {
+        diesel::insert_into(replicache_client::table)
+            .values(ReplicacheClient {
+                id: id.to_string(),
+                client_group_id: client_group_id.to_string(),
+                last_mutation_id: 0,
+                last_modified_version: 0,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await?;
+
+        replicache_client::table
+            .find(id)
+            .select(ReplicacheClient::as_select())
+            .first(conn)
+            .await
+    }
+
+    pub async fn find_changed_in_group(
+        conn: &mut AsyncPgConnection,
+        client_group_id: &str,
+        since_version: i32,
+    ) -> QueryResult<Vec<ReplicacheClient>> {
+        replicache_client::table
+            .filter(replicache_client::client_group_id.eq(client_group_id))
+            .filter(replicache_client::last_modified_version.gt(since_version))
+            .select(ReplicacheClient::as_select())
+            .load(conn)
+            .await
+    }
+
+    pub async fn set_last_mutation_id(
+        &self,
+        conn: &mut AsyncPgConnection,
+        last_mutation_id: i32,
+        version: i32,
+    ) -> QueryResult<()> {
+        diesel::update(replicache_client::table)
+            .filter(replicache_client::id.eq(&self.id))
+            .set((
+                replicache_client::last_mutation_id.eq(last_mutation_id),
+                replicache_client::last_modified_version.eq(version),
+            ))
+            .execute(conn)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Marks a key that was deleted, so clients that synced it before can drop it.
+#[derive(Debug, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = replicache_tombstones)]
+#[diesel(primary_key(key))]
+pub struct ReplicacheTombstone {
+    pub key: String,
+    pub trip_id: Uuid,
+    pub version: i32,
+}
+
+impl ReplicacheTombstone {
+    pub async fn find_since(
+        conn: &mut AsyncPgConnection,
+        trip_ids: &[Uuid],
+        since_version: i32,
+    ) -> QueryResult<Vec<ReplicacheTombstone>> {
+        replicache_tombstones::table
+            .filter(replicache_tombstones::trip_id.eq_any(trip_ids))
+            .filter(replicache_tombstones::version.gt(since_version))
+            .select(ReplicacheTombstone::as_select())
+            .load(conn)
+            .await
+    }
+
+    pub async fn delete_before(
+        conn: &mut AsyncPgConnection,
+        before: DateTime<Utc>,
+    ) -> QueryResult<usize> {
+        diesel::delete(
+            replicache_tombstones::table.filter(replicache_tombstones::deleted_at.lt(before)),
+        )
+        .execute(conn)
+        .await
+    }
+}
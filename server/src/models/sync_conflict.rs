@@ -0,0 +1,72 @@
+use crate::schema::sync_conflicts;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// A change that lost a merge and is kept until a collaborator decides between it and the
+/// current state, see `replicache::conflicts`.
+#[derive(Clone, Debug, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = sync_conflicts)]
+pub struct SyncConflict {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    pub field: Option<String>,
+    pub current_value: Option<serde_json::Value>,
+    pub proposed_value: serde_json::Value,
+    pub proposed_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SyncConflict {
+    pub async fn find(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<SyncConflict> {
+        sync_conflicts::table
+            .find(id)
+            .select(SyncConflict::as_select())
+            .first(conn)
+            .await
+    }
+
+    pub async fn find_by_trip(
+        conn: &mut AsyncPgConnection,
+        trip_id: &Uuid,
+    ) -> QueryResult<Vec<SyncConflict>> {
+        sync_conflicts::table
+            .filter(sync_conflicts::trip_id.eq(trip_id))
+            .order(sync_conflicts::created_at.asc())
+            .select(SyncConflict::as_select())
+            .load(conn)
+            .await
+    }
+
+    pub async fn delete(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        diesel::delete(sync_conflicts::table.find(id))
+            .execute(conn)
+            .await
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = sync_conflicts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewSyncConflict<'a> {
+    pub trip_id: Uuid,
+    pub entity_type: &'a str,
+    pub entity_id: Uuid,
+    pub field: Option<&'a str>,
+    pub current_value: Option<serde_json::Value>,
+    pub proposed_value: serde_json::Value,
+    pub proposed_by: Option<Uuid>,
+}
+
+impl NewSyncConflict<'_> {
+    pub async fn insert(&self, conn: &mut AsyncPgConnection) -> QueryResult<SyncConflict> {
+        diesel::insert_into(sync_conflicts::table)
+            .values(self)
+            .returning(SyncConflict::as_returning())
+            .get_result(conn)
+            .await
+    }
+}
@@ -0,0 +1,120 @@
+use crate::schema::tasks;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = tasks)]
+pub struct Task {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub description: String,
+    pub completed: bool,
+    pub created_at: DateTime<Utc>,
+    pub title: String,
+    pub position: String,
+    pub urgency: String,
+}
+
+impl Task {
+    pub async fn find(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<Task> {
+        tasks::table
+            .find(id)
+            .select(Task::as_select())
+            .first(conn)
+            .await
+    }
+
+    /// The trip's tasks in list order.
+    pub async fn find_by_trip(
+        conn: &mut AsyncPgConnection,
+        trip_id: &Uuid,
+    ) -> QueryResult<Vec<Task>> {
+        tasks::table
+            .filter(tasks::trip_id.eq(trip_id))
+            .order(tasks::position.asc())
+            .select(Task::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Position of the last task in the trip's list, if the trip has any tasks.
+    pub async fn last_position(
+        conn: &mut AsyncPgConnection,
+        trip_id: &Uuid,
+    ) -> QueryResult<Option<String>> {
+        tasks::table
+            .filter(tasks::trip_id.eq(trip_id))
+            .order(tasks::position.desc())
+            .select(tasks::position)
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// Reads the task's field versions and locks the row until the transaction ends, see
+    /// `replicache::merge`.
+    pub async fn lock_field_versions(
+        conn: &mut AsyncPgConnection,
+        id: &Uuid,
+    ) -> QueryResult<serde_json::Value> {
+        tasks::table
+            .find(id)
+            .select(tasks::field_versions)
+            .for_update()
+            .first(conn)
+            .await
+    }
+
+    pub async fn delete(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        diesel::delete(tasks::table.find(id)).execute(conn).await
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = tasks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewTask<'a> {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub title: &'a str,
+    pub description: &'a str,
+    pub completed: bool,
+    pub position: &'a str,
+    pub urgency: &'a str,
+}
+
+impl NewTask<'_> {
+    pub async fn insert(&self, conn: &mut AsyncPgConnection) -> QueryResult<usize> {
+        diesel::insert_into(tasks::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await
+    }
+}
+
+#[derive(Debug, Default, PartialEq, AsChangeset)]
+#[diesel(table_name = tasks)]
+pub struct TaskChanges {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub completed: Option<bool>,
+    pub position: Option<String>,
+    pub urgency: Option<String>,
+    pub field_versions: Option<serde_json::Value>,
+}
+
+impl TaskChanges {
+    pub async fn apply(self, conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        if self == Self::default() {
+            return Ok(0);
+        }
+
+        diesel::update(tasks::table.find(id))
+            .set(self)
+            .execute(conn)
+            .await
+    }
+}
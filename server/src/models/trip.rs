@@ -0,0 +1,496 @@
+use crate::{
+    models::user_trip::{Role, UserTrip},
+    schema::{accommodations, documents, flights, itinerary_items, trips, user_trip},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel_async::{
+    AsyncConnection, AsyncPgConnection, RunQueryDsl, scoped_futures::ScopedFutureExt,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Where a trip is in its life. Archived trips work as usual but are kept out of the trip list,
+/// deleted ones are gone for everyone until the owner restores them or they're purged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TripState {
+    #[default]
+    Active,
+    Archived,
+    Deleted,
+}
+
+/// Where a trip is in time, from its dates. Trips without a start date have none.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TripStatus {
+    /// Starts after today
+    Upcoming,
+    /// Started and hasn't ended yet, a trip without an end date only lasts its start date
+    Ongoing,
+    /// Ended before today
+    Past,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TripSort {
+    /// Most recently edited first
+    #[default]
+    Updated,
+    /// Earliest start date first, trips without one last
+    StartDate,
+}
+
+/// Where a page of the trip list left off: the sort keys of its last trip.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct TripCursor {
+    sort: TripSort,
+    id: Uuid,
+    updated_at: DateTime<Utc>,
+    start_date: Option<NaiveDate>,
+}
+
+impl TripCursor {
+    pub fn after(trip: &Trip, sort: TripSort) -> Self {
+        Self {
+            sort,
+            id: trip.id,
+            updated_at: trip.updated_at,
+            start_date: trip.start_date,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("cursors always serialize"))
+    }
+
+    /// Reads a cursor from `encode`, as long as it was made for `sort`.
+    pub fn decode(cursor: &str, sort: TripSort) -> Option<Self> {
+        let cursor: Self = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+
+        (cursor.sort == sort).then_some(cursor)
+    }
+}
+
+/// Narrows down and orders the trips a user is on, see `Trip::find_for_user`.
+#[derive(Debug, Default)]
+pub struct TripFilter {
+    pub state: TripState,
+    /// Case-insensitive substring of the title
+    pub search: Option<String>,
+    pub status: Option<TripStatus>,
+    /// Only trips the user has exactly this role on
+    pub role: Option<Role>,
+    pub sort: TripSort,
+    /// Only trips after this one in `sort` order, it has to be for the same sort
+    pub cursor: Option<TripCursor>,
+    pub limit: i64,
+}
+
+#[derive(Clone, Debug, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = trips)]
+pub struct Trip {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub title: Option<String>,
+    pub banner_image: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub no_collaborators: i32,
+    pub created_at: Option<DateTime<Utc>>,
+    pub description: Option<String>,
+    pub updated_at: DateTime<Utc>,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl Trip {
+    /// Finds the trip unless it was deleted, see `find_deleted`.
+    pub async fn find(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<Trip> {
+        trips::table
+            .find(id)
+            .filter(trips::deleted_at.is_null())
+            .select(Trip::as_select())
+            .first(conn)
+            .await
+    }
+
+    /// Finds the trip only if it was deleted and hasn't been purged yet.
+    pub async fn find_deleted(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<Trip> {
+        trips::table
+            .find(id)
+            .filter(trips::deleted_at.is_not_null())
+            .select(Trip::as_select())
+            .first(conn)
+            .await
+    }
+
+    /// Trips the user is on that match the filter, in its order, at most `filter.limit` of them.
+    pub async fn find_for_user(
+        conn: &mut AsyncPgConnection,
+        user_id: &Uuid,
+        filter: &TripFilter,
+    ) -> QueryResult<Vec<Trip>> {
+        let mut query = trips::table
+            .inner_join(user_trip::table)
+            .filter(user_trip::user_id.eq(user_id))
+            .select(Trip::as_select())
+            .limit(filter.limit)
+            .into_boxed();
+
+        query = match filter.state {
+            TripState::Active => query
+                .filter(trips::deleted_at.is_null())
+                .filter(trips::archived_at.is_null()),
+            TripState::Archived => query
+                .filter(trips::deleted_at.is_null())
+                .filter(trips::archived_at.is_not_null()),
+            TripState::Deleted => query.filter(trips::deleted_at.is_not_null()),
+        };
+
+        if let Some(search) = &filter.search {
+            query = query.filter(trips::title.ilike(format!("%{}%", escape_like(search))));
+        }
+
+        if let Some(role) = filter.role {
+            query = query.filter(user_trip::permission.eq(role));
+        }
+
+        if let Some(status) = filter.status {
+            let today = Utc::now().date_naive();
+
+            query = match status {
+                TripStatus::Upcoming => query.filter(trips::start_date.gt(today)),
+                TripStatus::Ongoing => query.filter(trips::start_date.le(today)).filter(
+                    trips::end_date
+                        .ge(today)
+                        .or(trips::end_date.is_null().and(trips::start_date.eq(today))),
+                ),
+                TripStatus::Past => query.filter(
+                    trips::end_date
+                        .lt(today)
+                        .or(trips::end_date.is_null().and(trips::start_date.lt(today))),
+                ),
+            };
+        }
+
+        // the ID breaks ties, so every trip has one place in the order and pages never overlap
+        query = match filter.sort {
+            TripSort::Updated => query.order((trips::updated_at.desc(), trips::id.desc())),
+            TripSort::StartDate => {
+                query.order((trips::start_date.asc().nulls_last(), trips::id.asc()))
+            }
+        };
+
+        if let Some(cursor) = &filter.cursor {
+            query = match (filter.sort, cursor.start_date) {
+                (TripSort::Updated, _) => query.filter(
+                    trips::updated_at.lt(cursor.updated_at).or(trips::updated_at
+                        .eq(cursor.updated_at)
+                        .and(trips::id.lt(cursor.id))),
+                ),
+                (TripSort::StartDate, Some(start_date)) => query.filter(
+                    trips::start_date
+                        .gt(start_date)
+                        .or(trips::start_date
+                            .eq(start_date)
+                            .and(trips::id.gt(cursor.id)))
+                        .or(trips::start_date.is_null()),
+                ),
+                (TripSort::StartDate, None) => query
+                    .filter(trips::start_date.is_null())
+                    .filter(trips::id.gt(cursor.id)),
+            };
+        }
+
+        query.load(conn).await
+    }
+
+    /// Deleted trips that are past their grace period.
+    pub async fn find_purgeable(
+        conn: &mut AsyncPgConnection,
+        deleted_before: DateTime<Utc>,
+    ) -> QueryResult<Vec<Uuid>> {
+        trips::table
+            .filter(trips::deleted_at.lt(deleted_before))
+            .select(trips::id)
+            .load(conn)
+            .await
+    }
+
+    /// Bumps `updated_at` so the trip sorts as recently edited.
+    pub async fn touch(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        diesel::update(trips::table.find(id))
+            .set(trips::updated_at.eq(Utc::now()))
+            .execute(conn)
+            .await
+    }
+
+    /// The locations the trip's itinerary, flights and accommodations use.
+    pub async fn location_ids(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<Vec<Uuid>> {
+        let mut ids: Vec<Uuid> = itinerary_items::table
+            .filter(itinerary_items::trip_id.eq(id))
+            .filter(itinerary_items::location_id.is_not_null())
+            .select(itinerary_items::location_id.assume_not_null())
+            .load(conn)
+            .await?;
+
+        let flights = flights::table
+            .filter(flights::trip_id.eq(id))
+            .select((flights::departure_location, flights::arrival_location))
+            .load::<(Option<Uuid>, Option<Uuid>)>(conn)
+            .await?;
+
+        let stays: Vec<Option<Uuid>> = accommodations::table
+            .filter(accommodations::trip_id.eq(id))
+            .select(accommodations::location)
+            .load(conn)
+            .await?;
+
+        ids.extend(
+            flights
+                .into_iter()
+                .flat_map(|(from, to)| [from, to])
+                .chain(stays)
+                .flatten(),
+        );
+
+        Ok(ids)
+    }
+
+    /// Reads the trip's field versions and locks the row until the transaction ends, see
+    /// `replicache::merge`.
+    pub async fn lock_field_versions(
+        conn: &mut AsyncPgConnection,
+        id: &Uuid,
+    ) -> QueryResult<serde_json::Value> {
+        trips::table
+            .find(id)
+            .select(trips::field_versions)
+            .for_update()
+            .first(conn)
+            .await
+    }
+
+    pub async fn set_archived(
+        conn: &mut AsyncPgConnection,
+        id: &Uuid,
+        archived: bool,
+    ) -> QueryResult<usize> {
+        let archived_at = archived.then(Utc::now);
+
+        diesel::update(trips::table.find(id).filter(trips::deleted_at.is_null()))
+            .set(trips::archived_at.eq(archived_at))
+            .execute(conn)
+            .await
+    }
+
+    /// Deletes the trip for everyone, it can be restored until it's purged.
+    pub async fn delete(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        diesel::update(trips::table.find(id).filter(trips::deleted_at.is_null()))
+            .set(trips::deleted_at.eq(Utc::now()))
+            .execute(conn)
+            .await
+    }
+
+    pub async fn restore(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        diesel::update(
+            trips::table
+                .find(id)
+                .filter(trips::deleted_at.is_not_null()),
+        )
+        .set(trips::deleted_at.eq(None::<DateTime<Utc>>))
+        .execute(conn)
+        .await
+    }
+
+    /// Deletes the trip for good along with everything on it. Returns the URLs of its files in
+    /// S3, which are left to the caller to remove once the transaction went through.
+    pub async fn purge(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<Vec<String>> {
+        let mut files: Vec<String> = documents::table
+            .filter(documents::trip_id.eq(id))
+            .select(documents::document_url)
+            .load(conn)
+            .await?;
+
+        let banner_image: Option<Option<String>> = diesel::delete(trips::table.find(id))
+            .returning(trips::banner_image)
+            .get_result(conn)
+            .await
+            .optional()?;
+
+        files.extend(banner_image.flatten());
+
+        Ok(files)
+    }
+
+    /// Makes `new_owner`, who has to be on the trip already, its owner. The current owner stays
+    /// on as an editor.
+    pub async fn transfer_ownership(
+        conn: &mut AsyncPgConnection,
+        id: &Uuid,
+        new_owner: &Uuid,
+    ) -> QueryResult<()> {
+        conn.transaction(|conn| {
+            async move {
+                // demoted first, a trip can only have one owner at a time
+                diesel::update(
+                    user_trip::table
+                        .filter(user_trip::trip_id.eq(id))
+                        .filter(user_trip::permission.eq(Role::Owner)),
+                )
+                .set(user_trip::permission.eq(Role::Editor))
+                .execute(conn)
+                .await?;
+
+                diesel::update(user_trip::table.find((new_owner, id)))
+                    .set(user_trip::permission.eq(Role::Owner))
+                    .execute(conn)
+                    .await?;
+
+                diesel::update(trips::table.find(id))
+                    .set(trips::owner_id.eq(new_owner))
+                    .execute(conn)
+                    .await?;
+
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    /// Gets the trips a user owns out of the way before the account is deleted. Trips with an
+    /// editor go to their successor, see `UserTrip::find_successor`, and the rest are purged, even
+    /// if viewers are on them. Returns the URLs of the purged trips' files, see `purge`.
+    pub async fn hand_over_owned(
+        conn: &mut AsyncPgConnection,
+        owner_id: &Uuid,
+    ) -> QueryResult<Vec<String>> {
+        let owned: Vec<Uuid> = trips::table
+            .filter(trips::owner_id.eq(owner_id))
+            .select(trips::id)
+            .load(conn)
+            .await?;
+
+        let mut files = Vec::new();
+
+        for trip_id in owned {
+            match UserTrip::find_successor(conn, &trip_id, owner_id).await? {
+                Some(successor) => {
+                    Trip::transfer_ownership(conn, &trip_id, &successor.user_id).await?;
+                }
+                None => {
+                    files.extend(Trip::purge(conn, &trip_id).await?);
+                }
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = trips)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewTrip<'a> {
+    pub id: Option<Uuid>,
+    pub owner_id: Uuid,
+    pub title: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub banner_image: Option<&'a str>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl NewTrip<'_> {
+    /// Creates the trip along with the owner's `user_trip` row. `no_collaborators` starts at 1,
+    /// which already accounts for the owner.
+    pub async fn insert(&self, conn: &mut AsyncPgConnection) -> QueryResult<Trip> {
+        conn.transaction(|conn| {
+            async move {
+                let trip = diesel::insert_into(trips::table)
+                    .values(self)
+                    .returning(Trip::as_returning())
+                    .get_result(conn)
+                    .await?;
+
+                diesel::insert_into(user_trip::table)
+                    .values((
+                        user_trip::user_id.eq(trip.owner_id),
+                        user_trip::trip_id.eq(trip.id),
+                        user_trip::permission.eq(Role::Owner),
+                    ))
+                    .execute(conn)
+                    .await?;
+
+                Ok(trip)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+}
+
+#[derive(Debug, Default, AsChangeset)]
+#[diesel(table_name = trips)]
+pub struct TripChanges {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub banner_image: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub field_versions: Option<serde_json::Value>,
+}
+
+impl TripChanges {
+    pub async fn apply(mut self, conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        // always touching `updated_at` also keeps the changeset from ever being empty
+        self.updated_at = Some(Utc::now());
+
+        diesel::update(trips::table.find(id))
+            .set(self)
+            .execute(conn)
+            .await
+    }
+}
+
+/// Escapes `LIKE` wildcards so the text only matches itself.
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursors_only_work_for_their_sort() {
+        let cursor = TripCursor {
+            sort: TripSort::StartDate,
+            id: Uuid::new_v4(),
+            updated_at: Utc::now(),
+            start_date: NaiveDate::from_ymd_opt(2025, 6, 1),
+        };
+        let encoded = cursor.encode();
+
+        assert_eq!(
+            TripCursor::decode(&encoded, TripSort::StartDate),
+            Some(cursor)
+        );
+        assert_eq!(TripCursor::decode(&encoded, TripSort::Updated), None);
+        assert_eq!(TripCursor::decode("not a cursor", TripSort::Updated), None);
+    }
+
+    #[test]
+    fn like_wildcards_are_escaped() {
+        assert_eq!(escape_like("100%_off\\"), "100\\%\\_off\\\\");
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    email::Email,
+    models::user_trip::Role,
+    schema::{trip_invites, trips},
+};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+pub const PENDING: &str = "pending";
+pub const ACCEPTED: &str = "accepted";
+pub const DECLINED: &str = "declined";
+pub const REVOKED: &str = "revoked";
+
+/// An invitation to join a trip. Invites are addressed to an email, and to the user with that
+/// email once there is one, see `claim`.
+#[derive(Clone, Debug, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = trip_invites)]
+pub struct TripInvite {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub invitee_email: Option<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub permission: Role,
+    pub invited_by: Option<Uuid>,
+}
+
+impl TripInvite {
+    pub async fn find(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<TripInvite> {
+        trip_invites::table
+            .find(id)
+            .select(TripInvite::as_select())
+            .first(conn)
+            .await
+    }
+
+    /// Finds the invite only if its trip wasn't deleted.
+    pub async fn find_on_live_trip(
+        conn: &mut AsyncPgConnection,
+        id: &Uuid,
+    ) -> QueryResult<TripInvite> {
+        trip_invites::table
+            .inner_join(trips::table)
+            .filter(trip_invites::id.eq(id))
+            .filter(trips::deleted_at.is_null())
+            .select(TripInvite::as_select())
+            .first(conn)
+            .await
+    }
+
+    /// Pending invites to the trip, oldest first.
+    pub async fn find_pending_by_trip(
+        conn: &mut AsyncPgConnection,
+        trip_id: &Uuid,
+    ) -> QueryResult<Vec<TripInvite>> {
+        trip_invites::table
+            .filter(trip_invites::trip_id.eq(trip_id))
+            .filter(trip_invites::status.eq(PENDING))
+            .order(trip_invites::created_at.asc())
+            .select(TripInvite::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Pending invites for the user, with the title of the trip. Invites to deleted trips are left
+    /// out until the trip is restored.
+    pub async fn find_pending_for_user(
+        conn: &mut AsyncPgConnection,
+        user_id: &Uuid,
+    ) -> QueryResult<Vec<(TripInvite, Option<String>)>> {
+        trip_invites::table
+            .inner_join(trips::table)
+            .filter(trip_invites::status.eq(PENDING))
+            .filter(trip_invites::user_id.eq(user_id))
+            .filter(trips::deleted_at.is_null())
+            .order(trip_invites::created_at.desc())
+            .select((TripInvite::as_select(), trips::title))
+            .load(conn)
+            .await
+    }
+
+    /// Whether the trip already has a pending invite for the email.
+    pub async fn pending_exists(
+        conn: &mut AsyncPgConnection,
+        trip_id: &Uuid,
+        email: &str,
+    ) -> QueryResult<bool> {
+        diesel::select(diesel::dsl::exists(
+            trip_invites::table
+                .filter(trip_invites::trip_id.eq(trip_id))
+                .filter(trip_invites::status.eq(PENDING))
+                .filter(trip_invites::invitee_email.eq(email)),
+        ))
+        .get_result(conn)
+        .await
+    }
+
+    /// Addresses pending invites sent to `email` before it had an account to the new user.
+    pub async fn claim(
+        conn: &mut AsyncPgConnection,
+        user_id: &Uuid,
+        email: &str,
+    ) -> QueryResult<usize> {
+        diesel::update(trip_invites::table)
+            .filter(trip_invites::status.eq(PENDING))
+            .filter(trip_invites::user_id.is_null())
+            .filter(trip_invites::invitee_email.eq(email))
+            .set(trip_invites::user_id.eq(user_id))
+            .execute(conn)
+            .await
+    }
+
+    /// Moves a pending invite for `user_id` to `status`. Returns whether there was one.
+    pub async fn respond(
+        &self,
+        conn: &mut AsyncPgConnection,
+        user_id: &Uuid,
+        status: &str,
+    ) -> QueryResult<bool> {
+        let updated = diesel::update(trip_invites::table.find(self.id))
+            .filter(trip_invites::status.eq(PENDING))
+            .filter(trip_invites::user_id.eq(user_id))
+            .set(trip_invites::status.eq(status))
+            .execute(conn)
+            .await?;
+
+        Ok(updated > 0)
+    }
+
+    /// Withdraws a pending invite. Returns whether it was still pending.
+    pub async fn revoke(&self, conn: &mut AsyncPgConnection) -> QueryResult<bool> {
+        let updated = diesel::update(trip_invites::table.find(self.id))
+            .filter(trip_invites::status.eq(PENDING))
+            .set(trip_invites::status.eq(REVOKED))
+            .execute(conn)
+            .await?;
+
+        Ok(updated > 0)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = trip_invites)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewTripInvite<'a> {
+    pub trip_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub invitee_email: &'a str,
+    pub permission: Role,
+    pub invited_by: Uuid,
+}
+
+impl NewTripInvite<'_> {
+    pub async fn insert(&self, conn: &mut AsyncPgConnection) -> QueryResult<TripInvite> {
+        diesel::insert_into(trip_invites::table)
+            .values(self)
+            .returning(TripInvite::as_returning())
+            .get_result(conn)
+            .await
+    }
+}
+
+pub struct TripInviteEmail<'a> {
+    pub inviter: &'a str,
+    pub trip_title: &'a str,
+    pub invites_url: &'a str,
+}
+
+impl Email for TripInviteEmail<'_> {
+    fn subject(&self) -> String {
+        format!(
+            "{} invited you to {} on Journly",
+            self.inviter, self.trip_title
+        )
+    }
+
+    fn body(&self) -> String {
+        format!(
+            "Hi,\n\n\
+            {} invited you to plan {} together on Journly.\n\n\
+            To accept or decline, sign in or create an account with this email address:\n\n\
+            {}\n\n\
+            If you weren't expecting this invite, you can ignore this message.\n\n\
+            Thanks,\n\
+            The Journly Team",
+            self.inviter, self.trip_title, self.invites_url
+        )
+    }
+}
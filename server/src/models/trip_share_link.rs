@@ -0,0 +1,177 @@
+use crate::schema::trip_share_links;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SIGNATURE_LEN: usize = 32;
+
+/// Read-only access to a trip for anyone holding the link's token, until the link expires or the
+/// owner revokes it. Expenses and documents are only shared when the owner opted in.
+#[derive(Clone, Debug, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = trip_share_links)]
+pub struct TripShareLink {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub created_by: Option<Uuid>,
+    pub include_expenses: bool,
+    pub include_documents: bool,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TripShareLink {
+    /// Links to the trip that still work, newest first.
+    pub async fn find_active_by_trip(
+        conn: &mut AsyncPgConnection,
+        trip_id: &Uuid,
+    ) -> QueryResult<Vec<TripShareLink>> {
+        trip_share_links::table
+            .filter(trip_share_links::trip_id.eq(trip_id))
+            .filter(trip_share_links::revoked_at.is_null())
+            .filter(trip_share_links::expires_at.gt(Utc::now()))
+            .order(trip_share_links::created_at.desc())
+            .select(TripShareLink::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Looks up the link a token was made for, as long as it still works. Tokens with a bad
+    /// signature are turned away without a query.
+    pub async fn find_by_token(
+        conn: &mut AsyncPgConnection,
+        token: &str,
+        secret: &str,
+    ) -> QueryResult<TripShareLink> {
+        let id = verify_token(token, secret).ok_or(diesel::result::Error::NotFound)?;
+
+        trip_share_links::table
+            .find(id)
+            .filter(trip_share_links::revoked_at.is_null())
+            .filter(trip_share_links::expires_at.gt(Utc::now()))
+            .select(TripShareLink::as_select())
+            .first(conn)
+            .await
+    }
+
+    /// Revokes the link if it's on the trip and still works. Returns whether it was revoked.
+    pub async fn revoke(
+        conn: &mut AsyncPgConnection,
+        trip_id: &Uuid,
+        id: &Uuid,
+    ) -> QueryResult<bool> {
+        let revoked = diesel::update(
+            trip_share_links::table
+                .find(id)
+                .filter(trip_share_links::trip_id.eq(trip_id))
+                .filter(trip_share_links::revoked_at.is_null())
+                .filter(trip_share_links::expires_at.gt(Utc::now())),
+        )
+        .set(trip_share_links::revoked_at.eq(Utc::now()))
+        .execute(conn)
+        .await?;
+
+        Ok(revoked > 0)
+    }
+
+    /// The token that goes in the link: the link's ID with a signature, so tokens can't be made
+    /// up from guessed IDs. It's derived rather than stored, the owner can see it again later.
+    pub fn token(&self, secret: &str) -> String {
+        let mut token = self.id.as_bytes().to_vec();
+        token.extend(signer(&self.id, secret).finalize().into_bytes());
+
+        URL_SAFE_NO_PAD.encode(token)
+    }
+}
+
+fn signer(id: &Uuid, secret: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC takes any key size");
+    // keeps these signatures apart from anything else that might ever be signed with the secret
+    mac.update(b"trip-share-link:");
+    mac.update(id.as_bytes());
+    mac
+}
+
+/// The link ID in a token, if its signature checks out.
+fn verify_token(token: &str, secret: &str) -> Option<Uuid> {
+    let token = URL_SAFE_NO_PAD.decode(token).ok()?;
+
+    if token.len() != 16 + SIGNATURE_LEN {
+        return None;
+    }
+
+    let (id, signature) = token.split_at(16);
+    let id = Uuid::from_slice(id).ok()?;
+
+    signer(&id, secret).verify_slice(signature).ok()?;
+
+    Some(id)
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = trip_share_links)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewTripShareLink {
+    pub trip_id: Uuid,
+    pub created_by: Uuid,
+    pub include_expenses: bool,
+    pub include_documents: bool,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl NewTripShareLink {
+    pub async fn insert(&self, conn: &mut AsyncPgConnection) -> QueryResult<TripShareLink> {
+        diesel::insert_into(trip_share_links::table)
+            .values(self)
+            .returning(TripShareLink::as_returning())
+            .get_result(conn)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link() -> TripShareLink {
+        TripShareLink {
+            id: Uuid::new_v4(),
+            trip_id: Uuid::new_v4(),
+            created_by: None,
+            include_expenses: false,
+            include_documents: false,
+            expires_at: Utc::now(),
+            revoked_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn tokens_verify_with_their_secret() {
+        let link = link();
+        let token = link.token("secret");
+
+        assert_eq!(verify_token(&token, "secret"), Some(link.id));
+        assert_eq!(verify_token(&token, "other secret"), None);
+    }
+
+    #[test]
+    fn tampered_tokens_are_rejected() {
+        let link = link();
+        let mut token = URL_SAFE_NO_PAD.decode(link.token("secret")).unwrap();
+        token[0] ^= 1;
+
+        assert_eq!(
+            verify_token(&URL_SAFE_NO_PAD.encode(&token), "secret"),
+            None
+        );
+        assert_eq!(verify_token(&link.id.to_string(), "secret"), None);
+        assert_eq!(verify_token("not a token", "secret"), None);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     email::Email,
-    schema::{user_verification_codes, users},
+    models::user_trip::Role,
+    schema::{trips, user_trip, user_verification_codes, users},
 };
 use chrono::{DateTime, Duration, Utc};
 use diesel::prelude::*;
@@ -14,7 +15,7 @@ pub struct Collaborator {
     pub id: Uuid,
     pub username: String,
     pub avatar: Option<String>,
-    pub permission: Option<String>,
+    pub permission: Role,
 }
 
 #[derive(Clone, Debug, Queryable, Selectable, Identifiable, Serialize)]
@@ -64,7 +65,19 @@ impl User {
             .await
     }
 
+    /// Deletes the user, which takes their memberships with it, so the trips they were on get
+    /// one collaborator less. Trips they own have to be handed over first, see
+    /// `Trip::hand_over_owned`.
     pub async fn delete(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<usize> {
+        let memberships = user_trip::table
+            .filter(user_trip::user_id.eq(id))
+            .select(user_trip::trip_id);
+
+        diesel::update(trips::table.filter(trips::id.eq_any(memberships)))
+            .set(trips::no_collaborators.eq(trips::no_collaborators - 1))
+            .execute(conn)
+            .await?;
+
         diesel::delete(users::table.filter(users::id.eq(id)))
             .execute(conn)
             .await
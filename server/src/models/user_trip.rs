@@ -1,7 +1,92 @@
-use crate::schema::user_trip;
-use diesel::prelude::*;
+use std::{fmt, io::Write, str::FromStr};
+
+use crate::schema::{trips, user_trip};
+use chrono::{DateTime, Utc};
+use diesel::{
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::{Pg, PgValue},
+    prelude::*,
+    serialize::{self, IsNull, Output, ToSql},
+    sql_types::Text,
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+/// What a collaborator may do on a trip, stored in `user_trip.permission`. Roles are ordered,
+/// each one can do everything the ones before it can.
+#[typeshare]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    ToSchema,
+    AsExpression,
+    FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Can see the trip
+    Viewer,
+    /// Can change the trip and invite people
+    Editor,
+    /// Can delete the trip and hand it over, there's exactly one per trip
+    Owner,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Owner => "owner",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "editor" => Ok(Role::Editor),
+            "owner" => Ok(Role::Owner),
+            _ => Err(format!("unknown role `{s}`")),
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for Role {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for Role {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        Ok(std::str::from_utf8(bytes.as_bytes())?.parse()?)
+    }
+}
+
 #[derive(Debug, Queryable, Selectable, Identifiable)]
 #[diesel(table_name = user_trip)]
 #[diesel(primary_key(user_id, trip_id))]
@@ -10,5 +95,131 @@ use uuid::Uuid;
 pub struct UserTrip {
     pub user_id: Uuid,
     pub trip_id: Uuid,
-    pub permission: Option<String>,
+    #[diesel(column_name = permission)]
+    pub role: Role,
+    pub id: Uuid,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl UserTrip {
+    pub async fn find(
+        conn: &mut AsyncPgConnection,
+        user_id: &Uuid,
+        trip_id: &Uuid,
+    ) -> QueryResult<UserTrip> {
+        user_trip::table
+            .find((user_id, trip_id))
+            .select(UserTrip::as_select())
+            .first(conn)
+            .await
+    }
+
+    /// Looks up a membership by its collaborator ID, the key clients use to refer to it.
+    pub async fn find_by_id(conn: &mut AsyncPgConnection, id: &Uuid) -> QueryResult<UserTrip> {
+        user_trip::table
+            .filter(user_trip::id.eq(id))
+            .select(UserTrip::as_select())
+            .first(conn)
+            .await
+    }
+
+    /// Trips the user is on, leaving out deleted ones.
+    pub async fn find_trip_ids(
+        conn: &mut AsyncPgConnection,
+        user_id: &Uuid,
+    ) -> QueryResult<Vec<Uuid>> {
+        user_trip::table
+            .inner_join(trips::table)
+            .filter(user_trip::user_id.eq(user_id))
+            .filter(trips::deleted_at.is_null())
+            .select(user_trip::trip_id)
+            .load(conn)
+            .await
+    }
+
+    /// IDs of everyone on any of the given trips.
+    pub async fn find_member_ids(
+        conn: &mut AsyncPgConnection,
+        trip_ids: &[Uuid],
+    ) -> QueryResult<Vec<Uuid>> {
+        user_trip::table
+            .filter(user_trip::trip_id.eq_any(trip_ids))
+            .select(user_trip::user_id)
+            .distinct()
+            .load(conn)
+            .await
+    }
+
+    /// Who inherits the trip when `owner_id` leaves: the editor who's been on it the longest.
+    /// Viewers never inherit a trip, without an editor there's no successor.
+    pub async fn find_successor(
+        conn: &mut AsyncPgConnection,
+        trip_id: &Uuid,
+        owner_id: &Uuid,
+    ) -> QueryResult<Option<UserTrip>> {
+        user_trip::table
+            .filter(user_trip::trip_id.eq(trip_id))
+            .filter(user_trip::user_id.ne(owner_id))
+            .filter(user_trip::permission.eq(Role::Editor))
+            .order(user_trip::joined_at.asc())
+            .select(UserTrip::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    pub async fn set_role(&self, conn: &mut AsyncPgConnection, role: Role) -> QueryResult<usize> {
+        diesel::update(user_trip::table.filter(user_trip::id.eq(self.id)))
+            .set(user_trip::permission.eq(role))
+            .execute(conn)
+            .await
+    }
+
+    /// Removes the membership and keeps the trip's `no_collaborators` count in step.
+    pub async fn delete(&self, conn: &mut AsyncPgConnection) -> QueryResult<usize> {
+        let deleted = diesel::delete(user_trip::table.filter(user_trip::id.eq(self.id)))
+            .execute(conn)
+            .await?;
+
+        if deleted > 0 {
+            diesel::update(trips::table.find(self.trip_id))
+                .set(trips::no_collaborators.eq(trips::no_collaborators - 1))
+                .execute(conn)
+                .await?;
+        }
+
+        Ok(deleted)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = user_trip)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewUserTrip {
+    pub id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub trip_id: Uuid,
+    #[diesel(column_name = permission)]
+    pub role: Role,
+}
+
+impl NewUserTrip {
+    /// Adds the membership unless the user is already on the trip. Returns whether a row was
+    /// inserted, and bumps the trip's `no_collaborators` when it was.
+    pub async fn insert(&self, conn: &mut AsyncPgConnection) -> QueryResult<bool> {
+        let inserted = diesel::insert_into(user_trip::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await?;
+
+        if inserted > 0 {
+            diesel::update(trips::table.find(self.trip_id))
+                .set(trips::no_collaborators.eq(trips::no_collaborators + 1))
+                .execute(conn)
+                .await?;
+        }
+
+        Ok(inserted > 0)
+    }
 }
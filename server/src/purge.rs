@@ -0,0 +1,70 @@
+//! Purging of deleted trips.
+//!
+//! Deleting a trip only marks it deleted, so the owner can restore it for `grace_period_days`.
+//! After that it's deleted for good along with everything on it, and its files are removed from
+//! S3. Files are only removed once the trip is, a trip that failed to purge still has them.
+
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use diesel::QueryResult;
+use diesel_async::{AsyncPgConnection, pooled_connection::deadpool::Pool};
+
+use crate::{config::TripPurgeConfig, models::trip::Trip, s3_client::S3Client};
+
+/// Removes files of purged trips from S3. A file that can't be removed is logged and left behind.
+pub async fn delete_files(s3: Option<&S3Client>, urls: &[String]) {
+    let Some(s3) = s3 else {
+        return;
+    };
+
+    for url in urls {
+        if !s3.delete_file(&s3.get_key_from_url(url)).await {
+            log::warn!("failed to delete {url} of a purged trip");
+        }
+    }
+}
+
+/// Purges trips that were deleted before the grace period. Returns how many were purged.
+pub async fn purge(
+    conn: &mut AsyncPgConnection,
+    s3: Option<&S3Client>,
+    config: &TripPurgeConfig,
+) -> QueryResult<usize> {
+    let horizon = Utc::now() - TimeDelta::days(config.grace_period_days.get().into());
+
+    let trip_ids = Trip::find_purgeable(conn, horizon).await?;
+
+    for trip_id in trip_ids.iter() {
+        let files = Trip::purge(conn, trip_id).await?;
+
+        delete_files(s3, &files).await;
+    }
+
+    Ok(trip_ids.len())
+}
+
+/// Purges on an interval for as long as the server runs. A failed run is logged and retried on
+/// the next one.
+pub async fn run(database: Pool<AsyncPgConnection>, s3: Option<S3Client>, config: TripPurgeConfig) {
+    let mut interval = actix_web::rt::time::interval(Duration::from_secs(
+        config.purge_interval_minutes.get() * 60,
+    ));
+
+    loop {
+        interval.tick().await;
+
+        let mut conn = match database.get().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::warn!("trip purge couldn't connect: {e}");
+                continue;
+            }
+        };
+
+        match purge(&mut conn, s3.as_ref(), &config).await {
+            Ok(purged) => log::info!("trip purge deleted {purged} trips"),
+            Err(e) => log::warn!("trip purge failed: {e}"),
+        }
+    }
+}
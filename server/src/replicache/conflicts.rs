@@ -0,0 +1,192 @@
+//! Conflicts that shouldn't be merged silently, so they're recorded for a collaborator to review:
+//! - A cost change that arrives after a newer one to the same expense. The newer cost is kept, as
+//!   for any field, and the older one is recorded as the proposed value.
+//! - An edit to an item that was deleted in the meantime, or a delete of an item that was edited
+//!   after the delete was made. Deleting wins, and the edited item is recorded so it can be
+//!   brought back.
+//!
+//! Resolving a conflict either keeps the current state or applies the proposed value.
+
+use chrono::Utc;
+use diesel::{
+    QueryResult, QueryableByName,
+    sql_types::{Double, Jsonb, Text, Uuid as SqlUuid},
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    models::sync_conflict::{NewSyncConflict, SyncConflict},
+    replicache::entities,
+    util::errors::{AppError, AppResult},
+};
+
+/// The tables whose rows can end up in a conflict.
+#[derive(Clone, Copy, Debug)]
+pub enum SyncedTable {
+    ItineraryItem,
+    Expense,
+    Task,
+}
+
+impl SyncedTable {
+    fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            entities::ITINERARY_ITEM => Some(Self::ItineraryItem),
+            entities::EXPENSE => Some(Self::Expense),
+            entities::TASK => Some(Self::Task),
+            _ => None,
+        }
+    }
+
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::ItineraryItem => entities::ITINERARY_ITEM,
+            Self::Expense => entities::EXPENSE,
+            Self::Task => entities::TASK,
+        }
+    }
+
+    fn table_name(self) -> &'static str {
+        match self {
+            Self::ItineraryItem => "itinerary_items",
+            Self::Expense => "expenses",
+            Self::Task => "tasks",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictWinner {
+    Current,
+    Proposed,
+}
+
+#[derive(QueryableByName)]
+struct DeletedRow {
+    #[diesel(sql_type = SqlUuid)]
+    trip_id: Uuid,
+    #[diesel(sql_type = Jsonb)]
+    data: serde_json::Value,
+}
+
+/// Brings back a deleted row from its tombstone. Returns whether there was one to bring back.
+pub async fn restore_deleted(
+    conn: &mut AsyncPgConnection,
+    table: SyncedTable,
+    id: &Uuid,
+) -> QueryResult<bool> {
+    let restored = diesel::sql_query(format!(
+        "INSERT INTO {table} \
+         SELECT (jsonb_populate_record(NULL::{table}, data)).* FROM replicache_tombstones \
+         WHERE key = $1 AND data IS NOT NULL \
+         ON CONFLICT DO NOTHING",
+        table = table.table_name()
+    ))
+    .bind::<Text, _>(entities::key(table.prefix(), id))
+    .execute(conn)
+    .await?;
+
+    Ok(restored > 0)
+}
+
+/// Deletes a row and records it as a conflict, so it can be brought back on review.
+pub async fn delete_for_review(
+    conn: &mut AsyncPgConnection,
+    table: SyncedTable,
+    id: &Uuid,
+    proposed_by: Option<Uuid>,
+) -> QueryResult<()> {
+    let deleted: DeletedRow = diesel::sql_query(format!(
+        "DELETE FROM {table} WHERE id = $1 \
+         RETURNING trip_id, to_jsonb({table}) - 'version' - 'field_versions' AS data",
+        table = table.table_name()
+    ))
+    .bind::<SqlUuid, _>(id)
+    .get_result(conn)
+    .await?;
+
+    NewSyncConflict {
+        trip_id: deleted.trip_id,
+        entity_type: table.prefix(),
+        entity_id: *id,
+        field: None,
+        current_value: None,
+        proposed_value: deleted.data,
+        proposed_by,
+    }
+    .insert(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Settles a conflict. Applying the proposed value counts as the newest write to the field, so
+/// changes that were made before the review can't bring the old value back.
+pub async fn resolve(
+    conn: &mut AsyncPgConnection,
+    conflict: &SyncConflict,
+    winner: ConflictWinner,
+) -> AppResult<()> {
+    if winner == ConflictWinner::Proposed {
+        let table =
+            SyncedTable::from_prefix(&conflict.entity_type).ok_or(AppError::InternalError)?;
+
+        match &conflict.field {
+            Some(field) => apply_field(conn, table, conflict, field).await?,
+            None => restore_snapshot(conn, table, &conflict.proposed_value).await?,
+        };
+    }
+
+    SyncConflict::delete(conn, &conflict.id).await?;
+
+    Ok(())
+}
+
+async fn apply_field(
+    conn: &mut AsyncPgConnection,
+    table: SyncedTable,
+    conflict: &SyncConflict,
+    field: &str,
+) -> QueryResult<usize> {
+    // fields only ever come from this module, but they end up in the query text
+    if !field.chars().all(|c| c.is_ascii_lowercase() || c == '_') {
+        return Err(diesel::result::Error::NotFound);
+    }
+
+    diesel::sql_query(format!(
+        "UPDATE {table} SET \
+         {field} = (jsonb_populate_record(NULL::{table}, jsonb_build_object($2::text, $3))).{field}, \
+         field_versions = field_versions || jsonb_build_object(\
+           $2::text, GREATEST(COALESCE((field_versions ->> $2)::float8, 0), $4)) \
+         WHERE id = $1",
+        table = table.table_name()
+    ))
+    .bind::<SqlUuid, _>(conflict.entity_id)
+    .bind::<Text, _>(field)
+    .bind::<Jsonb, _>(&conflict.proposed_value)
+    .bind::<Double, _>(Utc::now().timestamp_millis() as f64)
+    .execute(conn)
+    .await
+}
+
+async fn restore_snapshot(
+    conn: &mut AsyncPgConnection,
+    table: SyncedTable,
+    snapshot: &serde_json::Value,
+) -> QueryResult<usize> {
+    // snapshots leave out the sync bookkeeping, which the row can't do without
+    diesel::sql_query(format!(
+        "INSERT INTO {table} \
+         SELECT (jsonb_populate_record(NULL::{table}, \
+           '{{\"version\": 0, \"field_versions\": {{}}}}'::jsonb || $1)).* \
+         ON CONFLICT DO NOTHING",
+        table = table.table_name()
+    ))
+    .bind::<Jsonb, _>(snapshot)
+    .execute(conn)
+    .await
+}
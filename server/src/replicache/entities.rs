@@ -0,0 +1,239 @@
+//! The shapes the web client stores in Replicache, see `app/web/src/models`. Optional fields are
+//! left out rather than sent as `null`, since the client's zod schemas reject `null`.
+
+use bigdecimal::ToPrimitive;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::{
+    expense::{Expense, ExpensePayer},
+    itinerary_item::ItineraryItem,
+    sync_conflict::SyncConflict,
+    task::Task,
+    trip::Trip,
+    user_trip::{Role, UserTrip},
+};
+
+// Key prefixes, these have to match the arguments given to the tombstone triggers.
+pub const TRIP: &str = "trip";
+pub const COLLABORATOR: &str = "collaborator";
+pub const ITINERARY_ITEM: &str = "itineraryItem";
+pub const EXPENSE: &str = "expense";
+pub const EXPENSE_PAYER: &str = "expensePayer";
+pub const TASK: &str = "task";
+pub const CONFLICT: &str = "conflict";
+
+pub fn key(prefix: &str, id: &Uuid) -> String {
+    format!("{prefix}/{id}")
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TripEntity {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl TripEntity {
+    /// `owner_collaborator_id` is the owner's `user_trip` ID: on the client a trip's owner
+    /// points at a collaborator rather than a user.
+    pub fn new(trip: Trip, owner_collaborator_id: Option<Uuid>) -> Self {
+        Self {
+            id: trip.id,
+            owner_id: owner_collaborator_id.unwrap_or(trip.owner_id),
+            name: trip.title.unwrap_or_default(),
+            description: trip.description,
+            start_date: trip.start_date.map(|date| date.to_string()),
+            end_date: trip.end_date.map(|date| date.to_string()),
+            cover_image: trip.banner_image,
+            archived_at: trip.archived_at.map(|time| time.to_rfc3339()),
+            created_at: trip.created_at.unwrap_or(trip.updated_at).to_rfc3339(),
+            updated_at: trip.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollaboratorEntity {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub email: String,
+    pub role: Role,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+}
+
+impl CollaboratorEntity {
+    pub fn new(
+        user_trip: UserTrip,
+        username: String,
+        email: String,
+        avatar_url: Option<String>,
+    ) -> Self {
+        Self {
+            id: user_trip.id,
+            trip_id: user_trip.trip_id,
+            user_id: user_trip.user_id,
+            username,
+            email,
+            role: user_trip.role,
+            avatar_url,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItineraryItemEntity {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub start_date_time: String,
+    pub end_date_time: String,
+    pub all_day: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Uuid>,
+    pub notes: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expense_id: Option<Uuid>,
+}
+
+impl From<ItineraryItem> for ItineraryItemEntity {
+    fn from(value: ItineraryItem) -> Self {
+        Self {
+            id: value.id,
+            trip_id: value.trip_id,
+            name: value.title,
+            description: value.description,
+            start_date_time: value.start_time.to_rfc3339(),
+            end_date_time: value.end_time.unwrap_or(value.start_time).to_rfc3339(),
+            all_day: value.all_day,
+            location: value.location_id,
+            notes: value.notes,
+            expense_id: value.expense_id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpenseEntity {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub amount: f64,
+    pub currency: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+impl From<Expense> for ExpenseEntity {
+    fn from(value: Expense) -> Self {
+        Self {
+            id: value.id,
+            trip_id: value.trip_id,
+            name: value.title.unwrap_or_default(),
+            description: value.description,
+            amount: value.cost.to_f64().unwrap_or_default(),
+            currency: value.currency,
+            category: value.category,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpensePayerEntity {
+    pub id: Uuid,
+    pub expense_id: Uuid,
+    pub collaborator_id: Uuid,
+}
+
+impl ExpensePayerEntity {
+    pub fn new(payer: ExpensePayer, collaborator_id: Uuid) -> Self {
+        Self {
+            id: payer.id,
+            expense_id: payer.expense_id,
+            collaborator_id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskEntity {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub title: String,
+    pub description: String,
+    pub completed: bool,
+    pub position: String,
+    pub urgency: String,
+}
+
+impl From<Task> for TaskEntity {
+    fn from(value: Task) -> Self {
+        Self {
+            id: value.id,
+            trip_id: value.trip_id,
+            title: value.title,
+            description: value.description,
+            completed: value.completed,
+            position: value.position,
+            urgency: value.urgency,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflictEntity {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_value: Option<serde_json::Value>,
+    pub proposed_value: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proposed_by: Option<Uuid>,
+    pub created_at: String,
+}
+
+impl From<SyncConflict> for SyncConflictEntity {
+    fn from(value: SyncConflict) -> Self {
+        Self {
+            id: value.id,
+            trip_id: value.trip_id,
+            entity_type: value.entity_type,
+            entity_id: value.entity_id,
+            field: value.field,
+            current_value: value.current_value,
+            proposed_value: value.proposed_value,
+            proposed_by: value.proposed_by,
+            created_at: value.created_at.to_rfc3339(),
+        }
+    }
+}
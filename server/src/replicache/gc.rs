@@ -0,0 +1,78 @@
+//! Garbage collection of sync state that no client needs anymore.
+//!
+//! Every browser tab gets its own client group, so groups and their clients would pile up
+//! forever. A group that hasn't pulled for `client_retention_days` is deleted along with its
+//! clients, and if it ever comes back it's told to start over, see `client_group_collected`.
+//!
+//! Tombstones only matter to clients that synced the deleted row before it was deleted. Every
+//! group that's left pulled within the retention period, so tombstones older than that have been
+//! seen by all of them and are deleted as well.
+
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use diesel::{OptionalExtension, QueryResult};
+use diesel_async::{AsyncPgConnection, pooled_connection::deadpool::Pool};
+
+use crate::{
+    config::ReplicacheConfig,
+    models::replicache::{ReplicacheClientGroup, ReplicacheTombstone},
+};
+
+/// Whether a client group was collected. Clients only push or pull with state from earlier
+/// syncs (`resuming`) if their group existed at some point.
+pub async fn client_group_collected(
+    conn: &mut AsyncPgConnection,
+    client_group_id: &str,
+    resuming: bool,
+) -> QueryResult<bool> {
+    if !resuming {
+        return Ok(false);
+    }
+
+    let client_group = ReplicacheClientGroup::find(conn, client_group_id)
+        .await
+        .optional()?;
+
+    Ok(client_group.is_none())
+}
+
+/// Deletes client groups and tombstones that are past the retention period. Returns how many of
+/// each were deleted.
+pub async fn collect(
+    conn: &mut AsyncPgConnection,
+    config: &ReplicacheConfig,
+) -> QueryResult<(usize, usize)> {
+    let horizon = Utc::now() - TimeDelta::days(config.client_retention_days.get().into());
+
+    let client_groups = ReplicacheClientGroup::delete_inactive(conn, horizon).await?;
+    let tombstones = ReplicacheTombstone::delete_before(conn, horizon).await?;
+
+    Ok((client_groups, tombstones))
+}
+
+/// Collects on an interval for as long as the server runs. A failed run is logged and retried
+/// on the next one.
+pub async fn run(database: Pool<AsyncPgConnection>, config: ReplicacheConfig) {
+    let mut interval =
+        actix_web::rt::time::interval(Duration::from_secs(config.gc_interval_minutes.get() * 60));
+
+    loop {
+        interval.tick().await;
+
+        let mut conn = match database.get().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::warn!("replicache garbage collection couldn't connect: {e}");
+                continue;
+            }
+        };
+
+        match collect(&mut conn, &config).await {
+            Ok((client_groups, tombstones)) => log::info!(
+                "replicache garbage collection deleted {client_groups} client groups and {tombstones} tombstones"
+            ),
+            Err(e) => log::warn!("replicache garbage collection failed: {e}"),
+        }
+    }
+}
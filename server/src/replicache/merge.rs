@@ -0,0 +1,125 @@
+//! Field-level last-writer-wins merging of concurrent edits.
+//!
+//! Trips, itinerary items, expenses and tasks keep a `field_versions` map from column name to the
+//! client timestamp of the mutation that last wrote that column. An update only overwrites the
+//! fields it is at least as new as, so collaborators editing different fields of the same row
+//! offline, or one editing a task while another reorders it, both keep their changes.
+//!
+//! Merge policy per mutator:
+//! - `create*`: the row is inserted as sent, replaying a create for an existing row does nothing.
+//! - `update*` on trips, itinerary items, expenses and tasks: the newest write to each field wins,
+//!   ties go to the mutation processed last.
+//! - `updateCollaborator`: the role change processed last wins.
+//! - `delete*`: deleting wins over concurrent edits. Edits that lose against a delete are kept as
+//!   conflicts for review, as are stale expense costs, see `replicache::conflicts`.
+//! - `createExpensePayer` / `deleteExpensePayer`: payers are a set, adding and removing are
+//!   idempotent.
+//! - `createTask`: the server picks the position, so concurrent creates never share one.
+
+use serde_json::{Map, Value};
+
+#[derive(Debug, Default)]
+pub struct FieldVersions {
+    versions: Map<String, Value>,
+    changed: bool,
+}
+
+impl FieldVersions {
+    pub fn new(versions: Value) -> Self {
+        match versions {
+            Value::Object(versions) => Self {
+                versions,
+                changed: false,
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// Returns `value` if a write made at `timestamp` is at least as new as the last write to
+    /// `field`, and records the write. Stale writes are dropped.
+    pub fn merge<T>(&mut self, field: &str, value: Option<T>, timestamp: f64) -> Option<T> {
+        let value = value?;
+
+        if self.is_stale(field, timestamp) {
+            return None;
+        }
+
+        self.versions
+            .insert(field.to_string(), Value::from(timestamp));
+        self.changed = true;
+
+        Some(value)
+    }
+
+    /// Whether a write made at `timestamp` is older than the last write to `field`.
+    pub fn is_stale(&self, field: &str, timestamp: f64) -> bool {
+        self.versions
+            .get(field)
+            .and_then(Value::as_f64)
+            .is_some_and(|last_write| timestamp < last_write)
+    }
+
+    /// The time of the newest write to any field.
+    pub fn newest(&self) -> Option<f64> {
+        self.versions
+            .values()
+            .filter_map(Value::as_f64)
+            .reduce(f64::max)
+    }
+
+    /// The updated versions, if any field was written.
+    pub fn into_changes(self) -> Option<Value> {
+        self.changed.then_some(Value::Object(self.versions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn newer_writes_win() {
+        let mut versions = FieldVersions::new(json!({ "title": 10.0 }));
+
+        assert_eq!(versions.merge("title", Some("new"), 20.0), Some("new"));
+        assert_eq!(versions.into_changes(), Some(json!({ "title": 20.0 })));
+    }
+
+    #[test]
+    fn stale_writes_are_dropped() {
+        let mut versions = FieldVersions::new(json!({ "title": 20.0 }));
+
+        assert_eq!(versions.merge("title", Some("old"), 10.0), None);
+        assert_eq!(versions.into_changes(), None);
+    }
+
+    #[test]
+    fn fields_merge_independently() {
+        let mut versions = FieldVersions::new(json!({ "title": 20.0 }));
+
+        assert_eq!(versions.merge("title", Some("old"), 10.0), None);
+        assert_eq!(versions.merge("position", Some("b"), 10.0), Some("b"));
+        assert_eq!(versions.merge::<&str>("notes", None, 30.0), None);
+        assert_eq!(
+            versions.into_changes(),
+            Some(json!({ "title": 20.0, "position": 10.0 }))
+        );
+    }
+
+    #[test]
+    fn newest_covers_every_field() {
+        let versions = FieldVersions::new(json!({ "title": 10.0, "notes": 30.0 }));
+
+        assert_eq!(versions.newest(), Some(30.0));
+        assert_eq!(FieldVersions::default().newest(), None);
+    }
+
+    #[test]
+    fn ties_go_to_the_last_write() {
+        let mut versions = FieldVersions::new(json!({ "title": 10.0 }));
+
+        assert_eq!(versions.merge("title", Some("later"), 10.0), Some("later"));
+    }
+}
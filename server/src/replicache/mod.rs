@@ -0,0 +1,148 @@
+pub mod conflicts;
+pub mod entities;
+pub mod gc;
+pub mod merge;
+pub mod mutators;
+pub mod poke;
+pub mod pull;
+pub mod upgrades;
+
+use diesel_async::{AsyncConnection, AsyncPgConnection, scoped_futures::ScopedFutureExt};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    models::replicache::{ReplicacheClient, ReplicacheClientGroup, ReplicacheSpace},
+    util::errors::{AppError, AppResult},
+};
+
+// Namespace for turning client-generated IDs (e.g. nanoids) into stable UUIDs.
+const CLIENT_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6a0f_3c1e_52d4_4b8e_9e57_2f0c_8d3b_71a4);
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Mutation {
+    #[serde(rename = "clientID")]
+    pub client_id: String,
+    pub id: i32,
+    pub name: String,
+    #[schema(value_type = Object)]
+    pub args: serde_json::Value,
+    pub timestamp: f64,
+}
+
+/// Errors Replicache understands in place of a push or pull response. They're sent with a 200,
+/// anything else is treated as a failed request and retried.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "error")]
+pub enum ReplicacheError {
+    /// The client group was garbage collected, the client has to start over with a new one
+    ClientStateNotFound,
+    /// The client is newer than the server, it keeps its mutations until the server catches up
+    VersionNotSupported {
+        #[serde(rename = "versionType")]
+        version_type: String,
+    },
+}
+
+/// Each trip is its own space, versioning the rows that belong to it.
+pub fn trip_space_id(trip_id: &Uuid) -> String {
+    trip_id.to_string()
+}
+
+/// A user's client groups share a space, versioning the last mutation IDs of their clients.
+pub fn user_space_id(user_id: &Uuid) -> String {
+    format!("user/{user_id}")
+}
+
+/// Maps an ID generated by a client onto the UUID primary keys used by the database. UUIDs pass
+/// through untouched, anything else is hashed into a deterministic v5 UUID so later mutations
+/// referring to the same client ID land on the same row.
+pub fn client_uuid(id: &str) -> Uuid {
+    Uuid::parse_str(id).unwrap_or_else(|_| Uuid::new_v5(&CLIENT_ID_NAMESPACE, id.as_bytes()))
+}
+
+/// Applies a single pushed mutation in its own transaction.
+///
+/// Mutations that were already applied are skipped. A mutation whose mutator fails is still
+/// marked as processed, otherwise the client would keep pushing it forever; the failure is
+/// logged and the database changes it made are rolled back.
+///
+/// `version` is the mutator version the mutation was made at, see `replicache::upgrades`.
+///
+/// Returns the trip the mutation changed, if it changed one.
+pub async fn process_mutation(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    client_group_id: &str,
+    mutation: &Mutation,
+    version: u32,
+) -> AppResult<Option<Uuid>> {
+    conn.transaction(|conn| {
+        async move {
+            let space = ReplicacheSpace::lock(conn, &user_space_id(user_id)).await?;
+
+            let client_group =
+                ReplicacheClientGroup::find_or_create(conn, client_group_id, user_id, &space.id)
+                    .await?;
+
+            if client_group.user_id != *user_id {
+                return Err(AppError::Forbidden("Client group belongs to another user."));
+            }
+
+            let client =
+                ReplicacheClient::find_or_create(conn, &mutation.client_id, client_group_id)
+                    .await?;
+
+            if client.client_group_id != client_group_id {
+                return Err(AppError::BadRequest(
+                    "Client does not belong to this client group.",
+                ));
+            }
+
+            let expected_id = client.last_mutation_id + 1;
+
+            if mutation.id < expected_id {
+                log::debug!(
+                    "skipping mutation {} from client {}, already processed",
+                    mutation.id,
+                    client.id
+                );
+                return Ok(None);
+            }
+
+            if mutation.id > expected_id {
+                return Err(AppError::BadRequest("Mutation is from the future."));
+            }
+
+            let result = conn
+                .transaction(|conn| mutators::apply(conn, user_id, mutation, version).scope_boxed())
+                .await;
+
+            let trip_id = match result {
+                Ok(trip_id) => Some(trip_id),
+                Err(e) => {
+                    log::warn!(
+                        "mutation {} ({}) from client {} failed: {}",
+                        mutation.id,
+                        mutation.name,
+                        client.id,
+                        e
+                    );
+                    None
+                }
+            };
+
+            // the client's new last mutation ID has to show up in the next pull as well
+            let version = ReplicacheSpace::next_version(conn, &space.id).await?;
+
+            client
+                .set_last_mutation_id(conn, expected_id, version)
+                .await?;
+
+            Ok(trip_id)
+        }
+        .scope_boxed()
+    })
+    .await
+}
@@ -0,0 +1,892 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::OptionalExtension;
+use diesel_async::AsyncPgConnection;
+use serde::Deserialize;
+use serde_json::json;
+use typeshare::typeshare;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        expense::{Expense, ExpenseChanges, ExpensePayer},
+        itinerary_item::{DEFAULT_ACTIVITY_TYPE, ItineraryItem, ItineraryItemChanges},
+        sync_conflict::NewSyncConflict,
+        task::{NewTask, Task, TaskChanges},
+        trip::{NewTrip, Trip, TripChanges},
+        user_trip::{Role, UserTrip},
+    },
+    replicache::{
+        Mutation, client_uuid,
+        conflicts::{self, SyncedTable},
+        entities,
+        merge::FieldVersions,
+        upgrades,
+    },
+};
+
+const DEFAULT_URGENCY: &str = "low";
+const FIRST_POSITION: &str = "a";
+
+#[derive(Debug, thiserror::Error)]
+pub enum MutationError {
+    #[error("unknown mutator `{0}`")]
+    UnknownMutator(String),
+    #[error("invalid arguments: {0}")]
+    InvalidArgs(#[from] serde_json::Error),
+    #[error("invalid date `{0}`")]
+    InvalidDate(String),
+    #[error("user is not allowed to modify trip {0}")]
+    Forbidden(Uuid),
+    #[error("location {0} is not on trip {1}")]
+    ForeignLocation(Uuid, Uuid),
+    #[error(transparent)]
+    Database(#[from] diesel::result::Error),
+}
+
+/// Mutators resolve to the ID of the trip they changed, so the push can poke that trip's clients.
+type MutationResult = Result<Uuid, MutationError>;
+
+/// The version of the argument shapes below. Bump it whenever a mutator's arguments change in a
+/// way older servers can't read, and add an upgrade from the previous shapes to
+/// `replicache::upgrades`. The web client sends it as its Replicache `schemaVersion`.
+pub const MUTATOR_VERSION: u32 = 3;
+
+/// Every mutator the server can replay. Mutator names and argument shapes mirror
+/// `app/web/src/mutators.tsx`, the TypeScript types are generated from these with typeshare.
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(tag = "name", content = "args", rename_all = "camelCase")]
+pub enum Mutator {
+    CreateTrip(CreateTripArgs),
+    UpdateTrip(TripUpdate),
+    DeleteTrip(DeleteArgs),
+    // collaborators join through invites, see `controllers::invite`
+    UpdateCollaborator(CollaboratorUpdate),
+    DeleteCollaborator(DeleteArgs),
+    CreateItineraryItem(ItineraryItemCreate),
+    UpdateItineraryItem(UpdateItineraryItemArgs),
+    DeleteItineraryItem(DeleteArgs),
+    CreateExpense(ExpenseCreate),
+    UpdateExpense(UpdateExpenseArgs),
+    DeleteExpense(DeleteArgs),
+    CreateExpensePayer(ExpensePayerCreate),
+    DeleteExpensePayer(DeleteArgs),
+    CreateTask(TaskCreate),
+    UpdateTask(TaskUpdate),
+    DeleteTask(DeleteArgs),
+    /// A mutator this server doesn't know, e.g. one that was removed
+    #[typeshare(skip)]
+    #[serde(other)]
+    Unknown,
+}
+
+impl Mutator {
+    /// Reads a mutation pushed by a client at `version`, upgrading older argument shapes first.
+    pub fn parse(mutation: &Mutation, version: u32) -> Result<Self, MutationError> {
+        let args = upgrades::upgrade(&mutation.name, mutation.args.clone(), version);
+
+        let unknown = || MutationError::UnknownMutator(mutation.name.clone());
+
+        match serde_json::from_value(json!({ "name": mutation.name, "args": args })) {
+            Ok(Mutator::Unknown) => Err(unknown()),
+            Ok(mutator) => Ok(mutator),
+            // `Unknown` only matches without arguments, so tell it apart by the name alone
+            Err(e) => match serde_json::from_value(json!({ "name": mutation.name })) {
+                Ok(Mutator::Unknown) => Err(unknown()),
+                _ => Err(e.into()),
+            },
+        }
+    }
+}
+
+/// Replays a client mutator against the database. How concurrent mutations are merged is
+/// described in `replicache::merge`.
+pub async fn apply(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    mutation: &Mutation,
+    version: u32,
+) -> MutationResult {
+    let timestamp = mutation.timestamp;
+
+    match Mutator::parse(mutation, version)? {
+        Mutator::CreateTrip(args) => create_trip(conn, user_id, args).await,
+        Mutator::UpdateTrip(args) => update_trip(conn, user_id, args, timestamp).await,
+        Mutator::DeleteTrip(args) => delete_trip(conn, user_id, args).await,
+        Mutator::UpdateCollaborator(args) => update_collaborator(conn, user_id, args).await,
+        Mutator::DeleteCollaborator(args) => delete_collaborator(conn, user_id, args).await,
+        Mutator::CreateItineraryItem(args) => create_itinerary_item(conn, user_id, args).await,
+        Mutator::UpdateItineraryItem(args) => {
+            update_itinerary_item(conn, user_id, args, timestamp).await
+        }
+        Mutator::DeleteItineraryItem(args) => {
+            delete_itinerary_item(conn, user_id, args, timestamp).await
+        }
+        Mutator::CreateExpense(args) => create_expense(conn, user_id, args).await,
+        Mutator::UpdateExpense(args) => update_expense(conn, user_id, args, timestamp).await,
+        Mutator::DeleteExpense(args) => delete_expense(conn, user_id, args, timestamp).await,
+        Mutator::CreateExpensePayer(args) => create_expense_payer(conn, user_id, args).await,
+        Mutator::DeleteExpensePayer(args) => delete_expense_payer(conn, user_id, args).await,
+        Mutator::CreateTask(args) => create_task(conn, user_id, args).await,
+        Mutator::UpdateTask(args) => update_task(conn, user_id, args, timestamp).await,
+        Mutator::DeleteTask(args) => delete_task(conn, user_id, args, timestamp).await,
+        Mutator::Unknown => Err(MutationError::UnknownMutator(mutation.name.clone())),
+    }
+}
+
+/// Accepts either a plain date or the ISO timestamps the web client produces.
+fn parse_date(value: &str) -> Result<NaiveDate, MutationError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.date_naive())
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d"))
+        .map_err(|_| MutationError::InvalidDate(value.to_string()))
+}
+
+fn parse_optional_date(value: Option<&str>) -> Result<Option<NaiveDate>, MutationError> {
+    value.map(parse_date).transpose()
+}
+
+/// Same scheme as `getNextPosition` in the web client: bump the last character.
+fn next_position(position: &str) -> String {
+    let mut chars: Vec<char> = position.chars().collect();
+
+    match chars.pop().and_then(|last| char::from_u32(last as u32 + 1)) {
+        Some(next) => {
+            chars.push(next);
+            chars.into_iter().collect()
+        }
+        None => FIRST_POSITION.to_string(),
+    }
+}
+
+/// Same check as the `TripMember` extractor, for mutations.
+async fn require_role(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    trip_id: &Uuid,
+    role: Role,
+) -> Result<UserTrip, MutationError> {
+    // deleted trips can't be changed until they're restored
+    if Trip::find(conn, trip_id).await.optional()?.is_none() {
+        return Err(MutationError::Forbidden(*trip_id));
+    }
+
+    match UserTrip::find(conn, user_id, trip_id).await {
+        Ok(membership) if membership.role >= role => Ok(membership),
+        Ok(_) | Err(diesel::result::Error::NotFound) => Err(MutationError::Forbidden(*trip_id)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Maps the location a client linked onto its row, which has to be one the trip already uses.
+async fn trip_location(
+    conn: &mut AsyncPgConnection,
+    trip_id: &Uuid,
+    location: Option<&str>,
+) -> Result<Option<Uuid>, MutationError> {
+    let Some(location_id) = location.map(client_uuid) else {
+        return Ok(None);
+    };
+
+    if !Trip::location_ids(conn, trip_id)
+        .await?
+        .contains(&location_id)
+    {
+        return Err(MutationError::ForeignLocation(location_id, *trip_id));
+    }
+
+    Ok(Some(location_id))
+}
+
+/// Arguments of every `delete*` mutator.
+#[typeshare]
+#[derive(Debug, Deserialize)]
+pub struct DeleteArgs {
+    pub id: String,
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TripCreate {
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub cover_image: Option<String>,
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+pub struct CreateTripArgs {
+    pub trip: TripCreate,
+}
+
+async fn create_trip(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: CreateTripArgs,
+) -> MutationResult {
+    let trip = args.trip;
+
+    // the owner is always the pushing user, whatever the client put in `args.user`
+    let trip = NewTrip {
+        id: Some(client_uuid(&trip.id)),
+        owner_id: *user_id,
+        title: trip.name.as_deref(),
+        description: trip.description.as_deref(),
+        banner_image: trip.cover_image.as_deref(),
+        start_date: parse_optional_date(trip.start_date.as_deref())?,
+        end_date: parse_optional_date(trip.end_date.as_deref())?,
+    }
+    .insert(conn)
+    .await?;
+
+    Ok(trip.id)
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TripUpdate {
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub cover_image: Option<String>,
+}
+
+async fn update_trip(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: TripUpdate,
+    timestamp: f64,
+) -> MutationResult {
+    let trip_id = client_uuid(&args.id);
+
+    require_role(conn, user_id, &trip_id, Role::Editor).await?;
+
+    let start_date = parse_optional_date(args.start_date.as_deref())?;
+    let end_date = parse_optional_date(args.end_date.as_deref())?;
+
+    let mut versions = FieldVersions::new(Trip::lock_field_versions(conn, &trip_id).await?);
+
+    TripChanges {
+        title: versions.merge("title", args.name, timestamp),
+        description: versions.merge("description", args.description, timestamp),
+        banner_image: versions.merge("banner_image", args.cover_image, timestamp),
+        start_date: versions.merge("start_date", start_date, timestamp),
+        end_date: versions.merge("end_date", end_date, timestamp),
+        updated_at: None,
+        field_versions: versions.into_changes(),
+    }
+    .apply(conn, &trip_id)
+    .await?;
+
+    Ok(trip_id)
+}
+
+async fn delete_trip(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: DeleteArgs,
+) -> MutationResult {
+    let trip_id = client_uuid(&args.id);
+
+    require_role(conn, user_id, &trip_id, Role::Owner).await?;
+
+    Trip::delete(conn, &trip_id).await?;
+
+    Ok(trip_id)
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollaboratorUpdate {
+    pub id: String,
+    pub role: Option<Role>,
+}
+
+async fn update_collaborator(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: CollaboratorUpdate,
+) -> MutationResult {
+    let collaborator = UserTrip::find_by_id(conn, &client_uuid(&args.id)).await?;
+
+    require_role(conn, user_id, &collaborator.trip_id, Role::Editor).await?;
+
+    if let Some(role) = args.role {
+        if role == Role::Owner || collaborator.role == Role::Owner {
+            return Err(MutationError::Forbidden(collaborator.trip_id));
+        }
+
+        collaborator.set_role(conn, role).await?;
+    }
+
+    Trip::touch(conn, &collaborator.trip_id).await?;
+
+    Ok(collaborator.trip_id)
+}
+
+async fn delete_collaborator(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: DeleteArgs,
+) -> MutationResult {
+    let collaborator = UserTrip::find_by_id(conn, &client_uuid(&args.id)).await?;
+
+    // anyone can leave a trip, removing someone else takes an editor
+    if collaborator.user_id != *user_id {
+        require_role(conn, user_id, &collaborator.trip_id, Role::Editor).await?;
+    }
+
+    // the owner can't be removed from their own trip
+    if collaborator.role == Role::Owner {
+        return Err(MutationError::Forbidden(collaborator.trip_id));
+    }
+
+    collaborator.delete(conn).await?;
+
+    Trip::touch(conn, &collaborator.trip_id).await?;
+
+    Ok(collaborator.trip_id)
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItineraryItemCreate {
+    pub id: String,
+    pub trip_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub start_date_time: DateTime<Utc>,
+    pub end_date_time: Option<DateTime<Utc>>,
+    pub all_day: Option<bool>,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    pub expense_id: Option<String>,
+}
+
+async fn create_itinerary_item(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: ItineraryItemCreate,
+) -> MutationResult {
+    let trip_id = client_uuid(&args.trip_id);
+
+    require_role(conn, user_id, &trip_id, Role::Editor).await?;
+
+    let location_id = trip_location(conn, &trip_id, args.location.as_deref()).await?;
+
+    ItineraryItem {
+        id: client_uuid(&args.id),
+        trip_id,
+        title: args.name,
+        activity_type: DEFAULT_ACTIVITY_TYPE.to_string(),
+        location_id,
+        start_time: args.start_date_time,
+        end_time: args.end_date_time,
+        expense_id: args.expense_id.as_deref().map(client_uuid),
+        notes: args.notes.unwrap_or_default(),
+        description: args.description,
+        all_day: args.all_day.unwrap_or_default(),
+        ical_uid: None,
+    }
+    .insert(conn)
+    .await?;
+
+    Trip::touch(conn, &trip_id).await?;
+
+    Ok(trip_id)
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItineraryItemUpdate {
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub start_date_time: Option<DateTime<Utc>>,
+    pub end_date_time: Option<DateTime<Utc>>,
+    pub all_day: Option<bool>,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    pub expense_id: Option<String>,
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateItineraryItemArgs {
+    pub itinerary_item: ItineraryItemUpdate,
+}
+
+async fn update_itinerary_item(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: UpdateItineraryItemArgs,
+    timestamp: f64,
+) -> MutationResult {
+    let update = args.itinerary_item;
+    let item_id = client_uuid(&update.id);
+    let restored = conflicts::restore_deleted(conn, SyncedTable::ItineraryItem, &item_id).await?;
+    let item = ItineraryItem::find(conn, &item_id).await?;
+
+    require_role(conn, user_id, &item.trip_id, Role::Editor).await?;
+
+    let location_id = trip_location(conn, &item.trip_id, update.location.as_deref()).await?;
+    let expense_id = update.expense_id.as_deref().map(client_uuid);
+
+    let mut versions =
+        FieldVersions::new(ItineraryItem::lock_field_versions(conn, &item.id).await?);
+
+    ItineraryItemChanges {
+        title: versions.merge("title", update.name, timestamp),
+        activity_type: None,
+        location_id: versions.merge("location_id", location_id, timestamp),
+        start_time: versions.merge("start_time", update.start_date_time, timestamp),
+        end_time: versions.merge("end_time", update.end_date_time, timestamp),
+        expense_id: versions.merge("expense_id", expense_id, timestamp),
+        notes: versions.merge("notes", update.notes, timestamp),
+        description: versions.merge("description", update.description, timestamp),
+        all_day: versions.merge("all_day", update.all_day, timestamp),
+        field_versions: versions.into_changes(),
+    }
+    .apply(conn, &item.id)
+    .await?;
+
+    // the item was deleted before this edit, it stays deleted and the edit is kept for review
+    if restored {
+        conflicts::delete_for_review(conn, SyncedTable::ItineraryItem, &item.id, Some(*user_id))
+            .await?;
+    }
+
+    Trip::touch(conn, &item.trip_id).await?;
+
+    Ok(item.trip_id)
+}
+
+async fn delete_itinerary_item(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: DeleteArgs,
+    timestamp: f64,
+) -> MutationResult {
+    let item = ItineraryItem::find(conn, &client_uuid(&args.id)).await?;
+
+    require_role(conn, user_id, &item.trip_id, Role::Editor).await?;
+
+    let versions = FieldVersions::new(ItineraryItem::lock_field_versions(conn, &item.id).await?);
+
+    // the item was edited after this delete, it is deleted and the edit is kept for review
+    if versions
+        .newest()
+        .is_some_and(|last_write| last_write > timestamp)
+    {
+        conflicts::delete_for_review(conn, SyncedTable::ItineraryItem, &item.id, None).await?;
+    } else {
+        ItineraryItem::delete(conn, &item.id).await?;
+    }
+
+    Trip::touch(conn, &item.trip_id).await?;
+
+    Ok(item.trip_id)
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpenseCreate {
+    pub id: String,
+    pub trip_id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub category: Option<String>,
+}
+
+async fn create_expense(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: ExpenseCreate,
+) -> MutationResult {
+    let trip_id = client_uuid(&args.trip_id);
+
+    require_role(conn, user_id, &trip_id, Role::Editor).await?;
+
+    Expense {
+        id: client_uuid(&args.id),
+        trip_id,
+        title: args.name,
+        cost: args.amount,
+        currency: args.currency,
+        description: args.description,
+        category: args.category,
+    }
+    .insert(conn)
+    .await?;
+
+    Trip::touch(conn, &trip_id).await?;
+
+    Ok(trip_id)
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpenseUpdate {
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub amount: Option<BigDecimal>,
+    pub currency: Option<String>,
+    pub category: Option<String>,
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateExpenseArgs {
+    pub expense: ExpenseUpdate,
+}
+
+async fn update_expense(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: UpdateExpenseArgs,
+    timestamp: f64,
+) -> MutationResult {
+    let update = args.expense;
+    let expense_id = client_uuid(&update.id);
+    let restored = conflicts::restore_deleted(conn, SyncedTable::Expense, &expense_id).await?;
+
+    // locked before reading the cost, which a stale update is compared against
+    let mut versions = FieldVersions::new(Expense::lock_field_versions(conn, &expense_id).await?);
+    let expense = Expense::find(conn, &expense_id).await?;
+
+    require_role(conn, user_id, &expense.trip_id, Role::Editor).await?;
+
+    // a cost that was changed in the meantime isn't silently reverted, but it's not lost either
+    if let Some(amount) = &update.amount
+        && *amount != expense.cost
+        && versions.is_stale("cost", timestamp)
+    {
+        NewSyncConflict {
+            trip_id: expense.trip_id,
+            entity_type: entities::EXPENSE,
+            entity_id: expense.id,
+            field: Some("cost"),
+            current_value: Some(expense.cost.to_string().into()),
+            proposed_value: amount.to_string().into(),
+            proposed_by: Some(*user_id),
+        }
+        .insert(conn)
+        .await?;
+    }
+
+    ExpenseChanges {
+        title: versions.merge("title", update.name, timestamp),
+        cost: versions.merge("cost", update.amount, timestamp),
+        currency: versions.merge("currency", update.currency, timestamp),
+        description: versions.merge("description", update.description, timestamp),
+        category: versions.merge("category", update.category, timestamp),
+        field_versions: versions.into_changes(),
+    }
+    .apply(conn, &expense.id)
+    .await?;
+
+    // the expense was deleted before this edit, it stays deleted and the edit is kept for review
+    if restored {
+        conflicts::delete_for_review(conn, SyncedTable::Expense, &expense.id, Some(*user_id))
+            .await?;
+    }
+
+    Trip::touch(conn, &expense.trip_id).await?;
+
+    Ok(expense.trip_id)
+}
+
+async fn delete_expense(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: DeleteArgs,
+    timestamp: f64,
+) -> MutationResult {
+    let expense = Expense::find(conn, &client_uuid(&args.id)).await?;
+
+    require_role(conn, user_id, &expense.trip_id, Role::Editor).await?;
+
+    let versions = FieldVersions::new(Expense::lock_field_versions(conn, &expense.id).await?);
+
+    // the expense was edited after this delete, it is deleted and the edit is kept for review
+    if versions
+        .newest()
+        .is_some_and(|last_write| last_write > timestamp)
+    {
+        conflicts::delete_for_review(conn, SyncedTable::Expense, &expense.id, None).await?;
+    } else {
+        Expense::delete(conn, &expense.id).await?;
+    }
+
+    Trip::touch(conn, &expense.trip_id).await?;
+
+    Ok(expense.trip_id)
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpensePayerCreate {
+    pub id: String,
+    pub expense_id: String,
+    pub collaborator_id: String,
+}
+
+async fn create_expense_payer(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: ExpensePayerCreate,
+) -> MutationResult {
+    let expense = Expense::find(conn, &client_uuid(&args.expense_id)).await?;
+
+    require_role(conn, user_id, &expense.trip_id, Role::Editor).await?;
+
+    let collaborator = UserTrip::find_by_id(conn, &client_uuid(&args.collaborator_id)).await?;
+
+    // payers have to be on the trip the expense belongs to
+    if collaborator.trip_id != expense.trip_id {
+        return Err(MutationError::Forbidden(expense.trip_id));
+    }
+
+    ExpensePayer {
+        expense_id: expense.id,
+        user_id: collaborator.user_id,
+        id: client_uuid(&args.id),
+        trip_id: expense.trip_id,
+    }
+    .insert(conn)
+    .await?;
+
+    Trip::touch(conn, &expense.trip_id).await?;
+
+    Ok(expense.trip_id)
+}
+
+async fn delete_expense_payer(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: DeleteArgs,
+) -> MutationResult {
+    let payer = ExpensePayer::find(conn, &client_uuid(&args.id)).await?;
+    let expense = Expense::find(conn, &payer.expense_id).await?;
+
+    require_role(conn, user_id, &expense.trip_id, Role::Editor).await?;
+
+    ExpensePayer::delete(conn, &payer.id).await?;
+
+    Trip::touch(conn, &expense.trip_id).await?;
+
+    Ok(expense.trip_id)
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskCreate {
+    pub id: Option<String>,
+    pub trip_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: Option<bool>,
+    pub urgency: Option<String>,
+}
+
+async fn create_task(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: TaskCreate,
+) -> MutationResult {
+    let trip_id = client_uuid(&args.trip_id);
+
+    require_role(conn, user_id, &trip_id, Role::Editor).await?;
+
+    // the client picks the ID and position inside the mutator, so they have to be recomputed
+    let position = match Task::last_position(conn, &trip_id).await? {
+        Some(last) => next_position(&last),
+        None => FIRST_POSITION.to_string(),
+    };
+
+    NewTask {
+        id: args
+            .id
+            .as_deref()
+            .map(client_uuid)
+            .unwrap_or_else(Uuid::new_v4),
+        trip_id,
+        title: &args.title,
+        description: args.description.as_deref().unwrap_or_default(),
+        completed: args.completed.unwrap_or_default(),
+        position: &position,
+        urgency: args.urgency.as_deref().unwrap_or(DEFAULT_URGENCY),
+    }
+    .insert(conn)
+    .await?;
+
+    Trip::touch(conn, &trip_id).await?;
+
+    Ok(trip_id)
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskUpdate {
+    pub id: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub completed: Option<bool>,
+    pub position: Option<String>,
+    pub urgency: Option<String>,
+}
+
+async fn update_task(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: TaskUpdate,
+    timestamp: f64,
+) -> MutationResult {
+    let task_id = client_uuid(&args.id);
+    let restored = conflicts::restore_deleted(conn, SyncedTable::Task, &task_id).await?;
+    let task = Task::find(conn, &task_id).await?;
+
+    require_role(conn, user_id, &task.trip_id, Role::Editor).await?;
+
+    let mut versions = FieldVersions::new(Task::lock_field_versions(conn, &task.id).await?);
+
+    TaskChanges {
+        title: versions.merge("title", args.title, timestamp),
+        description: versions.merge("description", args.description, timestamp),
+        completed: versions.merge("completed", args.completed, timestamp),
+        position: versions.merge("position", args.position, timestamp),
+        urgency: versions.merge("urgency", args.urgency, timestamp),
+        field_versions: versions.into_changes(),
+    }
+    .apply(conn, &task.id)
+    .await?;
+
+    // the task was deleted before this edit, it stays deleted and the edit is kept for review
+    if restored {
+        conflicts::delete_for_review(conn, SyncedTable::Task, &task.id, Some(*user_id)).await?;
+    }
+
+    Trip::touch(conn, &task.trip_id).await?;
+
+    Ok(task.trip_id)
+}
+
+async fn delete_task(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    args: DeleteArgs,
+    timestamp: f64,
+) -> MutationResult {
+    let task = Task::find(conn, &client_uuid(&args.id)).await?;
+
+    require_role(conn, user_id, &task.trip_id, Role::Editor).await?;
+
+    let versions = FieldVersions::new(Task::lock_field_versions(conn, &task.id).await?);
+
+    // the task was edited after this delete, it is deleted and the edit is kept for review
+    if versions
+        .newest()
+        .is_some_and(|last_write| last_write > timestamp)
+    {
+        conflicts::delete_for_review(conn, SyncedTable::Task, &task.id, None).await?;
+    } else {
+        Task::delete(conn, &task.id).await?;
+    }
+
+    Trip::touch(conn, &task.trip_id).await?;
+
+    Ok(task.trip_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_position_bumps_last_character() {
+        assert_eq!(next_position("a"), "b");
+        assert_eq!(next_position("ab"), "ac");
+    }
+
+    #[test]
+    fn parse_date_accepts_iso_timestamps() {
+        let expected = NaiveDate::from_ymd_opt(2025, 12, 20).unwrap();
+
+        assert_eq!(parse_date("2025-12-20").unwrap(), expected);
+        assert_eq!(parse_date("2025-12-20T00:00:00.000Z").unwrap(), expected);
+        assert!(parse_date("not a date").is_err());
+    }
+
+    fn mutation(name: &str, args: serde_json::Value) -> Mutation {
+        Mutation {
+            client_id: "client".to_string(),
+            id: 1,
+            name: name.to_string(),
+            args,
+            timestamp: 0.0,
+        }
+    }
+
+    #[test]
+    fn mutations_parse_into_mutators() {
+        let mutator = Mutator::parse(&mutation("deleteTask", json!({ "id": "t1" })), 2).unwrap();
+
+        assert!(matches!(mutator, Mutator::DeleteTask(DeleteArgs { id }) if id == "t1"));
+    }
+
+    #[test]
+    fn old_mutations_are_upgraded() {
+        let mutator = Mutator::parse(&mutation("deleteTrip", json!("t1")), 1).unwrap();
+
+        assert!(matches!(mutator, Mutator::DeleteTrip(DeleteArgs { id }) if id == "t1"));
+    }
+
+    #[test]
+    fn unknown_mutators_are_rejected() {
+        let result = Mutator::parse(&mutation("launchRocket", json!({ "id": "t1" })), 2);
+
+        assert!(
+            matches!(result, Err(MutationError::UnknownMutator(name)) if name == "launchRocket")
+        );
+    }
+
+    #[test]
+    fn collaborators_cant_be_created_by_mutation() {
+        let args = json!({ "id": "c1", "tripId": "t1", "userId": "u1", "role": "editor" });
+        let result = Mutator::parse(&mutation("createCollaborator", args), 2);
+
+        assert!(matches!(result, Err(MutationError::UnknownMutator(_))));
+    }
+
+    #[test]
+    fn client_ids_map_to_stable_uuids() {
+        let id = Uuid::new_v4();
+
+        assert_eq!(client_uuid(&id.to_string()), id);
+        assert_eq!(
+            client_uuid("V1StGXR8_Z5jdHi6B"),
+            client_uuid("V1StGXR8_Z5jdHi6B")
+        );
+    }
+}
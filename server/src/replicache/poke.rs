@@ -0,0 +1,173 @@
+//! Pokes tell connected clients that something they sync changed, so they pull right away instead
+//! of waiting for their next scheduled pull. A poke carries no data, it only names a channel:
+//! either a trip ID or `user/<user ID>` for everything a user can see.
+//!
+//! Pokes are published to Redis so that every server process hears about them, and each process
+//! fans them out to its own event streams.
+
+use std::{collections::HashSet, pin::pin, time::Duration};
+
+use actix_web_lab::sse;
+use diesel::QueryResult;
+use diesel_async::AsyncPgConnection;
+use futures_util::{
+    StreamExt,
+    future::{Either, select},
+};
+use redis::{AsyncCommands, Client as RedisClient, RedisResult};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+use uuid::Uuid;
+
+use crate::{
+    models::user_trip::UserTrip,
+    replicache::{trip_space_id, user_space_id},
+};
+
+const REDIS_CHANNEL: &str = "replicache:poke";
+const POKE: &str = "poke";
+
+// how many pokes an event stream may fall behind before it starts missing them
+const LOCAL_CAPACITY: usize = 1024;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// channels are named after the space whose changes they announce
+pub fn trip_channel(trip_id: &Uuid) -> String {
+    trip_space_id(trip_id)
+}
+
+pub fn user_channel(user_id: &Uuid) -> String {
+    user_space_id(user_id)
+}
+
+/// Whether the user may listen on the channel: their own user channel, or a trip they're on.
+pub async fn may_listen(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    channel: &str,
+) -> QueryResult<bool> {
+    if channel == user_channel(user_id) {
+        return Ok(true);
+    }
+
+    let Ok(trip_id) = channel.parse::<Uuid>() else {
+        return Ok(false);
+    };
+
+    Ok(UserTrip::find_trip_ids(conn, user_id)
+        .await?
+        .contains(&trip_id))
+}
+
+#[derive(Clone)]
+pub struct Pokes {
+    redis: RedisClient,
+    local: broadcast::Sender<String>,
+}
+
+impl Pokes {
+    pub fn new(redis: RedisClient) -> Self {
+        let (local, _) = broadcast::channel(LOCAL_CAPACITY);
+
+        Self { redis, local }
+    }
+
+    /// Pokes the given channels on every server process. Pokes are only a hint, clients still
+    /// pull on an interval, so failing to publish is logged rather than failing the request.
+    pub async fn publish(&self, channels: impl IntoIterator<Item = String>) {
+        if let Err(e) = self.try_publish(channels).await {
+            log::warn!("failed to publish pokes: {e}");
+        }
+    }
+
+    /// Pokes everyone on the given trips, plus `user_id` who changed them: a deleted trip has no
+    /// members left.
+    pub async fn trips_changed(
+        &self,
+        conn: &mut AsyncPgConnection,
+        trip_ids: &[Uuid],
+        user_id: &Uuid,
+    ) -> QueryResult<()> {
+        let mut user_ids: HashSet<_> = UserTrip::find_member_ids(conn, trip_ids)
+            .await?
+            .into_iter()
+            .collect();
+        user_ids.insert(*user_id);
+
+        let channels = trip_ids
+            .iter()
+            .map(trip_channel)
+            .chain(user_ids.iter().map(user_channel));
+
+        self.publish(channels).await;
+
+        Ok(())
+    }
+
+    async fn try_publish(&self, channels: impl IntoIterator<Item = String>) -> RedisResult<()> {
+        let mut conn = self.redis.get_multiplexed_async_connection().await?;
+
+        for channel in channels {
+            let _: () = conn.publish(REDIS_CHANNEL, channel).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Relays pokes from Redis to the event streams of this process, reconnecting whenever the
+    /// subscription drops. Runs for as long as the server does.
+    pub async fn listen(self) {
+        loop {
+            if let Err(e) = self.relay().await {
+                log::warn!("poke subscription failed: {e}");
+            }
+
+            actix_web::rt::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    async fn relay(&self) -> RedisResult<()> {
+        let mut pubsub = self.redis.get_async_pubsub().await?;
+        pubsub.subscribe(REDIS_CHANNEL).await?;
+
+        let mut messages = pubsub.into_on_message();
+
+        while let Some(message) = messages.next().await {
+            let channel: String = message.get_payload()?;
+
+            // nobody listening on this process is fine
+            let _ = self.local.send(channel);
+        }
+
+        Ok(())
+    }
+
+    /// Returns a stream of pokes for `channel`. The stream ends once the receiver is dropped.
+    pub fn subscribe(&self, channel: String) -> mpsc::Receiver<sse::Event> {
+        let (sender, receiver) = mpsc::channel(1);
+        let mut pokes = self.local.subscribe();
+
+        actix_web::rt::spawn(async move {
+            loop {
+                let poked = match select(pin!(pokes.recv()), pin!(sender.closed())).await {
+                    Either::Left((Ok(poked), _)) => poked == channel,
+                    // some pokes were missed, pulling one time too many is harmless
+                    Either::Left((Err(RecvError::Lagged(_)), _)) => true,
+                    Either::Left((Err(RecvError::Closed), _)) | Either::Right(_) => break,
+                };
+
+                // a poke that's already queued covers this one as well
+                if poked
+                    && sender.try_send(sse::Data::new(POKE).into()).is_err()
+                    && sender.is_closed()
+                {
+                    break;
+                }
+            }
+        });
+
+        receiver
+    }
+}
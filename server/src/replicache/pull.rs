@@ -0,0 +1,365 @@
+use std::collections::{BTreeMap, HashMap};
+
+use diesel::{dsl::sql, prelude::*, sql_types::Bool};
+use diesel_async::{AsyncPgConnection, RunQueryDsl, scoped_futures::ScopedFutureExt};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        expense::{Expense, ExpensePayer},
+        itinerary_item::ItineraryItem,
+        replicache::{
+            ReplicacheClient, ReplicacheClientGroup, ReplicacheSpace, ReplicacheTombstone,
+        },
+        sync_conflict::SyncConflict,
+        task::Task,
+        trip::Trip,
+        user_trip::UserTrip,
+    },
+    replicache::{
+        entities::{
+            self, CollaboratorEntity, ExpenseEntity, ExpensePayerEntity, ItineraryItemEntity,
+            SyncConflictEntity, TaskEntity, TripEntity,
+        },
+        trip_space_id, user_space_id,
+    },
+    schema::{
+        expense_payers, expenses, itinerary_items, sync_conflicts, tasks, trips, user_trip, users,
+    },
+    util::errors::{AppError, AppResult},
+};
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Put {
+        key: String,
+        #[schema(value_type = Object)]
+        value: serde_json::Value,
+    },
+    Del {
+        key: String,
+    },
+    Clear,
+}
+
+impl PatchOperation {
+    fn put<T: Serialize>(key: String, value: T) -> Self {
+        Self::Put {
+            key,
+            value: serde_json::to_value(value).expect("entities always serialize"),
+        }
+    }
+
+    fn into_del(self) -> Self {
+        match self {
+            Self::Put { key, .. } | Self::Del { key } => Self::Del { key },
+            Self::Clear => Self::Clear,
+        }
+    }
+}
+
+/// What a client has synced so far. Replicache hands it back untouched with the next pull.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Cookie {
+    /// Grows whenever anything below changes, Replicache uses it to order pull responses
+    pub order: i64,
+    /// The version each of the user's trips was synced at
+    pub trips: HashMap<Uuid, i32>,
+    /// The version of the user's space the last mutation IDs were synced at
+    pub clients: i32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PullResponse {
+    pub cookie: Cookie,
+    #[serde(rename = "lastMutationIDChanges")]
+    pub last_mutation_id_changes: HashMap<String, i32>,
+    pub patch: Vec<PatchOperation>,
+}
+
+// Rows written before versioning existed are still at version 0, so a full sync has to ask for
+// everything after this.
+const NEVER_SYNCED: i32 = -1;
+
+/// Builds the patch that takes a client from the state described by `cookie` to the current
+/// state of the trips `user_id` is on.
+///
+/// Every synced row carries the version of its trip's space it was last written at and deleted
+/// rows leave a tombstone behind, so only what changed since the cookie is sent. Trips the user
+/// joined since are sent in full, trips they left or that were deleted are removed. Without a
+/// cookie the client is told to clear its cache and gets a full snapshot instead.
+pub async fn pull(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+    client_group_id: &str,
+    cookie: Option<Cookie>,
+) -> AppResult<PullResponse> {
+    // the group is kept around for as long as it keeps pulling, see `replicache::gc`
+    let client_group =
+        ReplicacheClientGroup::touch(conn, client_group_id, user_id, &user_space_id(user_id))
+            .await?;
+
+    if client_group.user_id != *user_id {
+        return Err(AppError::Forbidden("Client group belongs to another user."));
+    }
+
+    conn.build_transaction()
+        .repeatable_read()
+        .read_only()
+        .run(|conn| {
+            async move {
+                let reset = cookie.is_none();
+                let previous = cookie.unwrap_or_default();
+
+                let mut patch = if reset {
+                    vec![PatchOperation::Clear]
+                } else {
+                    Vec::new()
+                };
+
+                let trips = trip_versions(conn, user_id).await?;
+
+                // trips are grouped by the version the client has them at, so trips that are
+                // sent in full are fetched together
+                let mut pending: BTreeMap<i32, Vec<Uuid>> = BTreeMap::new();
+
+                for (trip_id, version) in trips.iter() {
+                    match previous.trips.get(trip_id) {
+                        Some(synced) if synced == version => {}
+                        Some(synced) if synced < version => {
+                            pending.entry(*synced).or_default().push(*trip_id)
+                        }
+                        _ => pending.entry(NEVER_SYNCED).or_default().push(*trip_id),
+                    }
+                }
+
+                for (since, trip_ids) in pending.iter() {
+                    if *since != NEVER_SYNCED {
+                        patch.extend(deleted_since(conn, trip_ids, *since).await?);
+                    }
+
+                    patch.extend(changed_entities(conn, trip_ids, *since).await?);
+                }
+
+                for (trip_id, synced) in previous.trips.iter() {
+                    if trips.contains_key(trip_id) {
+                        continue;
+                    }
+
+                    // the user left the trip or it was deleted, drop whatever is left of it
+                    let trip_ids = [*trip_id];
+
+                    patch.extend(deleted_since(conn, &trip_ids, *synced).await?);
+                    patch.extend(
+                        changed_entities(conn, &trip_ids, NEVER_SYNCED)
+                            .await?
+                            .into_iter()
+                            .map(PatchOperation::into_del),
+                    );
+                }
+
+                let clients = ReplicacheSpace::find(conn, &user_space_id(user_id))
+                    .await
+                    .optional()?
+                    .map_or(0, |space| space.version);
+
+                let clients_since = if reset {
+                    NEVER_SYNCED
+                } else {
+                    previous.clients
+                };
+
+                let last_mutation_id_changes =
+                    ReplicacheClient::find_changed_in_group(conn, client_group_id, clients_since)
+                        .await?
+                        .into_iter()
+                        .map(|client| (client.id, client.last_mutation_id))
+                        .collect();
+
+                let unchanged = trips == previous.trips && clients == previous.clients;
+
+                Ok(PullResponse {
+                    cookie: Cookie {
+                        order: if unchanged {
+                            previous.order
+                        } else {
+                            previous.order + 1
+                        },
+                        trips,
+                        clients,
+                    },
+                    last_mutation_id_changes,
+                    patch,
+                })
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+async fn trip_versions(
+    conn: &mut AsyncPgConnection,
+    user_id: &Uuid,
+) -> QueryResult<HashMap<Uuid, i32>> {
+    let trip_ids = UserTrip::find_trip_ids(conn, user_id).await?;
+    let space_ids: Vec<String> = trip_ids.iter().map(trip_space_id).collect();
+
+    let versions: HashMap<String, i32> = ReplicacheSpace::find_many(conn, &space_ids)
+        .await?
+        .into_iter()
+        .map(|space| (space.id, space.version))
+        .collect();
+
+    Ok(trip_ids
+        .into_iter()
+        .map(|trip_id| {
+            let version = versions.get(&trip_space_id(&trip_id)).copied();
+            (trip_id, version.unwrap_or_default())
+        })
+        .collect())
+}
+
+async fn deleted_since(
+    conn: &mut AsyncPgConnection,
+    trip_ids: &[Uuid],
+    since: i32,
+) -> QueryResult<Vec<PatchOperation>> {
+    Ok(ReplicacheTombstone::find_since(conn, trip_ids, since)
+        .await?
+        .into_iter()
+        .map(|tombstone| PatchOperation::Del { key: tombstone.key })
+        .collect())
+}
+
+async fn changed_entities(
+    conn: &mut AsyncPgConnection,
+    trip_ids: &[Uuid],
+    since: i32,
+) -> QueryResult<Vec<PatchOperation>> {
+    let mut patch = Vec::new();
+
+    let owner_membership = user_trip::trip_id
+        .eq(trips::id)
+        .and(user_trip::user_id.eq(trips::owner_id));
+
+    let changed_trips: Vec<(Trip, Option<Uuid>)> = trips::table
+        .left_join(user_trip::table.on(owner_membership))
+        .filter(trips::id.eq_any(trip_ids))
+        .filter(trips::version.gt(since))
+        .select((Trip::as_select(), user_trip::id.nullable()))
+        .load(conn)
+        .await?;
+
+    patch.extend(changed_trips.into_iter().map(|(trip, owner)| {
+        PatchOperation::put(
+            entities::key(entities::TRIP, &trip.id),
+            TripEntity::new(trip, owner),
+        )
+    }));
+
+    let changed_collaborators: Vec<(UserTrip, String, String, Option<String>)> = user_trip::table
+        .inner_join(users::table)
+        .filter(user_trip::trip_id.eq_any(trip_ids))
+        .filter(user_trip::version.gt(since))
+        .select((
+            UserTrip::as_select(),
+            users::username,
+            users::email,
+            users::avatar,
+        ))
+        .load(conn)
+        .await?;
+
+    patch.extend(
+        changed_collaborators
+            .into_iter()
+            .map(|(user_trip, username, email, avatar)| {
+                PatchOperation::put(
+                    entities::key(entities::COLLABORATOR, &user_trip.id),
+                    CollaboratorEntity::new(user_trip, username, email, avatar),
+                )
+            }),
+    );
+
+    let changed_items: Vec<ItineraryItem> = itinerary_items::table
+        .filter(itinerary_items::trip_id.eq_any(trip_ids))
+        .filter(itinerary_items::version.gt(since))
+        .select(ItineraryItem::as_select())
+        .load(conn)
+        .await?;
+
+    patch.extend(changed_items.into_iter().map(|item| {
+        PatchOperation::put(
+            entities::key(entities::ITINERARY_ITEM, &item.id),
+            ItineraryItemEntity::from(item),
+        )
+    }));
+
+    let changed_expenses: Vec<Expense> = expenses::table
+        .filter(expenses::trip_id.eq_any(trip_ids))
+        .filter(expenses::version.gt(since))
+        .select(Expense::as_select())
+        .load(conn)
+        .await?;
+
+    patch.extend(changed_expenses.into_iter().map(|expense| {
+        PatchOperation::put(
+            entities::key(entities::EXPENSE, &expense.id),
+            ExpenseEntity::from(expense),
+        )
+    }));
+
+    // payers are keyed by collaborator on the client, so they're only synced while the paying
+    // user is still on the trip
+    let payer_membership = sql::<Bool>(
+        "user_trip.trip_id = expense_payers.trip_id AND user_trip.user_id = expense_payers.user_id",
+    );
+
+    let changed_payers: Vec<(ExpensePayer, Uuid)> = expense_payers::table
+        .inner_join(user_trip::table.on(payer_membership))
+        .filter(expense_payers::trip_id.eq_any(trip_ids))
+        .filter(expense_payers::version.gt(since))
+        .select((ExpensePayer::as_select(), user_trip::id))
+        .load(conn)
+        .await?;
+
+    patch.extend(changed_payers.into_iter().map(|(payer, collaborator_id)| {
+        PatchOperation::put(
+            entities::key(entities::EXPENSE_PAYER, &payer.id),
+            ExpensePayerEntity::new(payer, collaborator_id),
+        )
+    }));
+
+    let changed_tasks: Vec<Task> = tasks::table
+        .filter(tasks::trip_id.eq_any(trip_ids))
+        .filter(tasks::version.gt(since))
+        .select(Task::as_select())
+        .load(conn)
+        .await?;
+
+    patch.extend(changed_tasks.into_iter().map(|task| {
+        PatchOperation::put(
+            entities::key(entities::TASK, &task.id),
+            TaskEntity::from(task),
+        )
+    }));
+
+    let changed_conflicts: Vec<SyncConflict> = sync_conflicts::table
+        .filter(sync_conflicts::trip_id.eq_any(trip_ids))
+        .filter(sync_conflicts::version.gt(since))
+        .select(SyncConflict::as_select())
+        .load(conn)
+        .await?;
+
+    patch.extend(changed_conflicts.into_iter().map(|conflict| {
+        PatchOperation::put(
+            entities::key(entities::CONFLICT, &conflict.id),
+            SyncConflictEntity::from(conflict),
+        )
+    }));
+
+    Ok(patch)
+}
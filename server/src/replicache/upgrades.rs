@@ -0,0 +1,111 @@
+//! Upgrades for mutations pushed with argument shapes older than `MUTATOR_VERSION`.
+//!
+//! Replicache keeps mutations queued for as long as a client is offline, and pushes them with the
+//! schema version of the client that made them. Rather than keeping every old shape around, the
+//! arguments are upgraded step by step to the current shape before they're parsed.
+//!
+//! Versions:
+//! 1. The shapes the web client sent before mutators were versioned, without a schema version.
+//! 2. `delete*` mutators take `{ id }` instead of a bare ID.
+//! 3. `createTrip` takes the trip's ID in `trip.id` instead of leaving it to the server.
+
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::replicache::mutators::MUTATOR_VERSION;
+
+/// The version of mutations pushed without a schema version.
+const UNVERSIONED: u32 = 1;
+
+type Upgrade = fn(&str, Value) -> Value;
+
+/// The upgrade at index `i` takes arguments from version `i + 1` to version `i + 2`.
+const UPGRADES: [Upgrade; MUTATOR_VERSION as usize - 1] = [wrap_deleted_id, name_created_trip];
+
+/// Reads the schema version a push was made with. Returns `None` for versions this server
+/// doesn't know, which come from a newer client or aren't versions at all.
+pub fn mutator_version(schema_version: Option<&str>) -> Option<u32> {
+    match schema_version {
+        None | Some("") => Some(UNVERSIONED),
+        Some(version) => version
+            .parse()
+            .ok()
+            .filter(|version| (UNVERSIONED..=MUTATOR_VERSION).contains(version)),
+    }
+}
+
+/// Upgrades the arguments of mutator `name` from `version` to `MUTATOR_VERSION`.
+pub fn upgrade(name: &str, args: Value, version: u32) -> Value {
+    UPGRADES
+        .iter()
+        .skip(version.saturating_sub(UNVERSIONED) as usize)
+        .fold(args, |args, upgrade| upgrade(name, args))
+}
+
+fn wrap_deleted_id(name: &str, args: Value) -> Value {
+    match (name.starts_with("delete"), args) {
+        (true, Value::String(id)) => json!({ "id": id }),
+        (_, args) => args,
+    }
+}
+
+/// Older clients kept the trip's ID to themselves, so the server picks one like it used to.
+fn name_created_trip(name: &str, mut args: Value) -> Value {
+    if let Some(trip) = args
+        .get_mut("trip")
+        .and_then(Value::as_object_mut)
+        .filter(|_| name == "createTrip")
+    {
+        trip.entry("id")
+            .or_insert_with(|| Uuid::new_v4().to_string().into());
+    }
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned_pushes_are_version_one() {
+        assert_eq!(mutator_version(None), Some(1));
+        assert_eq!(mutator_version(Some("")), Some(1));
+        assert_eq!(mutator_version(Some("2")), Some(2));
+    }
+
+    #[test]
+    fn newer_versions_are_not_supported() {
+        assert_eq!(
+            mutator_version(Some(&(MUTATOR_VERSION + 1).to_string())),
+            None
+        );
+        assert_eq!(mutator_version(Some("0")), None);
+        assert_eq!(mutator_version(Some("latest")), None);
+    }
+
+    #[test]
+    fn old_deletes_are_upgraded() {
+        assert_eq!(upgrade("deleteTask", json!("t1"), 1), json!({ "id": "t1" }));
+        assert_eq!(
+            upgrade("deleteTask", json!({ "id": "t1" }), 2),
+            json!({ "id": "t1" })
+        );
+        assert_eq!(
+            upgrade("updateTask", json!({ "id": "t1" }), 1),
+            json!({ "id": "t1" })
+        );
+    }
+
+    #[test]
+    fn old_created_trips_get_an_id() {
+        let args = upgrade("createTrip", json!({ "trip": { "name": "Japan" } }), 2);
+
+        assert!(args["trip"]["id"].is_string());
+        assert_eq!(args["trip"]["name"], "Japan");
+
+        let args = upgrade("createTrip", json!({ "trip": { "id": "t1" } }), 2);
+
+        assert_eq!(args["trip"]["id"], "t1");
+    }
+}
@@ -1,4 +1,4 @@
-use actix_web::web::{ServiceConfig, delete, get, post, put, scope};
+use actix_web::web::{ServiceConfig, delete, get, patch, post, put, scope};
 use utoipa::{
     Modify, OpenApi,
     openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
@@ -9,7 +9,23 @@ use crate::controllers::{
         get_me, google_oauth, login, logout, refresh, register_user, resend_verification_code,
         verify_user_email,
     },
+    calendar::{get_calendar_feed, get_trip_calendar, revoke_calendar_feed, rotate_calendar_feed},
+    conflict::{get_conflicts, resolve_conflict},
     get_health,
+    invite::{
+        accept_invite, create_invite, decline_invite, get_my_invites, get_trip_invites,
+        revoke_invite,
+    },
+    replicache::{poke, pull, push},
+    share::{create_share_link, get_share_links, get_shared_trip, revoke_share_link},
+    trip::{
+        archive_trip, clone_trip, create_trip, delete_trip, get_trip, get_trips, restore_trip,
+        transfer_ownership, unarchive_trip, update_trip, upload_banner,
+    },
+    trip_plan::{
+        get_itinerary_warnings, get_trip_days, import_itinerary, preview_itinerary_import,
+        update_itinerary,
+    },
     user::{
         change_profile_picture, delete_user, get_user, get_users, update_user, update_user_password,
     },
@@ -30,7 +46,42 @@ use crate::controllers::{
         crate::controllers::user::delete_user,
         crate::controllers::user::update_user,
         crate::controllers::user::update_user_password,
-        crate::controllers::user::change_profile_picture
+        crate::controllers::user::change_profile_picture,
+        crate::controllers::calendar::rotate_calendar_feed,
+        crate::controllers::calendar::revoke_calendar_feed,
+        crate::controllers::calendar::get_calendar_feed,
+        crate::controllers::replicache::push,
+        crate::controllers::replicache::pull,
+        crate::controllers::replicache::poke,
+        crate::controllers::trip::get_trips,
+        crate::controllers::trip::get_trip,
+        crate::controllers::trip::create_trip,
+        crate::controllers::trip::update_trip,
+        crate::controllers::trip::upload_banner,
+        crate::controllers::trip_plan::update_itinerary,
+        crate::controllers::trip_plan::get_itinerary_warnings,
+        crate::controllers::trip_plan::get_trip_days,
+        crate::controllers::trip_plan::preview_itinerary_import,
+        crate::controllers::trip_plan::import_itinerary,
+        crate::controllers::calendar::get_trip_calendar,
+        crate::controllers::trip::delete_trip,
+        crate::controllers::trip::restore_trip,
+        crate::controllers::trip::archive_trip,
+        crate::controllers::trip::unarchive_trip,
+        crate::controllers::trip::transfer_ownership,
+        crate::controllers::trip::clone_trip,
+        crate::controllers::invite::create_invite,
+        crate::controllers::invite::get_trip_invites,
+        crate::controllers::invite::revoke_invite,
+        crate::controllers::invite::get_my_invites,
+        crate::controllers::invite::accept_invite,
+        crate::controllers::invite::decline_invite,
+        crate::controllers::conflict::get_conflicts,
+        crate::controllers::conflict::resolve_conflict,
+        crate::controllers::share::create_share_link,
+        crate::controllers::share::get_share_links,
+        crate::controllers::share::revoke_share_link,
+        crate::controllers::share::get_shared_trip
     ),
     modifiers(&SecurityAddon)
 )]
@@ -75,5 +126,61 @@ pub fn routes(cfg: &mut ServiceConfig ) {
                 .route("/{user_id}", put().to(update_user))
                 .route("/{user_id}/password", put().to(update_user_password))
                 .route("/{user_id}/profile-picture", put().to(change_profile_picture))
+                .route("/{user_id}/calendar-feed", post().to(rotate_calendar_feed))
+                .route("/{user_id}/calendar-feed", delete().to(revoke_calendar_feed))
+        )
+       .service(
+            scope("/api/v1/replicache")
+                .route("/push", post().to(push))
+                .route("/pull", post().to(pull))
+                .route("/poke", get().to(poke))
+        )
+       .service(
+            scope("/api/v1/trips")
+                .route("", get().to(get_trips))
+                .route("", post().to(create_trip))
+                .route("/{trip_id}", get().to(get_trip))
+                .route("/{trip_id}", put().to(update_trip))
+                .route("/{trip_id}", delete().to(delete_trip))
+                .route("/{trip_id}/banner", put().to(upload_banner))
+                .route("/{trip_id}/itinerary", patch().to(update_itinerary))
+                .route(
+                    "/{trip_id}/itinerary/warnings",
+                    get().to(get_itinerary_warnings),
+                )
+                .route(
+                    "/{trip_id}/itinerary/import/preview",
+                    post().to(preview_itinerary_import),
+                )
+                .route("/{trip_id}/itinerary/import", post().to(import_itinerary))
+                .route("/{trip_id}/days", get().to(get_trip_days))
+                .route("/{trip_id}/calendar.ics", get().to(get_trip_calendar))
+                .route("/{trip_id}/restore", post().to(restore_trip))
+                .route("/{trip_id}/archive", post().to(archive_trip))
+                .route("/{trip_id}/unarchive", post().to(unarchive_trip))
+                .route("/{trip_id}/owner", put().to(transfer_ownership))
+                .route("/{trip_id}/clone", post().to(clone_trip))
+                .route("/{trip_id}/invites", get().to(get_trip_invites))
+                .route("/{trip_id}/invites", post().to(create_invite))
+                .route("/{trip_id}/invites/{invite_id}", delete().to(revoke_invite))
+                .route("/{trip_id}/conflicts", get().to(get_conflicts))
+                .route("/{trip_id}/conflicts/{conflict_id}/resolve", post().to(resolve_conflict))
+                .route("/{trip_id}/shares", get().to(get_share_links))
+                .route("/{trip_id}/shares", post().to(create_share_link))
+                .route("/{trip_id}/shares/{share_id}", delete().to(revoke_share_link))
+        )
+       .service(
+            scope("/api/v1/calendar-feeds")
+                .route("/{token}.ics", get().to(get_calendar_feed))
+        )
+       .service(
+            scope("/api/v1/invites")
+                .route("", get().to(get_my_invites))
+                .route("/{invite_id}/accept", post().to(accept_invite))
+                .route("/{invite_id}/decline", post().to(decline_invite))
+        )
+       .service(
+            scope("/api/v1/shared")
+                .route("/{token}", get().to(get_shared_trip))
         );
 }
@@ -91,6 +91,23 @@ impl S3Client {
         let mut contents = Vec::with_capacity(size_estimate);
         file.read_to_end(&mut contents).await.unwrap();
 
+        self.put_object(contents, key, content_type).await
+    }
+
+    /// Uploads contents that were made in memory rather than received as a file, returns the URL.
+    pub async fn upload_bytes(
+        &self,
+        contents: Vec<u8>,
+        key_prefix: &str,
+        ext: &str,
+        content_type: &str,
+    ) -> String {
+        let key = format!("{key_prefix}{}.{ext}", Uuid::new_v4());
+
+        self.put_object(contents, &key, content_type).await
+    }
+
+    async fn put_object(&self, contents: Vec<u8>, key: &str, content_type: &str) -> String {
         let _res = self
             .s3
             .put_object()
@@ -0,0 +1,300 @@
+//! Lays a trip out day by day.
+//!
+//! Each entry goes on the date it has on the clock where it takes place. Entries without a
+//! location use the trip's zone, the one most of the trip's located entries are in, and UTC when
+//! none of them have a location.
+
+use std::collections::{BTreeMap, HashMap};
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+
+use crate::views::{
+    EncodableFlight, EncodableItineraryItem, EncodableStay, EncodableTripDay, ItineraryExpense,
+};
+
+pub struct Schedule {
+    /// The zone dates are taken in for entries without a location
+    pub timezone: Option<Tz>,
+    pub days: Vec<EncodableTripDay>,
+}
+
+/// The zone most of the entries are in, ties go to the zone that comes first by name.
+fn trip_zone<'a>(zones: impl Iterator<Item = Option<&'a str>>) -> Option<Tz> {
+    let mut counts: HashMap<Tz, usize> = HashMap::new();
+
+    for zone in zones.flatten().filter_map(|zone| zone.parse().ok()) {
+        *counts.entry(zone).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.name().cmp(a.name())))
+        .map(|(zone, _)| zone)
+}
+
+/// Every date from `start_date` to `end_date`, with the entries on it. Entries on other dates
+/// are left out, see `feasibility` for warnings about them.
+pub fn trip_days(
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    itinerary: Vec<EncodableItineraryItem>,
+    flights: Vec<EncodableFlight>,
+    stays: Vec<EncodableStay>,
+) -> Schedule {
+    let trip_zone = trip_zone(
+        itinerary
+            .iter()
+            .map(|item| item.timezone.as_deref())
+            .chain(flights.iter().flat_map(|flight| {
+                [
+                    flight.departure_timezone.as_deref(),
+                    flight.arrival_timezone.as_deref(),
+                ]
+            }))
+            .chain(stays.iter().map(|stay| stay.timezone.as_deref())),
+    );
+
+    let local_date = |time: DateTime<Utc>, zone: Option<&str>| match zone
+        .and_then(|zone| zone.parse::<Tz>().ok())
+        .or(trip_zone)
+    {
+        Some(zone) => time.with_timezone(&zone).date_naive(),
+        None => time.date_naive(),
+    };
+    let day_of = |date: NaiveDate| {
+        (start_date..=end_date)
+            .contains(&date)
+            .then(|| (date - start_date).num_days() as usize)
+    };
+
+    let mut days: Vec<EncodableTripDay> = start_date
+        .iter_days()
+        .take_while(|date| *date <= end_date)
+        .map(|date| EncodableTripDay {
+            date,
+            items: Vec::new(),
+            flights: Vec::new(),
+            stay: None,
+            spending: Vec::new(),
+        })
+        .collect();
+
+    for item in itinerary {
+        let Some(day) = item
+            .start_time
+            .and_then(|start| day_of(local_date(start, item.timezone.as_deref())))
+        else {
+            continue;
+        };
+
+        days[day].items.push(item);
+    }
+
+    for flight in flights {
+        let departure = flight
+            .departure_time
+            .map(|time| local_date(time, flight.departure_timezone.as_deref()));
+        let arrival = flight
+            .arrival_time
+            .map(|time| local_date(time, flight.arrival_timezone.as_deref()))
+            .filter(|arrival| Some(*arrival) != departure);
+
+        for day in [departure, arrival]
+            .into_iter()
+            .flatten()
+            .filter_map(day_of)
+        {
+            days[day].flights.push(flight.clone());
+        }
+    }
+
+    for stay in stays {
+        let Some(check_in) = stay.check_in_time else {
+            continue;
+        };
+
+        let first_night = local_date(check_in, stay.timezone.as_deref());
+        // the night before check-out is the last one spent there
+        let last_night = stay
+            .check_out_time
+            .and_then(|check_out| local_date(check_out, stay.timezone.as_deref()).pred_opt())
+            .unwrap_or(first_night)
+            .max(first_night);
+
+        let last_night = last_night.min(end_date);
+
+        // stays are in check-in order, so a later check-in takes over the nights they share
+        for night in first_night
+            .max(start_date)
+            .iter_days()
+            .take_while(|night| *night <= last_night)
+        {
+            if let Some(day) = day_of(night) {
+                days[day].stay = Some(stay.clone());
+            }
+        }
+    }
+
+    for day in days.iter_mut() {
+        let mut spending: BTreeMap<&str, BigDecimal> = BTreeMap::new();
+
+        for cost in day.items.iter().filter_map(|item| item.cost.as_ref()) {
+            *spending.entry(&cost.currency).or_default() += &cost.cost;
+        }
+
+        day.spending = spending
+            .into_iter()
+            .map(|(currency, cost)| ItineraryExpense {
+                cost,
+                currency: currency.to_string(),
+            })
+            .collect();
+    }
+
+    Schedule {
+        timezone: trip_zone,
+        days,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 6, day).unwrap()
+    }
+
+    fn item(
+        start: DateTime<Utc>,
+        zone: Option<&str>,
+        cost: Option<(&str, &str)>,
+    ) -> EncodableItineraryItem {
+        EncodableItineraryItem {
+            id: Some(Uuid::new_v4()),
+            activity_type: None,
+            title: None,
+            location: None,
+            start_time: Some(start),
+            end_time: None,
+            timezone: zone.map(str::to_string),
+            local_start_time: None,
+            local_end_time: None,
+            cost: cost.map(|(cost, currency)| ItineraryExpense {
+                cost: BigDecimal::from_str(cost).unwrap(),
+                currency: currency.to_string(),
+            }),
+            notes: None,
+        }
+    }
+
+    fn stay(check_in: DateTime<Utc>, check_out: Option<DateTime<Utc>>) -> EncodableStay {
+        EncodableStay {
+            id: Uuid::new_v4(),
+            location: None,
+            timezone: Some("Asia/Tokyo".to_string()),
+            check_in_time: Some(check_in),
+            local_check_in_time: None,
+            check_out_time: check_out,
+            local_check_out_time: None,
+        }
+    }
+
+    #[test]
+    fn entries_go_on_their_local_date() {
+        // 23:30 in UTC is already the next morning in Tokyo
+        let late = Utc.with_ymd_and_hms(2025, 6, 1, 23, 30, 0).unwrap();
+        let flight = EncodableFlight {
+            id: Uuid::new_v4(),
+            flight_code: Some("KL861".to_string()),
+            departure_time: Some(Utc.with_ymd_and_hms(2025, 6, 1, 12, 35, 0).unwrap()),
+            departure_timezone: Some("Europe/Amsterdam".to_string()),
+            local_departure_time: None,
+            arrival_time: Some(late),
+            arrival_timezone: Some("Asia/Tokyo".to_string()),
+            local_arrival_time: None,
+        };
+
+        let schedule = trip_days(
+            date(1),
+            date(3),
+            vec![
+                item(late, Some("Asia/Tokyo"), None),
+                item(late, None, None),
+                item(late + chrono::TimeDelta::days(5), None, None),
+            ],
+            vec![flight],
+            Vec::new(),
+        );
+
+        assert_eq!(schedule.timezone, Some(Tz::Asia__Tokyo));
+        assert_eq!(schedule.days.len(), 3);
+        assert_eq!(schedule.days[0].items.len(), 0);
+        assert_eq!(schedule.days[1].items.len(), 2);
+        assert_eq!(schedule.days[2].items.len(), 0);
+        assert_eq!(schedule.days[0].flights.len(), 1);
+        assert_eq!(schedule.days[1].flights.len(), 1);
+    }
+
+    #[test]
+    fn stays_cover_the_nights_until_check_out() {
+        let hotel = stay(
+            Utc.with_ymd_and_hms(2025, 6, 1, 6, 0, 0).unwrap(),
+            Some(Utc.with_ymd_and_hms(2025, 6, 3, 2, 0, 0).unwrap()),
+        );
+        let ryokan = stay(Utc.with_ymd_and_hms(2025, 6, 3, 6, 0, 0).unwrap(), None);
+
+        let schedule = trip_days(
+            date(1),
+            date(4),
+            Vec::new(),
+            Vec::new(),
+            vec![hotel.clone(), ryokan.clone()],
+        );
+        let stays: Vec<_> = schedule
+            .days
+            .iter()
+            .map(|day| day.stay.as_ref().map(|stay| stay.id))
+            .collect();
+
+        assert_eq!(
+            stays,
+            [Some(hotel.id), Some(hotel.id), Some(ryokan.id), None]
+        );
+    }
+
+    #[test]
+    fn spending_is_totaled_per_currency() {
+        let morning = Utc.with_ymd_and_hms(2025, 6, 1, 9, 0, 0).unwrap();
+
+        let schedule = trip_days(
+            date(1),
+            date(1),
+            vec![
+                item(morning, None, Some(("12.50", "EUR"))),
+                item(morning, None, Some(("3000", "JPY"))),
+                item(morning, None, Some(("7.50", "EUR"))),
+                item(morning, None, None),
+            ],
+            Vec::new(),
+            Vec::new(),
+        );
+        let spending: Vec<_> = schedule.days[0]
+            .spending
+            .iter()
+            .map(|spent| (spent.currency.as_str(), spent.cost.to_string()))
+            .collect();
+
+        assert_eq!(
+            spending,
+            [("EUR", "20.00".to_string()), ("JPY", "3000".to_string())]
+        );
+    }
+}
@@ -25,6 +25,14 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    calendar_feeds (token) {
+        token -> Text,
+        user_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     documents (id) {
         id -> Uuid,
@@ -50,6 +58,9 @@ diesel::table! {
     expense_payers (expense_id, user_id) {
         expense_id -> Uuid,
         user_id -> Uuid,
+        id -> Uuid,
+        version -> Int4,
+        trip_id -> Uuid,
     }
 }
 
@@ -60,6 +71,10 @@ diesel::table! {
         title -> Nullable<Text>,
         cost -> Numeric,
         currency -> Text,
+        description -> Nullable<Text>,
+        category -> Nullable<Text>,
+        version -> Int4,
+        field_versions -> Jsonb,
     }
 }
 
@@ -87,6 +102,11 @@ diesel::table! {
         end_time -> Nullable<Timestamptz>,
         expense_id -> Nullable<Uuid>,
         notes -> Text,
+        description -> Nullable<Text>,
+        all_day -> Bool,
+        version -> Int4,
+        field_versions -> Jsonb,
+        ical_uid -> Nullable<Text>,
     }
 }
 
@@ -106,6 +126,7 @@ diesel::table! {
         display_name -> Nullable<Text>,
         longitude -> Float8,
         latitude -> Float8,
+        timezone -> Nullable<Text>,
     }
 }
 
@@ -168,6 +189,7 @@ diesel::table! {
         id -> Text,
         user_id -> Uuid,
         space_id -> Text,
+        last_pulled_at -> Timestamptz,
     }
 }
 
@@ -178,6 +200,31 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    replicache_tombstones (key) {
+        key -> Text,
+        trip_id -> Uuid,
+        version -> Int4,
+        data -> Nullable<Jsonb>,
+        deleted_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    sync_conflicts (id) {
+        id -> Uuid,
+        trip_id -> Uuid,
+        entity_type -> Text,
+        entity_id -> Uuid,
+        field -> Nullable<Text>,
+        current_value -> Nullable<Jsonb>,
+        proposed_value -> Jsonb,
+        proposed_by -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        version -> Int4,
+    }
+}
+
 diesel::table! {
     tasks (id) {
         id -> Uuid,
@@ -185,6 +232,11 @@ diesel::table! {
         description -> Text,
         completed -> Bool,
         created_at -> Timestamptz,
+        title -> Text,
+        position -> Text,
+        urgency -> Text,
+        version -> Int4,
+        field_versions -> Jsonb,
     }
 }
 
@@ -194,8 +246,23 @@ diesel::table! {
         trip_id -> Uuid,
         user_id -> Nullable<Uuid>,
         invitee_email -> Nullable<Text>,
-        status -> Nullable<Text>,
-        created_at -> Nullable<Timestamptz>,
+        status -> Text,
+        created_at -> Timestamptz,
+        permission -> Text,
+        invited_by -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    trip_share_links (id) {
+        id -> Uuid,
+        trip_id -> Uuid,
+        created_by -> Nullable<Uuid>,
+        include_expenses -> Bool,
+        include_documents -> Bool,
+        expires_at -> Timestamptz,
+        revoked_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
     }
 }
 
@@ -209,6 +276,12 @@ diesel::table! {
         end_date -> Nullable<Date>,
         no_collaborators -> Int4,
         created_at -> Nullable<Timestamptz>,
+        description -> Nullable<Text>,
+        updated_at -> Timestamptz,
+        version -> Int4,
+        field_versions -> Jsonb,
+        archived_at -> Nullable<Timestamptz>,
+        deleted_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -231,7 +304,10 @@ diesel::table! {
     user_trip (user_id, trip_id) {
         user_id -> Uuid,
         trip_id -> Uuid,
-        permission -> Nullable<Text>,
+        permission -> Text,
+        id -> Uuid,
+        version -> Int4,
+        joined_at -> Timestamptz,
     }
 }
 
@@ -263,8 +339,10 @@ diesel::joinable!(accommodations -> documents (from_document));
 diesel::joinable!(accommodations -> locations (location));
 diesel::joinable!(accommodations -> trips (trip_id));
 diesel::joinable!(budget_planners -> trips (trip_id));
+diesel::joinable!(calendar_feeds -> users (user_id));
 diesel::joinable!(documents -> trips (trip_id));
 diesel::joinable!(expense_payers -> expenses (expense_id));
+diesel::joinable!(expense_payers -> trips (trip_id));
 diesel::joinable!(expense_payers -> users (user_id));
 diesel::joinable!(expenses -> trips (trip_id));
 diesel::joinable!(flights -> documents (from_document));
@@ -281,9 +359,12 @@ diesel::joinable!(passengers -> flights (flight_id));
 diesel::joinable!(passengers -> users (user_id));
 diesel::joinable!(refresh_tokens -> users (user_id));
 diesel::joinable!(replicache_client -> replicache_client_group (client_group_id));
+diesel::joinable!(sync_conflicts -> trips (trip_id));
+diesel::joinable!(sync_conflicts -> users (proposed_by));
 diesel::joinable!(tasks -> trips (trip_id));
 diesel::joinable!(trip_invites -> trips (trip_id));
-diesel::joinable!(trip_invites -> users (user_id));
+diesel::joinable!(trip_share_links -> trips (trip_id));
+diesel::joinable!(trip_share_links -> users (created_by));
 diesel::joinable!(trips -> users (owner_id));
 diesel::joinable!(user_journal -> journals (journal_id));
 diesel::joinable!(user_journal -> users (user_id));
@@ -295,6 +376,7 @@ diesel::joinable!(user_trip -> users (user_id));
 diesel::allow_tables_to_appear_in_same_query!(
     accommodations,
     budget_planners,
+    calendar_feeds,
     documents,
     email_subscribers,
     expense_payers,
@@ -311,8 +393,11 @@ diesel::allow_tables_to_appear_in_same_query!(
     replicache_client,
     replicache_client_group,
     replicache_space,
+    replicache_tombstones,
+    sync_conflicts,
     tasks,
     trip_invites,
+    trip_share_links,
     trips,
     user_journal,
     user_map,
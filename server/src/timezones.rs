@@ -0,0 +1,108 @@
+//! Local time zones of locations.
+//!
+//! Times are stored in UTC, which reads oddly for anyone planning from another zone. Each
+//! location gets the IANA zone its coordinates are in, looked up in a timezone-boundary dataset
+//! that's bundled with the server so it works offline. Views show times on the local clock next
+//! to UTC.
+
+use std::sync::LazyLock;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl, pooled_connection::deadpool::Pool};
+use tzf_rs::DefaultFinder;
+use uuid::Uuid;
+
+use crate::schema::locations;
+
+// loading the boundaries takes a moment, so it's done once on the first lookup
+static FINDER: LazyLock<DefaultFinder> = LazyLock::new(DefaultFinder::new);
+
+/// The zone the coordinates are in, out at sea it's the nautical `Etc/GMT` zone.
+pub fn zone_at(latitude: f64, longitude: f64) -> Option<Tz> {
+    FINDER.get_tz_name(longitude, latitude).parse().ok()
+}
+
+/// The time on the clock in `zone`.
+pub fn local_time(time: DateTime<Utc>, zone: &str) -> Option<NaiveDateTime> {
+    let zone: Tz = zone.parse().ok()?;
+
+    Some(time.with_timezone(&zone).naive_local())
+}
+
+/// Fills in the zone of locations that were stored before zones were. Returns how many were.
+pub async fn backfill(conn: &mut AsyncPgConnection) -> QueryResult<usize> {
+    let missing = locations::table
+        .filter(locations::timezone.is_null())
+        .select((locations::id, locations::latitude, locations::longitude))
+        .load::<(Uuid, f64, f64)>(conn)
+        .await?;
+
+    let mut filled = 0;
+
+    for (id, latitude, longitude) in missing {
+        let Some(zone) = zone_at(latitude, longitude) else {
+            continue;
+        };
+
+        filled += diesel::update(locations::table.find(id))
+            .set(locations::timezone.eq(zone.name()))
+            .execute(conn)
+            .await?;
+    }
+
+    Ok(filled)
+}
+
+/// Runs `backfill` once when the server starts.
+pub async fn run_backfill(database: Pool<AsyncPgConnection>) {
+    // loaded up front so the first request that stores a location doesn't wait on it
+    if actix_web::web::block(|| {
+        LazyLock::force(&FINDER);
+    })
+    .await
+    .is_err()
+    {
+        log::warn!("timezone boundaries couldn't be loaded");
+        return;
+    }
+
+    let mut conn = match database.get().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::warn!("timezone backfill couldn't connect: {e}");
+            return;
+        }
+    };
+
+    match backfill(&mut conn).await {
+        Ok(filled) => log::info!("timezone backfill filled in {filled} locations"),
+        Err(e) => log::warn!("timezone backfill failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn zones_come_from_coordinates() {
+        assert_eq!(zone_at(35.6762, 139.6503), Some(Tz::Asia__Tokyo));
+        assert_eq!(zone_at(-33.8688, 151.2093), Some(Tz::Australia__Sydney));
+        assert_eq!(zone_at(52.3676, 4.9041), Some(Tz::Europe__Amsterdam));
+    }
+
+    #[test]
+    fn local_times_follow_the_zone() {
+        let tour = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            local_time(tour, "Asia/Tokyo").map(|time| time.to_string()),
+            Some("2025-06-01 09:00:00".to_string())
+        );
+        assert_eq!(local_time(tour, "Not/AZone"), None);
+    }
+}
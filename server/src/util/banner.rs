@@ -0,0 +1,102 @@
+//! Processing of uploaded trip banners.
+//!
+//! The file type is sniffed from the bytes rather than taken from the file name or the
+//! `Content-Type` the client sent. Banners are scaled down to `BANNER_WIDTH` and re-encoded as
+//! JPEG, which also drops whatever metadata the original carried.
+
+use std::io::Cursor;
+
+use image::{ImageFormat, ImageReader, Limits, codecs::jpeg::JpegEncoder, imageops::FilterType};
+
+use crate::util::errors::{AppError, AppResult};
+
+/// Banners are shown full width, wider images are scaled down to this.
+pub const BANNER_WIDTH: u32 = 1600;
+
+const JPEG_QUALITY: u8 = 82;
+
+/// Refuses images that would take too much memory to decode.
+const MAX_DIMENSION: u32 = 12_000;
+
+/// The image format of `bytes`, if it's one banners can be made from.
+pub fn detect_format(bytes: &[u8]) -> Option<ImageFormat> {
+    match infer::get(bytes)?.mime_type() {
+        "image/jpeg" => Some(ImageFormat::Jpeg),
+        "image/png" => Some(ImageFormat::Png),
+        "image/webp" => Some(ImageFormat::WebP),
+        _ => None,
+    }
+}
+
+/// Turns an uploaded image into a banner, returns the JPEG to store.
+pub fn process(bytes: &[u8]) -> AppResult<Vec<u8>> {
+    let format = detect_format(bytes).ok_or(AppError::BadRequest(
+        "Banners must be JPEG, PNG or WebP images.",
+    ))?;
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+
+    let mut image = reader
+        .decode()
+        .map_err(|_| AppError::BadRequest("The image couldn't be read."))?;
+
+    if image.width() > BANNER_WIDTH {
+        // the height bound is never the tighter one, so only the width decides
+        image = image.resize(BANNER_WIDTH, u32::MAX, FilterType::CatmullRom);
+    }
+
+    let mut banner = Vec::new();
+
+    image
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut banner, JPEG_QUALITY))
+        .map_err(|_| AppError::InternalError)?;
+
+    Ok(banner)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageFormat, RgbImage};
+
+    use super::*;
+
+    fn encoded(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+            .write_to(&mut bytes, format)
+            .unwrap();
+
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn wide_images_are_scaled_down_to_jpeg() {
+        let banner = process(&encoded(3200, 1000, ImageFormat::Png)).unwrap();
+
+        assert_eq!(detect_format(&banner), Some(ImageFormat::Jpeg));
+
+        let banner = image::load_from_memory(&banner).unwrap();
+        assert_eq!((banner.width(), banner.height()), (BANNER_WIDTH, 500));
+    }
+
+    #[test]
+    fn narrow_images_keep_their_size() {
+        let banner = process(&encoded(800, 300, ImageFormat::Jpeg)).unwrap();
+
+        let banner = image::load_from_memory(&banner).unwrap();
+        assert_eq!((banner.width(), banner.height()), (800, 300));
+    }
+
+    #[test]
+    fn files_that_arent_images_are_rejected() {
+        assert!(process(b"%PDF-1.7 definitely an image").is_err());
+        assert!(process(&[]).is_err());
+    }
+}
@@ -1,3 +1,4 @@
 pub mod auth;
+pub mod banner;
 pub mod errors;
 pub mod view_conversion;
@@ -1,6 +1,14 @@
 use crate::{
-    models::user::{Collaborator, User},
-    views::{EncodableCollaborator, EncodableUser, EncodableUserPreview},
+    models::{
+        trip::Trip,
+        trip_invite::TripInvite,
+        trip_share_link::TripShareLink,
+        user::{Collaborator, User},
+    },
+    views::{
+        EncodableCollaborator, EncodableShareLink, EncodableTripInvite, EncodableTripOverview,
+        EncodableUser, EncodableUserPreview,
+    },
 };
 
 impl From<&Collaborator> for EncodableCollaborator {
@@ -9,7 +17,7 @@ impl From<&Collaborator> for EncodableCollaborator {
             id: value.id,
             username: value.username.clone(),
             avatar: value.avatar.clone(),
-            permission: value.permission.clone(),
+            permission: value.permission,
         }
     }
 }
@@ -34,3 +42,47 @@ impl From<User> for EncodableUserPreview {
         }
     }
 }
+
+impl From<Trip> for EncodableTripOverview {
+    fn from(value: Trip) -> Self {
+        Self {
+            id: value.id,
+            title: value.title,
+            banner_image: value.banner_image,
+            start_date: value.start_date,
+            end_date: value.end_date,
+            no_collaborators: value.no_collaborators,
+            archived_at: value.archived_at,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+impl EncodableTripInvite {
+    pub fn new(invite: TripInvite, trip_title: Option<String>) -> Self {
+        Self {
+            id: invite.id,
+            trip_id: invite.trip_id,
+            trip_title,
+            invitee_email: invite.invitee_email,
+            user_id: invite.user_id,
+            permission: invite.permission,
+            invited_by: invite.invited_by,
+            created_at: invite.created_at,
+        }
+    }
+}
+
+impl EncodableShareLink {
+    pub fn new(link: TripShareLink, secret: &str) -> Self {
+        Self {
+            token: link.token(secret),
+            id: link.id,
+            trip_id: link.trip_id,
+            include_expenses: link.include_expenses,
+            include_documents: link.include_documents,
+            expires_at: link.expires_at,
+            created_at: link.created_at,
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use bigdecimal::BigDecimal;
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::models::user_trip::Role;
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct EncodableUser {
     pub id: Uuid,
@@ -19,7 +21,7 @@ pub struct EncodableCollaborator {
     pub id: Uuid,
     pub username: String,
     pub avatar: Option<String>,
-    pub permission: Option<String>,
+    pub permission: Role,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -33,7 +35,11 @@ pub struct EncodableTripOverview {
     #[schema(example = "2025-12-20")]
     pub end_date: Option<NaiveDate>,
     pub no_collaborators: i32,
+    pub archived_at: Option<DateTime<Utc>>,
+    /// Set on deleted trips, they're purged once the grace period after this is over
+    pub deleted_at: Option<DateTime<Utc>>,
 }
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct EncodableUserPreview {
     pub id: Uuid,
@@ -52,13 +58,13 @@ pub struct EncodableExpense {
     pub payers: Vec<EncodableUserPreview>,
 }
 
-#[derive(Serialize, Deserialize, ToSchema)]
+#[derive(Default, Serialize, Deserialize, ToSchema)]
 pub struct EncodableBudgetPlan {
     pub group_budget: EncodableGroupBudget,
     pub expenses: Vec<EncodableExpense>,
 }
 
-#[derive(Serialize, Deserialize, ToSchema)]
+#[derive(Default, Serialize, Deserialize, ToSchema)]
 pub struct EncodableGroupBudget {
     pub currency: Option<String>,
     #[schema(value_type = String, example = "123.45")]
@@ -77,6 +83,8 @@ pub struct EncodableGroupBudget {
 
 #[derive(Serialize, Deserialize, ToSchema, Clone)]
 pub struct EncodableLocation {
+    /// Links an existing location of the trip, the other fields are ignored when set
+    pub id: Option<Uuid>,
     pub display_name: Option<String>,
     pub address: String,
     pub longitude: f64,
@@ -98,10 +106,105 @@ pub struct EncodableItineraryItem {
     pub location: Option<EncodableLocation>,
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
+    /// IANA zone of the item's location, left out when it has none
+    #[schema(example = "Asia/Tokyo")]
+    pub timezone: Option<String>,
+    /// `start_time` on the clock where the item takes place
+    #[schema(value_type = Option<String>, example = "2025-06-01T09:00:00")]
+    pub local_start_time: Option<NaiveDateTime>,
+    #[schema(value_type = Option<String>, example = "2025-06-01T11:30:00")]
+    pub local_end_time: Option<NaiveDateTime>,
     pub cost: Option<ItineraryExpense>,
     pub notes: Option<String>,
 }
 
+/// Times are in UTC and on the clock at the airport, when the airport's location is known.
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct EncodableFlight {
+    pub id: Uuid,
+    #[schema(example = "KL861")]
+    pub flight_code: Option<String>,
+    pub departure_time: Option<DateTime<Utc>>,
+    #[schema(example = "Europe/Amsterdam")]
+    pub departure_timezone: Option<String>,
+    #[schema(value_type = Option<String>, example = "2025-06-01T14:35:00")]
+    pub local_departure_time: Option<NaiveDateTime>,
+    pub arrival_time: Option<DateTime<Utc>>,
+    #[schema(example = "Asia/Tokyo")]
+    pub arrival_timezone: Option<String>,
+    #[schema(value_type = Option<String>, example = "2025-06-02T09:15:00")]
+    pub local_arrival_time: Option<NaiveDateTime>,
+}
+
+/// An accommodation, times are in UTC and on the clock where it is.
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct EncodableStay {
+    pub id: Uuid,
+    pub location: Option<EncodableLocation>,
+    #[schema(example = "Europe/Amsterdam")]
+    pub timezone: Option<String>,
+    pub check_in_time: Option<DateTime<Utc>>,
+    #[schema(value_type = Option<String>, example = "2025-06-01T15:00:00")]
+    pub local_check_in_time: Option<NaiveDateTime>,
+    pub check_out_time: Option<DateTime<Utc>>,
+    #[schema(value_type = Option<String>, example = "2025-06-04T11:00:00")]
+    pub local_check_out_time: Option<NaiveDateTime>,
+}
+
+/// One date of a trip, on the clock where its entries take place.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct EncodableTripDay {
+    pub date: NaiveDate,
+    /// Items starting that day, in order
+    pub items: Vec<EncodableItineraryItem>,
+    /// Flights departing or arriving that day
+    pub flights: Vec<EncodableFlight>,
+    /// Where the night after the date is spent
+    pub stay: Option<EncodableStay>,
+    /// What the day's items cost, one total per currency
+    pub spending: Vec<ItineraryExpense>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEntryType {
+    ItineraryItem,
+    Flight,
+    Accommodation,
+}
+
+/// Something on the trip's timeline that a warning is about.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EncodableTimelineEntry {
+    pub entry_type: TimelineEntryType,
+    pub id: Uuid,
+    /// The item's title or the flight code, accommodations have none
+    pub title: Option<String>,
+}
+
+/// A problem with following the itinerary as planned. These are warnings, nothing stops the
+/// itinerary from being saved.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EncodableItineraryWarning {
+    /// The two take place at the same time
+    Overlap {
+        first: EncodableTimelineEntry,
+        second: EncodableTimelineEntry,
+    },
+    /// The entry is before the trip's start date or after its end date
+    OutsideTripDates { entry: EncodableTimelineEntry },
+    /// There isn't enough time to get from one place to the next
+    Unreachable {
+        from: EncodableTimelineEntry,
+        to: EncodableTimelineEntry,
+        /// Great-circle distance between the two
+        distance_km: f64,
+        available_minutes: i64,
+        required_minutes: i64,
+    },
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct EncodableDocument {
     pub id: Uuid,
@@ -119,5 +222,51 @@ pub struct EncodableTripData {
     pub collaborators: Vec<EncodableCollaborator>,
     pub budget_plan: EncodableBudgetPlan,
     pub itinerary: Vec<EncodableItineraryItem>,
+    pub flights: Vec<EncodableFlight>,
     pub documents: Vec<EncodableDocument>,
+    pub warnings: Vec<EncodableItineraryWarning>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct EncodableConflict {
+    pub id: Uuid,
+    #[schema(example = "expense")]
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    /// The field the values are for, missing when the entity was deleted
+    #[schema(example = "cost")]
+    pub field: Option<String>,
+    #[schema(value_type = Option<Object>)]
+    pub current_value: Option<serde_json::Value>,
+    #[schema(value_type = Object)]
+    pub proposed_value: serde_json::Value,
+    pub proposed_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct EncodableTripInvite {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    #[schema(example = "Japan Trip 2025")]
+    pub trip_title: Option<String>,
+    #[schema(example = "funemail@journly.com")]
+    pub invitee_email: Option<String>,
+    /// Set when the invitee has an account
+    pub user_id: Option<Uuid>,
+    pub permission: Role,
+    pub invited_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct EncodableShareLink {
+    pub id: Uuid,
+    pub trip_id: Uuid,
+    /// Goes in the link, anyone with it can see the trip at `/api/v1/shared/{token}`
+    pub token: String,
+    pub include_expenses: bool,
+    pub include_documents: bool,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
 }
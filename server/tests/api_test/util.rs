@@ -1,6 +1,9 @@
 use std::str::FromStr;
 
-use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{
+    Response,
+    header::{HeaderName, HeaderValue},
+};
 
 pub struct AuthHeader {
     pub header_name: HeaderName,
@@ -15,3 +18,26 @@ impl AuthHeader {
         }
     }
 }
+
+pub struct RefreshCookie {
+    pub header_name: HeaderName,
+    pub header_value: HeaderValue,
+}
+
+impl RefreshCookie {
+    /// Reads the refresh token cookie set by a login response.
+    pub fn from_response(response: &Response) -> Self {
+        let refresh_token = response
+            .headers()
+            .get_all("Set-Cookie")
+            .iter()
+            .filter_map(|cookie| cookie.to_str().ok()?.split(';').next())
+            .find(|cookie| cookie.starts_with("refresh_token="))
+            .expect("Response did not set a refresh token cookie");
+
+        Self {
+            header_name: HeaderName::from_str("Cookie").unwrap(),
+            header_value: HeaderValue::from_str(refresh_token).unwrap(),
+        }
+    }
+}
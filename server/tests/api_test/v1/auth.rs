@@ -1,8 +1,9 @@
-use crate::{api_test::util::AuthHeader, spawn_app};
-use futures::FutureExt;
-use journly_server::controllers::auth::{
-    LoginCredentials, LoginResponse, RefreshTokenBody, RegisterUserBody,
+use crate::{
+    api_test::util::{AuthHeader, RefreshCookie},
+    spawn_app,
 };
+use futures::FutureExt;
+use journly_server::controllers::auth::{LoginCredentials, LoginResponse, RegisterUserBody};
 use reqwest::{Client, StatusCode};
 use std::panic::AssertUnwindSafe;
 
@@ -200,20 +201,18 @@ pub async fn logout_works() {
             .await
             .expect("Request to POST '/login' failed to resolve");
 
+        let refresh_cookie = RefreshCookie::from_response(&response);
+
         let response_body = response.text().await.unwrap();
 
         let tokens: LoginResponse =
             serde_json::from_str(&response_body).expect("Could not parse response body");
 
-        let logout_req_body = RefreshTokenBody {
-            refresh_token: tokens.refresh_token,
-        };
-
         let auth_header = AuthHeader::new(&tokens.access_token);
 
         let response = client
             .post(format!("{address}/api/v1/auth/logout"))
-            .json(&logout_req_body)
+            .header(refresh_cookie.header_name, refresh_cookie.header_value)
             .header(auth_header.header_name, auth_header.header_value)
             .send()
             .await
@@ -295,18 +294,11 @@ pub async fn get_access_token_with_refresh_token() {
             .await
             .expect("Request to POST '/login' failed to resolve");
 
-        let response_body = response.text().await.unwrap();
-
-        let tokens: LoginResponse =
-            serde_json::from_str(&response_body).expect("Could not parse response body");
-
-        let refresh_req_body = RefreshTokenBody {
-            refresh_token: tokens.refresh_token,
-        };
+        let refresh_cookie = RefreshCookie::from_response(&response);
 
         let response = client
             .post(format!("{address}/api/v1/auth/refresh"))
-            .json(&refresh_req_body)
+            .header(refresh_cookie.header_name, refresh_cookie.header_value)
             .send()
             .await
             .expect("Request to POST '/refresh' failed to resolve");
@@ -346,20 +338,21 @@ pub async fn refresh_token_invalidation_works() {
             .await
             .expect("Request to POST '/login' failed to resolve");
 
+        let refresh_cookie = RefreshCookie::from_response(&response);
+
         let response_body = response.text().await.unwrap();
 
         let tokens: LoginResponse =
             serde_json::from_str(&response_body).expect("Could not parse response body");
 
-        let req_body = RefreshTokenBody {
-            refresh_token: tokens.refresh_token,
-        };
-
         let auth_header = AuthHeader::new(&tokens.access_token);
 
         let response = client
             .post(format!("{address}/api/v1/auth/logout"))
-            .json(&req_body)
+            .header(
+                refresh_cookie.header_name.clone(),
+                refresh_cookie.header_value.clone(),
+            )
             .header(auth_header.header_name, auth_header.header_value)
             .send()
             .await
@@ -369,7 +362,7 @@ pub async fn refresh_token_invalidation_works() {
 
         let response = client
             .post(format!("{address}/api/v1/auth/refresh"))
-            .json(&req_body)
+            .header(refresh_cookie.header_name, refresh_cookie.header_value)
             .send()
             .await
             .expect("Request to POST '/refresh' failed to resolve");
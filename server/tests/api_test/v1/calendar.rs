@@ -0,0 +1,264 @@
+use std::panic::AssertUnwindSafe;
+
+use futures::FutureExt;
+use journly_server::controllers::{
+    auth::GetMeResponse,
+    calendar::CalendarFeedResponse,
+    trip::{CreateTripBody, CreateTripResponse},
+};
+use reqwest::{
+    Client, StatusCode,
+    header::{CONTENT_TYPE, HOST},
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{api_test::util::AuthHeader, spawn_app};
+
+#[actix_rt::test]
+pub async fn trip_calendar_has_an_event_per_item() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let auth_header = AuthHeader::new(&test_app.access_token);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Amsterdam".to_string()),
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let museum_id = Uuid::new_v4();
+
+        let response = client
+            .patch(format!("{address}/api/v1/trips/{}/itinerary", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&json!({
+                "operations": [
+                    {
+                        "type": "add_item",
+                        "item": {
+                            "id": museum_id,
+                            "title": "Museum",
+                            "start_time": "2025-06-01T09:00:00Z",
+                            "end_time": "2025-06-01T11:30:00Z",
+                            "location": {
+                                "address": "Museumplein 6, Amsterdam",
+                                "longitude": 4.88,
+                                "latitude": 52.36,
+                            },
+                        },
+                    },
+                ],
+            }))
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = client
+            .get(format!("{address}/api/v1/trips/{}/calendar.ics", trip.id))
+            .header(auth_header.header_name, auth_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(
+            response.headers()[CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/calendar")
+        );
+
+        let ics = response.text().await.expect("Failed to read calendar.");
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("\r\nX-WR-CALNAME:Amsterdam\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(ics.contains(&format!("\r\nUID:itinerary-item-{museum_id}@journly\r\n")));
+        assert!(ics.contains("\r\nDTSTART:20250601T090000Z\r\nDTEND:20250601T113000Z\r\n"));
+        assert!(ics.contains("\r\nLOCATION:Museumplein 6\\, Amsterdam\r\n"));
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn calendar_feed_works_until_rotated_or_revoked() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let auth_header = AuthHeader::new(&test_app.access_token);
+
+        let user = client
+            .get(format!("{address}/api/v1/auth/me"))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<GetMeResponse>()
+            .await
+            .expect("Failed to parse get_me return value.")
+            .user;
+
+        let lisbon = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Lisbon".to_string()),
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        for (trip_id, title) in [
+            (lisbon.id.to_string(), "Tram 28"),
+            ("c8381024-3f79-4a10-b5fe-06dc24e74bdc".to_string(), "Ferry"),
+        ] {
+            let response = client
+                .patch(format!("{address}/api/v1/trips/{trip_id}/itinerary"))
+                .header(
+                    auth_header.header_name.clone(),
+                    auth_header.header_value.clone(),
+                )
+                .json(&json!({
+                    "operations": [{
+                        "type": "add_item",
+                        "item": {
+                            "id": Uuid::new_v4(),
+                            "title": title,
+                            "start_time": "2025-06-01T09:00:00Z",
+                        },
+                    }],
+                }))
+                .send()
+                .await
+                .expect("Request could not be resolved.");
+
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let rotate_feed = || {
+            client
+                .post(format!("{address}/api/v1/users/{}/calendar-feed", user.id))
+                .header(
+                    auth_header.header_name.clone(),
+                    auth_header.header_value.clone(),
+                )
+                // the address in the response doesn't come from the request
+                .header(HOST, "attacker.example")
+                .send()
+        };
+
+        let feed = rotate_feed()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CalendarFeedResponse>()
+            .await
+            .expect("Failed to parse rotate_calendar_feed return value.");
+
+        assert!(
+            feed.url
+                .starts_with(&format!("{address}/api/v1/calendar-feeds/"))
+        );
+        assert!(feed.webcal_url.starts_with("webcal://127.0.0.1:"));
+
+        // calendar apps fetch the feed without signing in
+        let response = client
+            .get(&feed.url)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // every trip the user is on is in the feed
+        let calendar = response.text().await.expect("Failed to read calendar.");
+
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(calendar.contains("SUMMARY:Tram 28\r\n"));
+        assert!(calendar.contains("SUMMARY:Ferry\r\n"));
+
+        let rotated = rotate_feed()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CalendarFeedResponse>()
+            .await
+            .expect("Failed to parse rotate_calendar_feed return value.");
+
+        let response = client
+            .get(&feed.url)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = client
+            .delete(format!("{address}/api/v1/users/{}/calendar-feed", user.id))
+            .header(auth_header.header_name, auth_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = client
+            .get(&rotated.url)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
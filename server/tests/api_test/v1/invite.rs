@@ -0,0 +1,392 @@
+use std::panic::AssertUnwindSafe;
+
+use futures::FutureExt;
+use journly_server::{
+    auth::create_token,
+    controllers::{
+        invite::{CreateInviteBody, CreateInviteResponse, GetInvitesResponse},
+        trip::{CreateTripBody, CreateTripResponse, UpdateTripBody},
+    },
+    models::user_trip::Role,
+};
+use reqwest::{Client, StatusCode};
+use uuid::Uuid;
+
+use crate::{api_test::util::AuthHeader, spawn_app};
+
+const INVITEE_ID: &str = "22222222-2222-2222-2222-222222222222";
+
+#[actix_rt::test]
+pub async fn accepting_invite_joins_trip() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let owner_header = AuthHeader::new(&test_app.access_token);
+
+        let invitee_id = Uuid::parse_str(INVITEE_ID).unwrap();
+        let invitee_token = create_token(
+            &invitee_id,
+            &test_app.config.jwt_config.access_secret,
+            10,
+            "user",
+        );
+        let invitee_header = AuthHeader::new(&invitee_token);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                owner_header.header_name.clone(),
+                owner_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Invite Trip".to_string()),
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let response = client
+            .post(format!("{address}/api/v1/trips/{}/invites", trip.id))
+            .header(owner_header.header_name, owner_header.header_value)
+            .json(&CreateInviteBody {
+                email: None,
+                user_id: Some(invitee_id),
+                permission: Some(Role::Editor),
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let invite = response
+            .json::<CreateInviteResponse>()
+            .await
+            .expect("Failed to parse create_invite return value.")
+            .invite;
+
+        let response = client
+            .get(format!("{address}/api/v1/invites"))
+            .header(
+                invitee_header.header_name.clone(),
+                invitee_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        let invites = response
+            .json::<GetInvitesResponse>()
+            .await
+            .expect("Failed to parse get_my_invites return value.")
+            .invites;
+
+        assert!(invites.iter().any(|pending| pending.id == invite.id));
+
+        let response = client
+            .post(format!("{address}/api/v1/invites/{}/accept", invite.id))
+            .header(
+                invitee_header.header_name.clone(),
+                invitee_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = client
+            .get(format!("{address}/api/v1/trips/{}", trip.id))
+            .header(invitee_header.header_name, invitee_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn invite_without_invitee_returns_400() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let auth_header = AuthHeader::new(&test_app.access_token);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: None,
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let response = client
+            .post(format!("{address}/api/v1/trips/{}/invites", trip.id))
+            .header(auth_header.header_name, auth_header.header_value)
+            .json(&CreateInviteBody {
+                email: None,
+                user_id: None,
+                permission: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn viewer_cannot_update_trip() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let owner_header = AuthHeader::new(&test_app.access_token);
+
+        let invitee_id = Uuid::parse_str(INVITEE_ID).unwrap();
+        let invitee_token = create_token(
+            &invitee_id,
+            &test_app.config.jwt_config.access_secret,
+            10,
+            "user",
+        );
+        let invitee_header = AuthHeader::new(&invitee_token);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                owner_header.header_name.clone(),
+                owner_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Viewer Trip".to_string()),
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let invite = client
+            .post(format!("{address}/api/v1/trips/{}/invites", trip.id))
+            .header(owner_header.header_name, owner_header.header_value)
+            .json(&CreateInviteBody {
+                email: None,
+                user_id: Some(invitee_id),
+                permission: Some(Role::Viewer),
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateInviteResponse>()
+            .await
+            .expect("Failed to parse create_invite return value.")
+            .invite;
+
+        client
+            .post(format!("{address}/api/v1/invites/{}/accept", invite.id))
+            .header(
+                invitee_header.header_name.clone(),
+                invitee_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        let response = client
+            .put(format!("{address}/api/v1/trips/{}", trip.id))
+            .header(invitee_header.header_name, invitee_header.header_value)
+            .json(&UpdateTripBody {
+                title: Some("Renamed".to_string()),
+                description: None,
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn invites_to_deleted_trips_cannot_be_accepted() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let owner_header = AuthHeader::new(&test_app.access_token);
+
+        let invitee_id = Uuid::parse_str(INVITEE_ID).unwrap();
+        let invitee_token = create_token(
+            &invitee_id,
+            &test_app.config.jwt_config.access_secret,
+            10,
+            "user",
+        );
+        let invitee_header = AuthHeader::new(&invitee_token);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                owner_header.header_name.clone(),
+                owner_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Deleted Trip".to_string()),
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let invite = client
+            .post(format!("{address}/api/v1/trips/{}/invites", trip.id))
+            .header(
+                owner_header.header_name.clone(),
+                owner_header.header_value.clone(),
+            )
+            .json(&CreateInviteBody {
+                email: None,
+                user_id: Some(invitee_id),
+                permission: Some(Role::Viewer),
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateInviteResponse>()
+            .await
+            .expect("Failed to parse create_invite return value.")
+            .invite;
+
+        let response = client
+            .delete(format!("{address}/api/v1/trips/{}", trip.id))
+            .header(
+                owner_header.header_name.clone(),
+                owner_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let invites = client
+            .get(format!("{address}/api/v1/invites"))
+            .header(
+                invitee_header.header_name.clone(),
+                invitee_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<GetInvitesResponse>()
+            .await
+            .expect("Failed to parse get_my_invites return value.")
+            .invites;
+
+        assert!(invites.iter().all(|pending| pending.id != invite.id));
+
+        let response = client
+            .post(format!("{address}/api/v1/invites/{}/accept", invite.id))
+            .header(
+                invitee_header.header_name.clone(),
+                invitee_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // restoring the trip brings the invite back
+        let response = client
+            .post(format!("{address}/api/v1/trips/{}/restore", trip.id))
+            .header(owner_header.header_name, owner_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = client
+            .post(format!("{address}/api/v1/invites/{}/accept", invite.id))
+            .header(invitee_header.header_name, invitee_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
@@ -0,0 +1,577 @@
+use std::panic::AssertUnwindSafe;
+
+use chrono::NaiveDate;
+use futures::FutureExt;
+use journly_server::controllers::{
+    trip::{CloneTripBody, CreateTripBody, CreateTripResponse, GetTripResponse},
+    trip_plan::{
+        GetTripDaysResponse, ImportItineraryBody, ImportItineraryResponse,
+        PreviewItineraryImportResponse, UpdateItineraryResponse,
+    },
+};
+use reqwest::{
+    Client, Method, StatusCode,
+    header::{
+        ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_REQUEST_HEADERS,
+        ACCESS_CONTROL_REQUEST_METHOD, CONTENT_TYPE, ORIGIN,
+    },
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{api_test::util::AuthHeader, spawn_app};
+
+#[actix_rt::test]
+pub async fn itinerary_operations_apply_together() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let auth_header = AuthHeader::new(&test_app.access_token);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Itinerary".to_string()),
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let museum_id = Uuid::new_v4();
+        let dinner_id = Uuid::new_v4();
+
+        let response = client
+            .patch(format!("{address}/api/v1/trips/{}/itinerary", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&json!({
+                "operations": [
+                    {
+                        "type": "add_item",
+                        "item": {
+                            "id": museum_id,
+                            "title": "Museum",
+                            "start_time": "2025-06-01T09:00:00Z",
+                            "location": {
+                                "address": "Museumplein 6, Amsterdam",
+                                "longitude": 4.88,
+                                "latitude": 52.36,
+                            },
+                            "cost": { "cost": "22.50", "currency": "EUR" },
+                        },
+                    },
+                    {
+                        "type": "add_item",
+                        "item": {
+                            "id": dinner_id,
+                            "title": "Dinner",
+                            "start_time": "2025-06-01T19:00:00Z",
+                        },
+                    },
+                    {
+                        "type": "update_item",
+                        "item_id": museum_id,
+                        "item": { "cost": { "cost": "25.00", "currency": "EUR" } },
+                    },
+                    { "type": "remove_item", "item_id": dinner_id },
+                ],
+            }))
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let itinerary = response
+            .json::<UpdateItineraryResponse>()
+            .await
+            .expect("Failed to parse update_itinerary return value.")
+            .itinerary;
+
+        assert_eq!(itinerary.len(), 1);
+        assert_eq!(itinerary[0].id, Some(museum_id));
+        assert_eq!(
+            itinerary[0].location.as_ref().map(|l| l.address.as_str()),
+            Some("Museumplein 6, Amsterdam")
+        );
+        assert_eq!(
+            itinerary[0].cost.as_ref().map(|c| c.cost.to_string()),
+            Some("25.00".to_string())
+        );
+
+        let museum_location = itinerary[0].location.as_ref().and_then(|l| l.id);
+
+        assert!(museum_location.is_some());
+
+        // an unchanged location keeps its row, a linked one is shared
+        let response = client
+            .patch(format!("{address}/api/v1/trips/{}/itinerary", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&json!({
+                "operations": [
+                    {
+                        "type": "update_item",
+                        "item_id": museum_id,
+                        "item": {
+                            "title": "Rijksmuseum",
+                            "location": {
+                                "address": "Museumplein 6, Amsterdam",
+                                "longitude": 4.88,
+                                "latitude": 52.36,
+                            },
+                        },
+                    },
+                    {
+                        "type": "add_item",
+                        "item": {
+                            "id": dinner_id,
+                            "title": "Lunch",
+                            "start_time": "2025-06-01T12:00:00Z",
+                            "location": {
+                                "id": museum_location,
+                                "address": "",
+                                "longitude": 0.0,
+                                "latitude": 0.0,
+                            },
+                        },
+                    },
+                ],
+            }))
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let itinerary = response
+            .json::<UpdateItineraryResponse>()
+            .await
+            .expect("Failed to parse update_itinerary return value.")
+            .itinerary;
+
+        assert_eq!(itinerary.len(), 2);
+        assert!(
+            itinerary
+                .iter()
+                .all(|item| item.location.as_ref().and_then(|l| l.id) == museum_location)
+        );
+
+        // locations of other trips can't be linked
+        let response = client
+            .patch(format!("{address}/api/v1/trips/{}/itinerary", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&json!({
+                "operations": [
+                    {
+                        "type": "update_item",
+                        "item_id": museum_id,
+                        "item": {
+                            "location": {
+                                "id": Uuid::new_v4(),
+                                "address": "",
+                                "longitude": 0.0,
+                                "latitude": 0.0,
+                            },
+                        },
+                    },
+                ],
+            }))
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = client
+            .patch(format!("{address}/api/v1/trips/{}/itinerary", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&json!({
+                "operations": [{ "type": "remove_item", "item_id": dinner_id }],
+            }))
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // the second operation fails, so the first one is rolled back too
+        let response = client
+            .patch(format!("{address}/api/v1/trips/{}/itinerary", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&json!({
+                "operations": [
+                    { "type": "remove_item", "item_id": museum_id },
+                    { "type": "remove_item", "item_id": dinner_id },
+                ],
+            }))
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let trip = client
+            .get(format!("{address}/api/v1/trips/{}", trip.id))
+            .header(auth_header.header_name, auth_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<GetTripResponse>()
+            .await
+            .expect("Failed to parse get_trip return value.")
+            .trip;
+
+        assert_eq!(trip.itinerary.len(), 1);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn itinerary_patch_passes_preflight() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let response = client
+            .request(
+                Method::OPTIONS,
+                format!("{address}/api/v1/trips/c8381024-3f79-4a10-b5fe-06dc24e74bdc/itinerary"),
+            )
+            .header(ORIGIN, "http://localhost:3000")
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "PATCH")
+            .header(
+                ACCESS_CONTROL_REQUEST_HEADERS,
+                "authorization, content-type",
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let allowed_methods = response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_METHODS)
+            .and_then(|methods| methods.to_str().ok())
+            .unwrap_or_default();
+
+        assert!(allowed_methods.split(", ").any(|method| method == "PATCH"));
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+const BOOKING: &str = "BEGIN:VCALENDAR\r\n\
+    VERSION:2.0\r\n\
+    BEGIN:VEVENT\r\n\
+    UID:booking-42@example.com\r\n\
+    SUMMARY:Tour\\, Rijksmuseum\r\n\
+    DTSTART;TZID=Europe/Amsterdam:20250601T100000\r\n\
+    DURATION:PT2H\r\n\
+    LOCATION:Museumstraat 1\\, Amsterdam\r\n\
+    GEO:52.36;4.885\r\n\
+    END:VEVENT\r\n\
+    END:VCALENDAR\r\n";
+
+#[actix_rt::test]
+pub async fn imported_events_update_their_items() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let auth_header = AuthHeader::new(&test_app.access_token);
+        let (address, client, auth_header) = (&address, &client, &auth_header);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Import".to_string()),
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let preview = |trip_id: Uuid| async move {
+            client
+                .post(format!(
+                    "{address}/api/v1/trips/{trip_id}/itinerary/import/preview"
+                ))
+                .header(
+                    auth_header.header_name.clone(),
+                    auth_header.header_value.clone(),
+                )
+                .header(CONTENT_TYPE, "multipart/form-data; boundary=booking")
+                .body(format!(
+                    "--booking\r\n\
+                    Content-Disposition: form-data; name=\"file\"; filename=\"booking.ics\"\r\n\
+                    Content-Type: text/calendar\r\n\r\n\
+                    {BOOKING}\r\n\
+                    --booking--\r\n"
+                ))
+                .send()
+                .await
+                .expect("Request could not be resolved.")
+                .json::<PreviewItineraryImportResponse>()
+                .await
+                .expect("Failed to parse preview_itinerary_import return value.")
+                .events
+        };
+
+        let import = |events| async {
+            client
+                .post(format!(
+                    "{address}/api/v1/trips/{}/itinerary/import",
+                    trip.id
+                ))
+                .header(
+                    auth_header.header_name.clone(),
+                    auth_header.header_value.clone(),
+                )
+                .json(&ImportItineraryBody { events })
+                .send()
+                .await
+                .expect("Request could not be resolved.")
+                .json::<ImportItineraryResponse>()
+                .await
+                .expect("Failed to parse import_itinerary return value.")
+        };
+
+        let events = preview(trip.id).await;
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Tour, Rijksmuseum");
+        assert_eq!(
+            events[0].start_time.to_rfc3339(),
+            "2025-06-01T08:00:00+00:00"
+        );
+        assert_eq!(events[0].item_id, None);
+
+        let imported = import(events).await;
+
+        assert_eq!((imported.added, imported.updated), (1, 0));
+        assert_eq!(
+            imported.itinerary[0]
+                .location
+                .as_ref()
+                .map(|l| l.address.as_str()),
+            Some("Museumstraat 1, Amsterdam")
+        );
+
+        // importing the same booking again finds the item and its location
+        let events = preview(trip.id).await;
+
+        assert_eq!(events[0].item_id, imported.itinerary[0].id);
+        assert!(
+            events[0]
+                .location
+                .as_ref()
+                .is_some_and(|l| l.location_id.is_some())
+        );
+
+        let imported = import(events).await;
+
+        assert_eq!((imported.added, imported.updated), (0, 1));
+        assert_eq!(imported.itinerary.len(), 1);
+
+        // a copy of the trip has its own items, the booking isn't matched to the copied one
+        let copy = client
+            .post(format!("{address}/api/v1/trips/{}/clone", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&CloneTripBody {
+                title: None,
+                start_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse clone_trip return value.")
+            .trip;
+
+        let events = preview(copy.id).await;
+
+        assert_eq!(events[0].item_id, None);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn trip_days_group_items_by_local_date() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let auth_header = AuthHeader::new(&test_app.access_token);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Tokyo".to_string()),
+                start_date: NaiveDate::from_ymd_opt(2025, 6, 1),
+                end_date: NaiveDate::from_ymd_opt(2025, 6, 3),
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let tokyo = json!({
+            "address": "Shibuya, Tokyo",
+            "longitude": 139.7016,
+            "latitude": 35.658,
+        });
+
+        let response = client
+            .patch(format!("{address}/api/v1/trips/{}/itinerary", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&json!({
+                "operations": [
+                    {
+                        "type": "add_item",
+                        "item": {
+                            "title": "Ramen",
+                            // the next morning in Tokyo
+                            "start_time": "2025-06-01T23:30:00Z",
+                            "location": tokyo,
+                            "cost": { "cost": "1200", "currency": "JPY" },
+                        },
+                    },
+                    {
+                        "type": "add_item",
+                        "item": {
+                            "title": "Shrine",
+                            "start_time": "2025-06-02T01:00:00Z",
+                            "location": tokyo,
+                            "cost": { "cost": "500", "currency": "JPY" },
+                        },
+                    },
+                ],
+            }))
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = client
+            .get(format!("{address}/api/v1/trips/{}/days", trip.id))
+            .header(auth_header.header_name, auth_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let schedule = response
+            .json::<GetTripDaysResponse>()
+            .await
+            .expect("Failed to parse get_trip_days return value.");
+
+        assert_eq!(schedule.timezone.as_deref(), Some("Asia/Tokyo"));
+        assert_eq!(
+            schedule
+                .days
+                .iter()
+                .map(|day| day.items.len())
+                .collect::<Vec<_>>(),
+            [0, 2, 0]
+        );
+        assert_eq!(
+            schedule.days[1].date,
+            NaiveDate::from_ymd_opt(2025, 6, 2).unwrap()
+        );
+        assert_eq!(
+            schedule.days[1]
+                .spending
+                .iter()
+                .map(|spent| (spent.currency.as_str(), spent.cost.to_string()))
+                .collect::<Vec<_>>(),
+            [("JPY", "1700.00".to_string())]
+        );
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
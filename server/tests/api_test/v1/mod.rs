@@ -3,3 +3,13 @@ pub mod trip;
 pub mod user;
 
 pub mod auth;
+
+pub mod invite;
+
+pub mod share;
+
+pub mod itinerary;
+
+pub mod calendar;
+
+pub mod replicache;
@@ -0,0 +1,986 @@
+use std::panic::AssertUnwindSafe;
+
+use chrono::Utc;
+use diesel_async::RunQueryDsl;
+use futures::FutureExt;
+use journly_server::{
+    auth::create_token,
+    config::ReplicacheConfig,
+    controllers::{
+        conflict::{GetConflictsResponse, ResolveConflictBody},
+        invite::{CreateInviteBody, CreateInviteResponse},
+        replicache::{PullRequest, PushRequest},
+        trip::{CreateTripBody, CreateTripResponse},
+        trip_plan::UpdateItineraryResponse,
+    },
+    models::user_trip::Role,
+    replicache::{Mutation, client_uuid, conflicts::ConflictWinner, gc, mutators::MUTATOR_VERSION},
+};
+use reqwest::{Client, Response, StatusCode};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::{api_test::util::AuthHeader, spawn_app};
+
+const TRIP_ID: &str = "c8381024-3f79-4a10-b5fe-06dc24e74bdc";
+
+const OTHER_USER_ID: &str = "22222222-2222-2222-2222-222222222222";
+
+fn mutation(client_id: &str, id: i32, name: &str, args: Value) -> Mutation {
+    Mutation {
+        client_id: client_id.to_string(),
+        id,
+        name: name.to_string(),
+        args,
+        timestamp: Utc::now().timestamp_millis() as f64,
+    }
+}
+
+async fn push(
+    client: &Client,
+    address: &str,
+    token: &str,
+    client_group_id: &str,
+    mutations: Vec<Mutation>,
+) -> Response {
+    let auth_header = AuthHeader::new(token);
+
+    client
+        .post(format!("{address}/api/v1/replicache/push"))
+        .header(auth_header.header_name, auth_header.header_value)
+        .json(&PushRequest {
+            push_version: 1,
+            client_group_id: client_group_id.to_string(),
+            mutations,
+            profile_id: None,
+            schema_version: Some(MUTATOR_VERSION.to_string()),
+        })
+        .send()
+        .await
+        .expect("Request could not be resolved.")
+}
+
+async fn pull(
+    client: &Client,
+    address: &str,
+    token: &str,
+    client_group_id: &str,
+    cookie: Option<Value>,
+) -> Response {
+    let auth_header = AuthHeader::new(token);
+
+    client
+        .post(format!("{address}/api/v1/replicache/pull"))
+        .header(auth_header.header_name, auth_header.header_value)
+        .json(&PullRequest {
+            pull_version: 1,
+            client_group_id: client_group_id.to_string(),
+            cookie,
+            profile_id: None,
+            schema_version: Some(MUTATOR_VERSION.to_string()),
+        })
+        .send()
+        .await
+        .expect("Request could not be resolved.")
+}
+
+/// The value a pull puts at `key`, if it puts anything there.
+fn put_value<'a>(response: &'a Value, key: &str) -> Option<&'a Value> {
+    response["patch"]
+        .as_array()?
+        .iter()
+        .find(|operation| operation["op"] == "put" && operation["key"] == key)
+        .map(|operation| &operation["value"])
+}
+
+fn deletes(response: &Value, key: &str) -> bool {
+    response["patch"].as_array().is_some_and(|patch| {
+        patch
+            .iter()
+            .any(|operation| operation["op"] == "del" && operation["key"] == key)
+    })
+}
+
+/// Adds an itinerary item with a new location to the trip, returns the location's ID.
+async fn add_location(client: &Client, address: &str, token: &str, trip_id: &str) -> Uuid {
+    let auth_header = AuthHeader::new(token);
+
+    client
+        .patch(format!("{address}/api/v1/trips/{trip_id}/itinerary"))
+        .header(auth_header.header_name, auth_header.header_value)
+        .json(&json!({
+            "operations": [{
+                "type": "add_item",
+                "item": {
+                    "id": Uuid::new_v4(),
+                    "title": "Museum",
+                    "start_time": "2025-06-01T09:00:00Z",
+                    "location": {
+                        "address": "Museumplein 6, Amsterdam",
+                        "longitude": 4.88,
+                        "latitude": 52.36,
+                    },
+                },
+            }],
+        }))
+        .send()
+        .await
+        .expect("Request could not be resolved.")
+        .json::<UpdateItineraryResponse>()
+        .await
+        .expect("Failed to parse update_itinerary return value.")
+        .itinerary[0]
+        .location
+        .as_ref()
+        .and_then(|location| location.id)
+        .expect("The item has no location.")
+}
+
+#[actix_rt::test]
+pub async fn poke_channels_need_access() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let other_token = create_token(
+            &Uuid::parse_str(OTHER_USER_ID).unwrap(),
+            &test_app.config.jwt_config.access_secret,
+            10,
+            "user",
+        );
+
+        let poke = |channel: &str, token: Option<&str>| {
+            let mut query = vec![("channel", channel.to_string())];
+            query.extend(token.map(|token| ("token", token.to_string())));
+
+            client
+                .get(format!("{address}/api/v1/replicache/poke"))
+                .query(&query)
+                .send()
+        };
+
+        let response = poke(TRIP_ID, None)
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = poke(TRIP_ID, Some("not-a-token"))
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = poke(TRIP_ID, Some(&test_app.access_token))
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response
+                .headers()
+                .get("Content-Type")
+                .and_then(|content_type| content_type.to_str().ok()),
+            Some("text/event-stream")
+        );
+
+        let response = poke(TRIP_ID, Some(&other_token))
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = poke(&format!("user/{OTHER_USER_ID}"), Some(&other_token))
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = poke(
+            &format!("user/{OTHER_USER_ID}"),
+            Some(&test_app.access_token),
+        )
+        .await
+        .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn pulls_leave_out_other_peoples_trips() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let other_token = create_token(
+            &Uuid::parse_str(OTHER_USER_ID).unwrap(),
+            &test_app.config.jwt_config.access_secret,
+            10,
+            "user",
+        );
+
+        let response = pull(
+            &client,
+            &address,
+            &test_app.access_token,
+            "owner-group",
+            None,
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to parse pull return value.");
+
+        assert!(put_value(&response, &format!("trip/{TRIP_ID}")).is_some());
+
+        let response = pull(&client, &address, &other_token, "other-group", None)
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to parse pull return value.");
+
+        assert!(put_value(&response, &format!("trip/{TRIP_ID}")).is_none());
+        assert!(response["cookie"]["trips"].get(TRIP_ID).is_none());
+
+        // nor can they pull through someone else's client group
+        let response = pull(&client, &address, &other_token, "owner-group", None).await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn pushes_into_other_peoples_trips_are_rejected() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let other_token = create_token(
+            &Uuid::parse_str(OTHER_USER_ID).unwrap(),
+            &test_app.config.jwt_config.access_secret,
+            10,
+            "user",
+        );
+
+        let response = push(
+            &client,
+            &address,
+            &other_token,
+            "other-group",
+            vec![mutation(
+                "other-client",
+                1,
+                "updateTrip",
+                json!({ "id": TRIP_ID, "name": "Hijacked" }),
+            )],
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // the mutation is used up, but the trip is untouched
+        let response = pull(&client, &address, &other_token, "other-group", None)
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to parse pull return value.");
+
+        assert_eq!(response["lastMutationIDChanges"]["other-client"], 1);
+
+        let response = pull(
+            &client,
+            &address,
+            &test_app.access_token,
+            "owner-group",
+            None,
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to parse pull return value.");
+
+        assert_eq!(
+            put_value(&response, &format!("trip/{TRIP_ID}")).map(|trip| &trip["name"]),
+            Some(&json!("foo"))
+        );
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn removed_members_lose_the_trip_on_next_pull() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let owner_header = AuthHeader::new(&test_app.access_token);
+
+        let member_id = Uuid::parse_str(OTHER_USER_ID).unwrap();
+        let member_token = create_token(
+            &member_id,
+            &test_app.config.jwt_config.access_secret,
+            10,
+            "user",
+        );
+        let member_header = AuthHeader::new(&member_token);
+
+        let invite = client
+            .post(format!("{address}/api/v1/trips/{TRIP_ID}/invites"))
+            .header(owner_header.header_name, owner_header.header_value)
+            .json(&CreateInviteBody {
+                email: None,
+                user_id: Some(member_id),
+                permission: Some(Role::Editor),
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateInviteResponse>()
+            .await
+            .expect("Failed to parse create_invite return value.")
+            .invite;
+
+        let response = client
+            .post(format!("{address}/api/v1/invites/{}/accept", invite.id))
+            .header(member_header.header_name, member_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = pull(&client, &address, &member_token, "member-group", None)
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to parse pull return value.");
+
+        assert!(put_value(&response, &format!("trip/{TRIP_ID}")).is_some());
+
+        let cookie = response["cookie"].clone();
+
+        let response = pull(
+            &client,
+            &address,
+            &test_app.access_token,
+            "owner-group",
+            None,
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to parse pull return value.");
+
+        let collaborator_id = response["patch"]
+            .as_array()
+            .and_then(|patch| {
+                patch
+                    .iter()
+                    .find(|operation| operation["value"]["userId"] == OTHER_USER_ID)
+            })
+            .map(|operation| operation["value"]["id"].clone())
+            .expect("The member is not a collaborator.");
+
+        let response = push(
+            &client,
+            &address,
+            &test_app.access_token,
+            "owner-group",
+            vec![mutation(
+                "owner-client",
+                1,
+                "deleteCollaborator",
+                json!({ "id": collaborator_id }),
+            )],
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = pull(
+            &client,
+            &address,
+            &member_token,
+            "member-group",
+            Some(cookie),
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to parse pull return value.");
+
+        assert!(deletes(&response, &format!("trip/{TRIP_ID}")));
+        assert!(response["cookie"]["trips"].get(TRIP_ID).is_none());
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn pulls_return_pushed_changes_since_the_cookie() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+        let token = &test_app.access_token;
+
+        let item_id = Uuid::new_v4();
+        let expense_id = Uuid::new_v4();
+
+        let response = pull(&client, &address, token, "owner-group", None)
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to parse pull return value.");
+
+        assert_eq!(response["patch"][0]["op"], "clear");
+
+        let cookie = response["cookie"].clone();
+
+        let mutations = vec![
+            mutation(
+                "owner-client",
+                1,
+                "createItineraryItem",
+                json!({
+                    "id": item_id,
+                    "tripId": TRIP_ID,
+                    "name": "Museum",
+                    "startDateTime": "2025-06-01T09:00:00Z",
+                }),
+            ),
+            mutation(
+                "owner-client",
+                2,
+                "createExpense",
+                json!({
+                    "id": expense_id,
+                    "tripId": TRIP_ID,
+                    "name": "Tickets",
+                    "amount": 22.5,
+                    "currency": "EUR",
+                }),
+            ),
+        ];
+
+        let response = push(&client, &address, token, "owner-group", mutations).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = pull(
+            &client,
+            &address,
+            token,
+            "owner-group",
+            Some(cookie.clone()),
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to parse pull return value.");
+
+        assert!(
+            !response["patch"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|op| op["op"] == "clear")
+        );
+        assert_eq!(
+            put_value(&response, &format!("itineraryItem/{item_id}")).map(|item| &item["name"]),
+            Some(&json!("Museum"))
+        );
+        assert_eq!(
+            put_value(&response, &format!("expense/{expense_id}"))
+                .map(|expense| &expense["amount"]),
+            Some(&json!(22.5))
+        );
+        assert!(put_value(&response, &format!("trip/{TRIP_ID}")).is_some());
+        assert_eq!(response["lastMutationIDChanges"]["owner-client"], 2);
+        assert!(response["cookie"]["order"].as_i64() > cookie["order"].as_i64());
+
+        let cookie = response["cookie"].clone();
+
+        // mutations that were already applied are skipped
+        let mutations = vec![mutation(
+            "owner-client",
+            2,
+            "createExpense",
+            json!({
+                "id": Uuid::new_v4(),
+                "tripId": TRIP_ID,
+                "amount": 5,
+                "currency": "EUR",
+            }),
+        )];
+
+        let response = push(&client, &address, token, "owner-group", mutations).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = pull(
+            &client,
+            &address,
+            token,
+            "owner-group",
+            Some(cookie.clone()),
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to parse pull return value.");
+
+        assert_eq!(response["patch"], json!([]));
+        assert_eq!(response["lastMutationIDChanges"], json!({}));
+        assert_eq!(response["cookie"], cookie);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn stale_field_updates_are_kept_for_review() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+        let token = &test_app.access_token;
+        let auth_header = AuthHeader::new(token);
+
+        let expense_id = Uuid::new_v4();
+        let created_at = Utc::now().timestamp_millis() as f64;
+
+        let create = mutation(
+            "laptop",
+            1,
+            "createExpense",
+            json!({
+                "id": expense_id,
+                "tripId": TRIP_ID,
+                "name": "Tickets",
+                "amount": 10,
+                "currency": "EUR",
+            }),
+        );
+
+        push(&client, &address, token, "laptop-group", vec![create]).await;
+
+        // the phone changes the cost after the laptop did, but gets to push first
+        let phone_edit = Mutation {
+            timestamp: created_at + 2000.0,
+            ..mutation(
+                "phone",
+                1,
+                "updateExpense",
+                json!({ "expense": { "id": expense_id, "amount": 20 } }),
+            )
+        };
+        let laptop_edit = Mutation {
+            timestamp: created_at + 1000.0,
+            ..mutation(
+                "laptop",
+                2,
+                "updateExpense",
+                json!({ "expense": { "id": expense_id, "amount": 15, "name": "Museum tickets" } }),
+            )
+        };
+
+        push(&client, &address, token, "phone-group", vec![phone_edit]).await;
+        push(&client, &address, token, "laptop-group", vec![laptop_edit]).await;
+
+        let response = pull(&client, &address, token, "laptop-group", None)
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to parse pull return value.");
+
+        // fields merge one by one, the newer cost stays while the name goes through
+        let expense = put_value(&response, &format!("expense/{expense_id}"))
+            .expect("The expense was not pulled.");
+
+        assert_eq!(expense["amount"], json!(20.0));
+        assert_eq!(expense["name"], "Museum tickets");
+
+        let conflicts = client
+            .get(format!("{address}/api/v1/trips/{TRIP_ID}/conflicts"))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<GetConflictsResponse>()
+            .await
+            .expect("Failed to parse get_conflicts return value.")
+            .conflicts;
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].entity_id, expense_id);
+        assert_eq!(conflicts[0].field.as_deref(), Some("cost"));
+        assert!(put_value(&response, &format!("conflict/{}", conflicts[0].id)).is_some());
+
+        let response = client
+            .post(format!(
+                "{address}/api/v1/trips/{TRIP_ID}/conflicts/{}/resolve",
+                conflicts[0].id
+            ))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&ResolveConflictBody {
+                winner: ConflictWinner::Proposed,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = pull(&client, &address, token, "laptop-group", None)
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to parse pull return value.");
+
+        assert_eq!(
+            put_value(&response, &format!("expense/{expense_id}"))
+                .map(|expense| &expense["amount"]),
+            Some(&json!(15.0))
+        );
+
+        let conflicts = client
+            .get(format!("{address}/api/v1/trips/{TRIP_ID}/conflicts"))
+            .header(auth_header.header_name, auth_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<GetConflictsResponse>()
+            .await
+            .expect("Failed to parse get_conflicts return value.")
+            .conflicts;
+
+        assert!(conflicts.is_empty());
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn collected_client_groups_have_to_start_over() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+        let token = &test_app.access_token;
+
+        let expense_id = Uuid::new_v4();
+
+        let mutations = vec![
+            mutation(
+                "old-tab",
+                1,
+                "createExpense",
+                json!({
+                    "id": expense_id,
+                    "tripId": TRIP_ID,
+                    "name": "Tickets",
+                    "amount": 10,
+                    "currency": "EUR",
+                }),
+            ),
+            mutation("old-tab", 2, "deleteExpense", json!({ "id": expense_id })),
+        ];
+
+        push(&client, &address, token, "old-group", mutations).await;
+
+        let response = pull(&client, &address, token, "old-group", None)
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to parse pull return value.");
+        let cookie = response["cookie"].clone();
+
+        pull(&client, &address, token, "new-group", None).await;
+
+        let mut conn = test_app.db_connection().await;
+
+        // the old tab was closed a while ago, and the expense deleted around the same time
+        diesel::sql_query(
+            "UPDATE replicache_client_group SET last_pulled_at = now() - interval '31 days' \
+             WHERE id = 'old-group'",
+        )
+        .execute(&mut conn)
+        .await
+        .expect("Could not backdate the client group");
+        diesel::sql_query(
+            "UPDATE replicache_tombstones SET deleted_at = now() - interval '31 days'",
+        )
+        .execute(&mut conn)
+        .await
+        .expect("Could not backdate the tombstones");
+
+        let (client_groups, tombstones) = gc::collect(&mut conn, &ReplicacheConfig::default())
+            .await
+            .expect("Garbage collection failed");
+
+        assert_eq!(client_groups, 1);
+        assert!(tombstones > 0);
+
+        let response = pull(&client, &address, token, "old-group", Some(cookie))
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to parse pull return value.");
+
+        assert_eq!(response, json!({ "error": "ClientStateNotFound" }));
+
+        let response = push(
+            &client,
+            &address,
+            token,
+            "old-group",
+            vec![mutation("old-tab", 3, "updateTrip", json!({}))],
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to parse push return value.");
+
+        assert_eq!(response, json!({ "error": "ClientStateNotFound" }));
+
+        let response = pull(&client, &address, token, "new-group", None)
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to parse pull return value.");
+
+        assert!(put_value(&response, &format!("trip/{TRIP_ID}")).is_some());
+        assert!(put_value(&response, &format!("expense/{expense_id}")).is_none());
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn new_trips_keep_the_client_id() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+        let token = &test_app.access_token;
+
+        // the client edits the trip before it ever pulled it
+        let mutations = vec![
+            mutation(
+                "offline-tab",
+                1,
+                "createTrip",
+                json!({ "trip": { "id": "V1StGXR8_Z5jdHi6B-myT", "name": "Japan" } }),
+            ),
+            mutation(
+                "offline-tab",
+                2,
+                "createItineraryItem",
+                json!({
+                    "id": "itinerary-item",
+                    "tripId": "V1StGXR8_Z5jdHi6B-myT",
+                    "name": "Shinkansen to Kyoto",
+                    "startDateTime": "2025-06-02T01:00:00Z",
+                }),
+            ),
+        ];
+
+        let response = push(&client, &address, token, "offline-group", mutations).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = pull(&client, &address, token, "offline-group", None)
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to parse pull return value.");
+
+        let trip_id = client_uuid("V1StGXR8_Z5jdHi6B-myT");
+        let item_id = client_uuid("itinerary-item");
+
+        assert_eq!(response["lastMutationIDChanges"]["offline-tab"], 2);
+        assert_eq!(
+            put_value(&response, &format!("trip/{trip_id}")).map(|trip| &trip["name"]),
+            Some(&json!("Japan"))
+        );
+        assert_eq!(
+            put_value(&response, &format!("itineraryItem/{item_id}")).map(|item| &item["tripId"]),
+            Some(&json!(trip_id))
+        );
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn items_only_link_locations_on_their_trip() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+        let token = &test_app.access_token;
+        let auth_header = AuthHeader::new(token);
+
+        let other_trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(auth_header.header_name, auth_header.header_value)
+            .json(&CreateTripBody {
+                title: Some("Other trip".to_string()),
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let location_id = add_location(&client, &address, token, TRIP_ID).await;
+        let foreign_location_id =
+            add_location(&client, &address, token, &other_trip.id.to_string()).await;
+
+        let mutations = vec![
+            mutation(
+                "tab",
+                1,
+                "createItineraryItem",
+                json!({
+                    "id": "linked",
+                    "tripId": TRIP_ID,
+                    "name": "Rijksmuseum",
+                    "startDateTime": "2025-06-01T13:00:00Z",
+                    "location": location_id,
+                }),
+            ),
+            mutation(
+                "tab",
+                2,
+                "createItineraryItem",
+                json!({
+                    "id": "foreign",
+                    "tripId": TRIP_ID,
+                    "name": "Somewhere else",
+                    "startDateTime": "2025-06-01T15:00:00Z",
+                    "location": foreign_location_id,
+                }),
+            ),
+            mutation(
+                "tab",
+                3,
+                "updateItineraryItem",
+                json!({
+                    "itineraryItem": { "id": "linked", "location": foreign_location_id },
+                }),
+            ),
+        ];
+
+        push(&client, &address, token, "tab-group", mutations).await;
+
+        let response = pull(&client, &address, token, "tab-group", None)
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to parse pull return value.");
+
+        assert_eq!(response["lastMutationIDChanges"]["tab"], 3);
+        assert_eq!(
+            put_value(
+                &response,
+                &format!("itineraryItem/{}", client_uuid("linked"))
+            )
+            .map(|item| &item["location"]),
+            Some(&json!(location_id))
+        );
+        assert!(
+            put_value(
+                &response,
+                &format!("itineraryItem/{}", client_uuid("foreign"))
+            )
+            .is_none()
+        );
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
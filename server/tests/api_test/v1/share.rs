@@ -0,0 +1,102 @@
+use std::panic::AssertUnwindSafe;
+
+use futures::FutureExt;
+use journly_server::controllers::{
+    share::{CreateShareLinkBody, CreateShareLinkResponse},
+    trip::{CreateTripBody, CreateTripResponse, GetTripResponse},
+};
+use reqwest::{Client, StatusCode};
+
+use crate::{api_test::util::AuthHeader, spawn_app};
+
+#[actix_rt::test]
+pub async fn revoked_share_link_stops_working() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let auth_header = AuthHeader::new(&test_app.access_token);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Shared Trip".to_string()),
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let link = client
+            .post(format!("{address}/api/v1/trips/{}/shares", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&CreateShareLinkBody {
+                expires_in_days: None,
+                include_expenses: None,
+                include_documents: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateShareLinkResponse>()
+            .await
+            .expect("Failed to parse create_share_link return value.")
+            .link;
+
+        // no authorization header, the token is all it takes
+        let shared = client
+            .get(format!("{address}/api/v1/shared/{}", link.token))
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<GetTripResponse>()
+            .await
+            .expect("Failed to parse get_shared_trip return value.")
+            .trip;
+
+        assert_eq!(shared.id, trip.id);
+        assert_eq!(shared.title, Some("Shared Trip".to_string()));
+
+        let response = client
+            .delete(format!(
+                "{address}/api/v1/trips/{}/shares/{}",
+                trip.id, link.id
+            ))
+            .header(auth_header.header_name, auth_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = client
+            .get(format!("{address}/api/v1/shared/{}", link.token))
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
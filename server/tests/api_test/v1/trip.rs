@@ -1,11 +1,25 @@
 use std::panic::AssertUnwindSafe;
 
+use chrono::NaiveDate;
 use futures::FutureExt;
-use journly_server::controllers::trip::{CreateTripBody, GetTripResponse, GetTripsResponse};
+use journly_server::{
+    auth::create_token,
+    controllers::{
+        invite::{CreateInviteBody, CreateInviteResponse},
+        trip::{
+            CloneTripBody, CreateTripBody, CreateTripResponse, GetTripResponse, GetTripsResponse,
+            TransferOwnershipBody,
+        },
+    },
+    models::user_trip::Role,
+};
 use reqwest::{Client, StatusCode};
+use uuid::Uuid;
 
 use crate::{api_test::util::AuthHeader, spawn_app};
 
+const COLLABORATOR_ID: &str = "22222222-2222-2222-2222-222222222222";
+
 #[actix_rt::test]
 pub async fn get_trips_returns_list() {
     let test_app = spawn_app().await;
@@ -170,3 +184,578 @@ pub async fn create_trip_with_works() {
         panic!("");
     }
 }
+
+#[actix_rt::test]
+pub async fn transfer_ownership_to_collaborator() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let owner_header = AuthHeader::new(&test_app.access_token);
+
+        let collaborator_id = Uuid::parse_str(COLLABORATOR_ID).unwrap();
+        let collaborator_token = create_token(
+            &collaborator_id,
+            &test_app.config.jwt_config.access_secret,
+            10,
+            "user",
+        );
+        let collaborator_header = AuthHeader::new(&collaborator_token);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                owner_header.header_name.clone(),
+                owner_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Handed Over".to_string()),
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        // not on the trip yet
+        let response = client
+            .put(format!("{address}/api/v1/trips/{}/owner", trip.id))
+            .header(
+                owner_header.header_name.clone(),
+                owner_header.header_value.clone(),
+            )
+            .json(&TransferOwnershipBody {
+                user_id: collaborator_id,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let invite = client
+            .post(format!("{address}/api/v1/trips/{}/invites", trip.id))
+            .header(
+                owner_header.header_name.clone(),
+                owner_header.header_value.clone(),
+            )
+            .json(&CreateInviteBody {
+                email: None,
+                user_id: Some(collaborator_id),
+                permission: Some(Role::Viewer),
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateInviteResponse>()
+            .await
+            .expect("Failed to parse create_invite return value.")
+            .invite;
+
+        client
+            .post(format!("{address}/api/v1/invites/{}/accept", invite.id))
+            .header(
+                collaborator_header.header_name.clone(),
+                collaborator_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        let response = client
+            .put(format!("{address}/api/v1/trips/{}/owner", trip.id))
+            .header(
+                owner_header.header_name.clone(),
+                owner_header.header_value.clone(),
+            )
+            .json(&TransferOwnershipBody {
+                user_id: collaborator_id,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let collaborators = client
+            .get(format!("{address}/api/v1/trips/{}", trip.id))
+            .header(
+                collaborator_header.header_name,
+                collaborator_header.header_value,
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<GetTripResponse>()
+            .await
+            .expect("Failed to parse get_trip return value.")
+            .trip
+            .collaborators;
+
+        for collaborator in collaborators {
+            let expected = if collaborator.id == collaborator_id {
+                Role::Owner
+            } else {
+                Role::Editor
+            };
+
+            assert_eq!(collaborator.permission, expected);
+        }
+
+        // the previous owner stays on as an editor, who can't delete the trip
+        let response = client
+            .delete(format!("{address}/api/v1/trips/{}", trip.id))
+            .header(owner_header.header_name, owner_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn clone_trip_shifts_dates() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let auth_header = AuthHeader::new(&test_app.access_token);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Template".to_string()),
+                start_date: NaiveDate::from_ymd_opt(2025, 4, 1),
+                end_date: NaiveDate::from_ymd_opt(2025, 4, 5),
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let response = client
+            .post(format!("{address}/api/v1/trips/{}/clone", trip.id))
+            .header(auth_header.header_name, auth_header.header_value)
+            .json(&CloneTripBody {
+                title: None,
+                start_date: NaiveDate::from_ymd_opt(2026, 5, 10),
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let copy = response
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse clone_trip return value.")
+            .trip;
+
+        assert_ne!(copy.id, trip.id);
+        assert_eq!(copy.title, Some("Template".to_string()));
+        assert_eq!(copy.start_date, NaiveDate::from_ymd_opt(2026, 5, 10));
+        assert_eq!(copy.end_date, NaiveDate::from_ymd_opt(2026, 5, 14));
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn deleted_trip_can_be_restored() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let auth_header = AuthHeader::new(&test_app.access_token);
+
+        let trip = client
+            .post(format!("{address}/api/v1/trips"))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .json(&CreateTripBody {
+                title: Some("Trash".to_string()),
+                start_date: None,
+                end_date: None,
+            })
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        let response = client
+            .delete(format!("{address}/api/v1/trips/{}", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let list_trip_ids = |state: &'static str| {
+            let request = client
+                .get(format!("{address}/api/v1/trips?state={state}"))
+                .header(
+                    auth_header.header_name.clone(),
+                    auth_header.header_value.clone(),
+                );
+
+            async move {
+                request
+                    .send()
+                    .await
+                    .expect("Request could not be resolved.")
+                    .json::<GetTripsResponse>()
+                    .await
+                    .expect("Failed to parse get_trips return value.")
+                    .trips
+                    .into_iter()
+                    .map(|trip| trip.id)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert!(!list_trip_ids("active").await.contains(&trip.id));
+        assert!(list_trip_ids("deleted").await.contains(&trip.id));
+
+        let response = client
+            .get(format!("{address}/api/v1/trips/{}", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = client
+            .post(format!("{address}/api/v1/trips/{}/restore", trip.id))
+            .header(
+                auth_header.header_name.clone(),
+                auth_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        assert!(list_trip_ids("active").await.contains(&trip.id));
+        assert!(!list_trip_ids("deleted").await.contains(&trip.id));
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+#[actix_rt::test]
+pub async fn get_trips_pages_through_search_results() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let auth_header = AuthHeader::new(&test_app.access_token);
+
+        for title in ["Paging Alpha", "Paging Beta", "Something else"] {
+            client
+                .post(format!("{address}/api/v1/trips"))
+                .header(
+                    auth_header.header_name.clone(),
+                    auth_header.header_value.clone(),
+                )
+                .json(&CreateTripBody {
+                    title: Some(title.to_string()),
+                    start_date: None,
+                    end_date: None,
+                })
+                .send()
+                .await
+                .expect("Request could not be resolved.");
+        }
+
+        let mut titles = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let mut url = format!("{address}/api/v1/trips?q=paging&limit=1");
+
+            if let Some(cursor) = &cursor {
+                url.push_str(&format!("&cursor={cursor}"));
+            }
+
+            let page = client
+                .get(url)
+                .header(
+                    auth_header.header_name.clone(),
+                    auth_header.header_value.clone(),
+                )
+                .send()
+                .await
+                .expect("Request could not be resolved.")
+                .json::<GetTripsResponse>()
+                .await
+                .expect("Failed to parse get_trips return value.");
+
+            assert!(page.trips.len() <= 1);
+            titles.extend(page.trips.into_iter().filter_map(|trip| trip.title));
+
+            match page.next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor),
+                None => break,
+            }
+        }
+
+        // most recently edited first
+        assert_eq!(titles, ["Paging Beta", "Paging Alpha"]);
+
+        let response = client
+            .get(format!(
+                "{address}/api/v1/trips?sort=start_date&cursor=not-a-cursor"
+            ))
+            .header(auth_header.header_name, auth_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
+
+/// Invites the user onto the trip with `role` and has them accept.
+async fn join_trip(
+    client: &Client,
+    address: &str,
+    owner_token: &str,
+    trip_id: Uuid,
+    user_id: Uuid,
+    role: Role,
+    user_token: &str,
+) {
+    let owner_header = AuthHeader::new(owner_token);
+    let user_header = AuthHeader::new(user_token);
+
+    let invite = client
+        .post(format!("{address}/api/v1/trips/{trip_id}/invites"))
+        .header(owner_header.header_name, owner_header.header_value)
+        .json(&CreateInviteBody {
+            email: None,
+            user_id: Some(user_id),
+            permission: Some(role),
+        })
+        .send()
+        .await
+        .expect("Request could not be resolved.")
+        .json::<CreateInviteResponse>()
+        .await
+        .expect("Failed to parse create_invite return value.")
+        .invite;
+
+    let response = client
+        .post(format!("{address}/api/v1/invites/{}/accept", invite.id))
+        .header(user_header.header_name, user_header.header_value)
+        .send()
+        .await
+        .expect("Request could not be resolved.");
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+pub async fn deleted_owners_hand_trips_to_their_longest_standing_editor() {
+    let test_app = spawn_app().await;
+
+    let result = AssertUnwindSafe(async {
+        let address = test_app.address.clone();
+        let client = Client::new();
+
+        let token = |id: &str| {
+            create_token(
+                &Uuid::parse_str(id).unwrap(),
+                &test_app.config.jwt_config.access_secret,
+                10,
+                "user",
+            )
+        };
+
+        let owner_token = token(COLLABORATOR_ID);
+        let owner_header = AuthHeader::new(&owner_token);
+        let editor_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+        let viewer_id = Uuid::parse_str("44444444-4444-4444-4444-444444444444").unwrap();
+        let viewer_token = token("44444444-4444-4444-4444-444444444444");
+        let viewer_header = AuthHeader::new(&viewer_token);
+
+        let create_trip = |title: &str| {
+            client
+                .post(format!("{address}/api/v1/trips"))
+                .header(
+                    owner_header.header_name.clone(),
+                    owner_header.header_value.clone(),
+                )
+                .json(&CreateTripBody {
+                    title: Some(title.to_string()),
+                    start_date: None,
+                    end_date: None,
+                })
+                .send()
+        };
+
+        let shared = create_trip("Shared")
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+        let viewed = create_trip("Viewed")
+            .await
+            .expect("Request could not be resolved.")
+            .json::<CreateTripResponse>()
+            .await
+            .expect("Failed to parse create_trip return value.")
+            .trip;
+
+        // the viewer has been on the trip longer, but only editors inherit it
+        join_trip(
+            &client,
+            &address,
+            &owner_token,
+            shared.id,
+            viewer_id,
+            Role::Viewer,
+            &viewer_token,
+        )
+        .await;
+        join_trip(
+            &client,
+            &address,
+            &owner_token,
+            shared.id,
+            editor_id,
+            Role::Editor,
+            &test_app.access_token,
+        )
+        .await;
+        join_trip(
+            &client,
+            &address,
+            &owner_token,
+            viewed.id,
+            viewer_id,
+            Role::Viewer,
+            &viewer_token,
+        )
+        .await;
+
+        let response = client
+            .delete(format!("{address}/api/v1/users/{COLLABORATOR_ID}"))
+            .header(
+                owner_header.header_name.clone(),
+                owner_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let collaborators = client
+            .get(format!("{address}/api/v1/trips/{}", shared.id))
+            .header(
+                viewer_header.header_name.clone(),
+                viewer_header.header_value.clone(),
+            )
+            .send()
+            .await
+            .expect("Request could not be resolved.")
+            .json::<GetTripResponse>()
+            .await
+            .expect("Failed to parse get_trip return value.")
+            .trip
+            .collaborators;
+
+        for collaborator in collaborators {
+            let expected = if collaborator.id == editor_id {
+                Role::Owner
+            } else {
+                Role::Viewer
+            };
+
+            assert_eq!(collaborator.permission, expected);
+        }
+
+        // without an editor there's nobody to hand the trip to
+        let response = client
+            .get(format!("{address}/api/v1/trips/{}", viewed.id))
+            .header(viewer_header.header_name, viewer_header.header_value)
+            .send()
+            .await
+            .expect("Request could not be resolved.");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    })
+    .catch_unwind()
+    .await;
+
+    test_app.cleanup().await;
+
+    if result.is_err() {
+        panic!("");
+    }
+}
@@ -6,6 +6,7 @@ use journly_server::{
     auth::create_token,
     config::{PgConfig, Server},
     db::get_connection_pool,
+    replicache::poke::Pokes,
     run,
 };
 use std::{net::TcpListener, sync::Arc};
@@ -29,14 +30,21 @@ pub async fn spawn_app() -> TestApp {
 
     config.postgres.db = db_id.clone();
 
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Bind failed.");
+    let port = listener.local_addr().unwrap().port();
+
+    // links the server hands out point back at it
+    config.base.domain_name = format!("127.0.0.1:{port}");
+
     let db_pool = get_connection_pool(&config).await;
 
-    let redis = redis::Client::open(config.redis_addr.clone()).unwrap();
+    let redis = redis::Client::open(config.redis_config.address.clone()).unwrap();
 
     let app = Arc::new(App {
         database: db_pool.clone(),
         emails: None,
         s3: None,
+        pokes: Pokes::new(redis.clone()),
         redis,
         config,
     });
@@ -52,9 +60,6 @@ pub async fn spawn_app() -> TestApp {
         panic!("");
     };
 
-    let listener = TcpListener::bind("127.0.0.1:0").expect("Bind failed.");
-    let port = listener.local_addr().unwrap().port();
-
     let server = run(listener, app).await.expect("Failed to start server");
 
     actix_rt::spawn(server);
@@ -99,6 +104,16 @@ impl TestApp {
     pub async fn cleanup(&self) {
         drop_database(&self.config.postgres.get_db_url(), &self.database_id).await;
     }
+
+    /// A connection to this test's database, for state the API can't reach.
+    pub async fn db_connection(&self) -> AsyncPgConnection {
+        let mut config = self.config.postgres.clone();
+        config.db = self.database_id.clone();
+
+        AsyncPgConnection::establish(&config.get_db_url())
+            .await
+            .expect("Failed to connect to the test database")
+    }
 }
 
 async fn configure_database(config: &PgConfig) -> String {
@@ -184,23 +199,18 @@ async fn load_fixtures(conn: &mut AsyncPgConnection) -> Result<(), diesel::resul
         );",
         "INSERT INTO user_trip (
             user_id,
-            trip_id
+            trip_id,
+            permission
         ) VALUES (
             '11111111-1111-1111-1111-111111111111',
-            'c8381024-3f79-4a10-b5fe-06dc24e74bdc'
+            'c8381024-3f79-4a10-b5fe-06dc24e74bdc',
+            'owner'
         );",
         "INSERT INTO budget_planners (
             trip_id
         ) VALUES (
             'c8381024-3f79-4a10-b5fe-06dc24e74bdc'
         )",
-        "INSERT INTO personal_budgets (
-            trip_id,
-            user_id
-        ) VALUES (
-            'c8381024-3f79-4a10-b5fe-06dc24e74bdc',
-            '11111111-1111-1111-1111-111111111111'
-        )",
     ];
 
     conn.transaction(|conn| {